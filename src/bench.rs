@@ -0,0 +1,133 @@
+use crate::connectivity::IpFamilies;
+use crate::i18n::tr;
+use crate::logging::{log_error, log_info};
+use crate::peer::Peer;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+struct PeerStats {
+    uri: String,
+    samples: Vec<u128>,
+    lost: usize,
+}
+
+// Probes the peers every `interval` until `duration` has passed and prints
+// per-peer min/avg/max latency and loss
+pub fn run(
+    uris: &[String],
+    duration: Duration,
+    interval: Duration,
+    families: &IpFamilies,
+    history_path: Option<&Path>,
+) {
+    let mut stats: Vec<PeerStats> = uris
+        .iter()
+        .map(|uri| PeerStats {
+            uri: uri.to_string(),
+            samples: Vec::new(),
+            lost: 0,
+        })
+        .collect();
+
+    let mut history = match history_path.map(crate::history::open) {
+        Some(Ok(_conn)) => Some(_conn),
+        Some(Err(e)) => {
+            log_error!("{}", tr!("Failed to open the history database ({}).", e));
+            None
+        }
+        _ => None,
+    };
+
+    let started = Instant::now();
+    let mut round: u32 = 0;
+    loop {
+        let round_started = Instant::now();
+        round += 1;
+
+        let peers: Vec<Peer> = std::thread::scope(|scope| {
+            let probes: Vec<_> = uris
+                .iter()
+                .filter_map(|uri| crate::parsing_peers::peer_from_uri(uri))
+                .map(|mut peer| {
+                    scope.spawn(move || {
+                        crate::latency::set_latency(&mut peer, families, false);
+                        peer
+                    })
+                })
+                .collect();
+            probes.into_iter().filter_map(|p| p.join().ok()).collect()
+        });
+
+        for peer in &peers {
+            if let Some(_s) = stats.iter_mut().find(|s| s.uri == peer.uri) {
+                match peer.is_alive {
+                    true => _s.samples.push(peer.latency),
+                    _ => _s.lost += 1,
+                }
+            }
+        }
+
+        if let Some(conn) = history.as_mut() {
+            if let Err(e) = crate::history::record(conn, &peers) {
+                log_error!(
+                    "{}",
+                    tr!("Failed to save the probe results to the history ({}).", e)
+                );
+            }
+        }
+
+        log_info!(
+            "{}",
+            tr!(
+                "Round {}: {} of {} peers are alive.",
+                round,
+                peers.iter().filter(|p| p.is_alive).count(),
+                uris.len()
+            )
+        );
+
+        if started.elapsed() + interval > duration {
+            break;
+        }
+        if let Some(_rest) = interval.checked_sub(round_started.elapsed()) {
+            std::thread::sleep(_rest);
+        }
+    }
+
+    print_stats(&stats);
+}
+
+fn print_stats(stats: &[PeerStats]) {
+    println!(
+        "{0:<60}|{1:<8}|{2:<8}|{3:<8}|{4:<8}",
+        tr!("URI"),
+        tr!("Min"),
+        tr!("Avg"),
+        tr!("Max"),
+        tr!("Loss")
+    );
+    println!("{0:-<96}", "-");
+    for s in stats {
+        let total = s.samples.len() + s.lost;
+        let loss = match total {
+            0 => 100,
+            _ => s.lost * 100 / total,
+        };
+        let (min, avg, max) = match s.samples.len() {
+            0 => ("-".to_string(), "-".to_string(), "-".to_string()),
+            _ => (
+                s.samples.iter().min().unwrap_or(&0).to_string(),
+                (s.samples.iter().sum::<u128>() / s.samples.len() as u128).to_string(),
+                s.samples.iter().max().unwrap_or(&0).to_string(),
+            ),
+        };
+        println!(
+            "{0:<60}|{1:<8}|{2:<8}|{3:<8}|{4:<8}",
+            crate::redact::redact(&s.uri),
+            min,
+            avg,
+            max,
+            format!("{}%", loss)
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use crate::i18n::tr;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io;
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+// '--bind': the probes and the downloads go out through this interface or
+// from this source address (a multihomed host ranking the peers of one uplink)
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+#[derive(Clone)]
+pub enum Bind {
+    Addr(IpAddr),
+    Interface(String),
+}
+
+static BIND: OnceLock<Bind> = OnceLock::new();
+
+impl Bind {
+    pub fn parse(spec: &str) -> Result<Bind, String> {
+        match spec
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+        {
+            Ok(_ip) => Ok(Bind::Addr(_ip)),
+            #[cfg(target_os = "linux")]
+            _ if !spec.is_empty() => Ok(Bind::Interface(spec.to_string())),
+            _ => Err(tr!(
+                "'{}' is not an IP address (binding to an interface is only supported on Linux).",
+                spec
+            )),
+        }
+    }
+}
+
+pub fn init(bind: Bind) {
+    let _ = BIND.set(bind);
+}
+
+// A socket for `target` that goes out as '--bind' says. An address of the
+// other IP family can't be bound, so such peers can't be probed.
+fn socket(target: &SocketAddr, kind: Type, protocol: Protocol) -> io::Result<Socket> {
+    let socket = Socket::new(Domain::for_address(*target), kind, Some(protocol))?;
+    match BIND.get() {
+        Some(Bind::Addr(_ip)) => socket.bind(&SocketAddr::new(*_ip, 0).into())?,
+        #[cfg(target_os = "linux")]
+        Some(Bind::Interface(_name)) => socket.bind_device(Some(_name.as_bytes()))?,
+        _ => {}
+    }
+    Ok(socket)
+}
+
+pub fn connect(target: &SocketAddr, timeout: Duration) -> io::Result<TcpStream> {
+    if BIND.get().is_none() {
+        return TcpStream::connect_timeout(target, timeout);
+    }
+    let socket = socket(target, Type::STREAM, Protocol::TCP)?;
+    socket.connect_timeout(&(*target).into(), timeout)?;
+    Ok(socket.into())
+}
+
+// The source address of the traffic to `target`. Connecting a UDP socket
+// sends nothing, it only makes the system pick a route and a source address.
+pub fn source_addr(target: &SocketAddr) -> io::Result<IpAddr> {
+    let socket = socket(target, Type::DGRAM, Protocol::UDP)?;
+    socket.connect(&(*target).into())?;
+    socket
+        .local_addr()?
+        .as_socket()
+        .map(|a| a.ip())
+        .ok_or_else(|| io::Error::from(io::ErrorKind::AddrNotAvailable))
+}
+
+// The HTTP client can only be bound to an address: the one the interface
+// would use is taken
+pub fn local_address() -> Option<IpAddr> {
+    match BIND.get()? {
+        Bind::Addr(_ip) => Some(*_ip),
+        Bind::Interface(_) => ["8.8.8.8:53", "[2001:4860:4860::8888]:53"]
+            .iter()
+            .filter_map(|t| t.parse::<SocketAddr>().ok())
+            .find_map(|t| source_addr(&t).ok()),
+    }
+}
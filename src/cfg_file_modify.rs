@@ -1,160 +1,591 @@
+use crate::i18n::tr;
+use crate::logging::log_error;
 use crate::peer::Peer;
+use std::collections::HashMap;
+use std::fs;
 use std::fs::File;
+use std::io;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::Path;
 
-pub fn add_peers_to_conf_new(
-    peers: &Vec<Peer>,
+const KEEP_MARKER: &str = "peers_updater:keep";
+const END_MARKER: &str = "peers_updater:end";
+
+// The hand-pinned part of the Peers section: the entry following a
+// '# peers_updater:keep' comment line, or everything up to the next
+// '# peers_updater:end' line. Pinned entries are preserved and count toward '-n'.
+#[derive(Default)]
+pub struct Pinned {
+    pub lines: Vec<String>,
+    pub uris: Vec<String>,
+}
 
-    conf_path: &PathBuf,
-    n_peers: u8,
-    always_in_p: Option<&String>,
-    ignored_peers: Option<&String>,
+// Returns the configuration text with the Peers section replaced
+pub fn add_peers_to_conf_new(
+    selected: &[&Peer],
+    extra_peers: &[String],
     cfg_txt: &str,
-) {
+) -> Option<String> {
     let mut char_vec: Vec<char> = cfg_txt.chars().collect();
-    let vec_len = char_vec.len();
 
-    let peers_start_pos = find_peers_start_pos(&char_vec, 1, vec_len);
-    let peers_end_pos = find_end_of_peers_fragment(&char_vec, peers_start_pos + 6, vec_len);
+    let (peers_start_pos, peers_end_pos) = match find_peers_block(&char_vec) {
+        Ok(_b) => _b,
+        Err(e) => {
+            log_error!(
+                "{}",
+                tr!(
+                    "Incorrect configuration file format ({}). The file was not written to.",
+                    e
+                )
+            );
+            return None;
+        }
+    };
 
-    if !(peers_start_pos < peers_end_pos) {
-        eprintln!("Incorrect configuration file format. The file was not written to.");
-        return;
-    }
+    let pinned = pinned_in_block(&char_vec[peers_start_pos..peers_end_pos]);
+    let comments = entry_comments(&char_vec[peers_start_pos..peers_end_pos]);
+    // The '#region/country' line written above a selected peer is generated
+    let comments_of = |uri: &str, generated: &str| {
+        comments
+            .get(&crate::uri_normalize::normalize(uri))
+            .into_iter()
+            .flatten()
+            .filter(|comment| comment.as_str() != generated)
+            .cloned()
+            .collect::<Vec<String>>()
+    };
+    let (selected, extra_peers) = apply_pinned(selected, extra_peers, &pinned);
 
-    let mut new_peers = String::from("Peers:\n  [");
+    // The block is formatted like the rest of the file
+    let layout = Layout::detect(&char_vec, peers_start_pos, peers_end_pos);
+    let key = match char_vec[peers_start_pos] {
+        '"' => "\"Peers\":",
+        _ => "Peers:",
+    };
+    let entry_indent = format!("{}{}", layout.indent, layout.unit);
 
-    let mut n_added: u8 = 0;
-    for peer in peers {
-        if let Some(ignored_peers_p) = ignored_peers {
-            if ignored_peers_p.contains(&peer.uri) {
-                continue;
-            }
+    let mut new_peers = match layout.same_line {
+        true => format!("{} [", key),
+        _ => format!("{}\n{}[", key, layout.indent),
+    };
+
+    if !pinned.lines.is_empty() {
+        for line in &pinned.lines {
+            new_peers.push_str(format!("\n{}{}", entry_indent, line).as_str());
         }
-        new_peers.push_str(
-            format!("\n    #{}/{}\n    {}", peer.region, peer.country, peer.uri).as_str(),
-        );
-        n_added += 1;
-        if n_added == n_peers {
-            break;
+        new_peers.push('\n');
+    }
+
+    for peer in selected {
+        let region_comment = format!("#{}/{}", peer.region, peer.country);
+        new_peers.push_str(format!("\n{}{}", entry_indent, region_comment).as_str());
+        for comment in comments_of(&peer.uri, &region_comment) {
+            new_peers.push_str(format!("\n{}{}", entry_indent, comment).as_str());
         }
+        new_peers.push_str(format!("\n{}{}", entry_indent, peer.uri).as_str());
     }
 
     //Always in
-    if let Some(always_in) = always_in_p {
-        let ai = always_in.split(" ");
-        new_peers.push_str("\n\n    #extra");
-        for ai_s in ai {
-            new_peers.push_str(format!("\n    {}", ai_s).as_str());
+    if !extra_peers.is_empty() {
+        new_peers.push_str(format!("\n\n{}#extra", entry_indent).as_str());
+        for ai_s in extra_peers {
+            for comment in comments_of(&ai_s, "") {
+                new_peers.push_str(format!("\n{}{}", entry_indent, comment).as_str());
+            }
+            new_peers.push_str(format!("\n{}{}", entry_indent, ai_s).as_str());
         }
     }
 
-    new_peers.push_str("\n  ]");
+    new_peers.push_str(format!("\n{}]", layout.indent).as_str());
+
+    // The new lines end the same way as the rest of the file
+    if uses_crlf(cfg_txt) {
+        new_peers = new_peers.replace('\n', "\r\n");
+    }
 
     char_vec.splice(peers_start_pos..peers_end_pos + 1, new_peers.chars());
 
-    if let Ok(mut f) = File::create(&conf_path) {
-        let _ = match f.write_all(char_vec.into_iter().collect::<String>().as_bytes()) {
-            Ok(_) => {}
-            Err(e) => {
-                eprintln!(
-                    "The changes could not be written to the configuration file ({}).",
-                    e
-                );
+    Some(char_vec.into_iter().collect::<String>())
+}
+
+// The formatting of the file: the indentation of the Peers key, the
+// indentation step (tabs or a number of spaces) and whether the opening
+// brackets are put on the line of the key ('Key: [') or on the next one
+struct Layout {
+    indent: String,
+    unit: String,
+    same_line: bool,
+}
+
+impl Layout {
+    // The rest of the file is what counts: the Peers block itself may have
+    // been written by an earlier run
+    fn detect(chars: &[char], block_start: usize, block_end: usize) -> Layout {
+        let line_start = chars[..block_start]
+            .iter()
+            .rposition(|c| *c == '\n')
+            .map_or(0, |p| p + 1);
+        let indent: String = chars[line_start..block_start]
+            .iter()
+            .take_while(|c| **c == ' ' || **c == '\t')
+            .collect();
+
+        let outside: String = chars[..block_start]
+            .iter()
+            .chain(chars.get(block_end + 1..).unwrap_or_default())
+            .collect();
+        let lines: Vec<&str> = outside.lines().filter(|l| !l.trim().is_empty()).collect();
+
+        let (mut tabs, mut min_spaces) = (0, usize::MAX);
+        for line in &lines {
+            match line.chars().next() {
+                Some('\t') => tabs += 1,
+                Some(' ') => {
+                    min_spaces = min_spaces.min(line.chars().take_while(|c| *c == ' ').count())
+                }
+                _ => {}
             }
+        }
+        let spaced = lines.iter().filter(|l| l.starts_with(' ')).count();
+        let unit = match (tabs > spaced, min_spaces) {
+            (true, _) => "\t".to_string(),
+            (_, usize::MAX) => "  ".to_string(),
+            (_, _n) => " ".repeat(_n),
         };
-    } else {
-        eprintln!("The changes could not be written to the configuration file.");
-    }
-}
-
-fn find_peers_start_pos(chars: &Vec<char>, from: usize, to: usize) -> usize {
-    let mut cur_pos = from;
-
-    while cur_pos <= to {
-        if let Some(cr) = chars.get(cur_pos) {
-            if *cr == '#' {
-                let _a = format!("{}", cr);
-                cur_pos += 1;
-                cur_pos =
-                    find_comment_end_and_continue(chars, &vec![10 as char], cur_pos, to, true);
-            } else if chars[cur_pos..cur_pos + 2].to_vec() == ['/', '/'] {
-                cur_pos += 2;
-                cur_pos =
-                    find_comment_end_and_continue(chars, &vec![10 as char], cur_pos, to, true);
-            } else if chars[cur_pos..cur_pos + 2].to_vec() == ['/', '*'] {
-                cur_pos += 2;
-                cur_pos = find_comment_end_and_continue(chars, &vec!['*', '/'], cur_pos, to, true);
-            } else if chars[cur_pos..cur_pos + 6] == ['P', 'e', 'e', 'r', 's', ':']
-                || chars[cur_pos..cur_pos + 8] == ['"', 'P', 'e', 'e', 'r', 's', '"', ':']
+
+        // 'Key: [' against 'Key:' followed by a line starting with a bracket
+        let (mut same, mut next) = (0, 0);
+        for (i, line) in lines.iter().enumerate() {
+            let line = line.trim();
+            if line.starts_with('#') || line.starts_with("//") {
+                continue;
+            }
+            if line.ends_with(": [") || line.ends_with(": {") || line.ends_with(":[") {
+                same += 1;
+            } else if line.ends_with(':')
+                && lines
+                    .get(i + 1)
+                    .is_some_and(|n| n.trim() == "[" || n.trim() == "{")
             {
-                return cur_pos;
+                next += 1;
             }
         }
-        cur_pos += 1;
+
+        // Without other arrays or objects the block keeps its own style
+        let same_line = match same == next {
+            true => !chars[block_start..block_end]
+                .iter()
+                .take_while(|c| **c != '[')
+                .any(|c| *c == '\n'),
+            _ => same > next,
+        };
+
+        Layout {
+            indent,
+            unit,
+            same_line,
+        }
+    }
+}
+
+// Whether most of the lines end with CRLF (files written on Windows)
+fn uses_crlf(cfg_txt: &str) -> bool {
+    let crlf = cfg_txt.matches("\r\n").count();
+    crlf > 0 && crlf * 2 >= cfg_txt.matches('\n').count()
+}
+
+// The pinned entries of the configuration's Peers section
+pub fn pinned_peers(cfg_txt: &str) -> Pinned {
+    let char_vec: Vec<char> = cfg_txt.chars().collect();
+    match find_peers_block(&char_vec) {
+        Ok((_s, _e)) => pinned_in_block(&char_vec[_s.._e]),
+        _ => Pinned::default(),
+    }
+}
+
+// The pinned peers replace the worst selected ones; the selected and extra
+// peers that are pinned already are not repeated
+pub fn apply_pinned<'a>(
+    selected: &[&'a Peer],
+    extra_peers: &[String],
+    pinned: &Pinned,
+) -> (Vec<&'a Peer>, Vec<String>) {
+    let pinned_uris: Vec<String> = pinned
+        .uris
+        .iter()
+        .map(|uri| crate::uri_normalize::normalize(uri))
+        .collect();
+    let is_pinned = |uri: &str| pinned_uris.contains(&crate::uri_normalize::normalize(uri));
+
+    let selected: Vec<&Peer> = selected
+        .iter()
+        .filter(|peer| !is_pinned(&peer.uri))
+        .take(selected.len().saturating_sub(pinned.uris.len()))
+        .copied()
+        .collect();
+    let extra_peers: Vec<String> = extra_peers
+        .iter()
+        .filter(|uri| !is_pinned(uri))
+        .cloned()
+        .collect();
+
+    (selected, extra_peers)
+}
+
+fn pinned_in_block(block: &[char]) -> Pinned {
+    let block: String = block.iter().collect();
+    let inner = match block.split_once('[') {
+        Some((_, _i)) => _i,
+        _ => return Pinned::default(),
+    };
+    let lines: Vec<&str> = inner.lines().map(|l| l.trim()).collect();
+
+    let mut pinned = Pinned::default();
+    let mut i = 0;
+    while i < lines.len() {
+        if !(is_comment(lines[i]) && lines[i].contains(KEEP_MARKER)) {
+            i += 1;
+            continue;
+        }
+
+        // A section if it is closed before the next marker, otherwise the
+        // next entry only
+        let end = lines[i + 1..]
+            .iter()
+            .position(|l| is_comment(l) && (l.contains(KEEP_MARKER) || l.contains(END_MARKER)))
+            .map(|p| i + 1 + p)
+            .filter(|e| lines[*e].contains(END_MARKER));
+        let last = match end {
+            Some(_e) => _e,
+            _ => match lines[i + 1..].iter().position(|l| entry_uri(l).is_some()) {
+                Some(_p) => i + 1 + _p,
+                _ => break,
+            },
+        };
+
+        pinned
+            .lines
+            .extend(lines[i..=last].iter().map(|l| l.to_string()));
+        pinned
+            .uris
+            .extend(lines[i + 1..=last].iter().filter_map(|l| entry_uri(l)));
+        i = last + 1;
+    }
+
+    pinned
+}
+
+// The comment lines written by the users right above the entries (without an
+// empty line in between), by the normalized URI. The '#extra' comment and the
+// markers are skipped; the '#region/country' ones are told apart by the caller.
+fn entry_comments(block: &[char]) -> HashMap<String, Vec<String>> {
+    let block: String = block.iter().collect();
+    let inner = match block.split_once('[') {
+        Some((_, _i)) => _i,
+        _ => return HashMap::new(),
+    };
+
+    let mut comments: HashMap<String, Vec<String>> = HashMap::new();
+    let mut pending: Vec<String> = Vec::new();
+    for line in inner.lines().map(|l| l.trim()) {
+        if line.is_empty() {
+            pending.clear();
+        } else if is_comment(line) {
+            if !is_generated_comment(line) {
+                pending.push(line.to_string());
+            }
+        } else if let Some(_uri) = entry_uri(line) {
+            if !pending.is_empty() {
+                comments.insert(
+                    crate::uri_normalize::normalize(&_uri),
+                    std::mem::take(&mut pending),
+                );
+            }
+        } else {
+            pending.clear();
+        }
+    }
+
+    comments
+}
+
+fn is_generated_comment(line: &str) -> bool {
+    line == "#extra" || line.contains(KEEP_MARKER) || line.contains(END_MARKER)
+}
+
+fn is_comment(line: &str) -> bool {
+    line.starts_with('#') || line.starts_with("//") || line.starts_with("/*")
+}
+
+// The URI of an array entry line (quoted or not, with an optional comma)
+fn entry_uri(line: &str) -> Option<String> {
+    if line.is_empty() || is_comment(line) {
+        return None;
+    }
+    let uri = line.trim_end_matches(',').trim().trim_matches('"');
+    match uri.contains("://") {
+        true => Some(uri.to_string()),
+        _ => None,
+    }
+}
+
+// The new file is written next to the old one and renamed over it. The
+// permissions, owner and extended attributes (the SELinux context too) are
+// copied from the original first; if that is not possible, the file is
+// rewritten in place, which keeps them as they are.
+pub fn write_config(conf_path: &Path, cfg_txt: &str) -> io::Result<()> {
+    // The target of a symlink is replaced, not the link itself
+    let target = fs::canonicalize(conf_path).unwrap_or_else(|_| conf_path.to_path_buf());
+    if let Ok(tmp) = write_replacement(&target, cfg_txt) {
+        if tmp.persist(&target).is_ok() {
+            return Ok(());
+        }
+    }
+
+    let mut f = File::create(conf_path)?;
+    f.write_all(cfg_txt.as_bytes())
+}
+
+fn write_replacement(conf_path: &Path, cfg_txt: &str) -> io::Result<tempfile::NamedTempFile> {
+    let dir = match conf_path.parent() {
+        Some(_d) if !_d.as_os_str().is_empty() => _d,
+        _ => Path::new("."),
+    };
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    tmp.write_all(cfg_txt.as_bytes())?;
+    tmp.as_file().sync_all()?;
+
+    let metadata = fs::metadata(conf_path)?;
+    fs::set_permissions(tmp.path(), metadata.permissions())?;
+    copy_ownership_and_xattrs(conf_path, tmp.path(), &metadata)?;
+
+    Ok(tmp)
+}
+
+#[cfg(unix)]
+fn copy_ownership_and_xattrs(from: &Path, to: &Path, metadata: &fs::Metadata) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    std::os::unix::fs::chown(to, Some(metadata.uid()), Some(metadata.gid()))?;
+
+    if !xattr::SUPPORTED_PLATFORM {
+        return Ok(());
+    }
+    for name in xattr::list(from)? {
+        if let Some(value) = xattr::get(from, &name)? {
+            xattr::set(to, &name, &value)?;
+        }
     }
 
-    cur_pos
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn copy_ownership_and_xattrs(_: &Path, _: &Path, _: &fs::Metadata) -> io::Result<()> {
+    Ok(())
 }
 
-fn find_comment_end_and_continue(
-    chars: &Vec<char>,
-    symbols: &Vec<char>,
-    from: usize,
-    to: usize,
-    find_start: bool,
-) -> usize {
-    let mut cur_pos = from;
-    let symbols_len = symbols.len();
+// A scanner of the HJSON text that skips the comments ('#', '//', '/* */') and
+// the strings (quoted, '''multiline''' and quoteless), so brackets or 'Peers:'
+// inside them don't count
+struct Scanner<'a> {
+    chars: &'a [char],
+    pos: usize,
+}
 
-    while cur_pos <= to {
-        if chars[cur_pos..cur_pos + symbols_len].to_vec() == *symbols {
-            if find_start {
-                cur_pos += symbols_len;
-                return cur_pos;
+impl Scanner<'_> {
+    fn starts_with(&self, text: &str) -> bool {
+        text.chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn line(&self, pos: usize) -> usize {
+        self.chars[..pos.min(self.chars.len())]
+            .iter()
+            .filter(|c| **c == '\n')
+            .count()
+            + 1
+    }
+
+    fn skip_to_line_end(&mut self) {
+        while self.pos < self.chars.len() && self.chars[self.pos] != '\n' {
+            self.pos += 1;
+        }
+    }
+
+    // Skips the whitespace, commas, comments and the BOM before the next token
+    fn skip_blank(&mut self) -> Result<(), String> {
+        while let Some(c) = self.chars.get(self.pos) {
+            if c.is_whitespace() || *c == ',' || (*c == '\u{feff}' && self.pos == 0) {
+                self.pos += 1;
+            } else if *c == '#' || self.starts_with("//") {
+                self.skip_to_line_end();
+            } else if self.starts_with("/*") {
+                let start = self.pos;
+                self.pos += 2;
+                while !self.starts_with("*/") {
+                    if self.pos >= self.chars.len() {
+                        return Err(tr!("an unclosed comment in line {}", self.line(start)));
+                    }
+                    self.pos += 1;
+                }
+                self.pos += 2;
             } else {
-                return cur_pos;
+                break;
             }
         }
-        cur_pos += 1;
+        Ok(())
     }
 
-    cur_pos
+    // Reads the string at the current position and returns its text. A
+    // quoteless key ends before ':', a quoteless value at the end of the line
+    // unless it is a JSON number or literal ('Key: 1, Other: 2' on one line).
+    fn read_string(&mut self, is_key: bool) -> Result<String, String> {
+        let start = self.pos;
+        let unclosed = |s: &Self| tr!("an unclosed string in line {}", s.line(start));
+
+        if self.starts_with("'''") {
+            self.pos += 3;
+            while !self.starts_with("'''") {
+                if self.pos >= self.chars.len() {
+                    return Err(unclosed(self));
+                }
+                self.pos += 1;
+            }
+            self.pos += 3;
+            return Ok(self.chars[start + 3..self.pos - 3].iter().collect());
+        }
+
+        let quote = self.chars[self.pos];
+        if quote == '"' || quote == '\'' {
+            let mut text = String::new();
+            self.pos += 1;
+            loop {
+                match self.chars.get(self.pos) {
+                    Some('\\') => {
+                        if let Some(_c) = self.chars.get(self.pos + 1) {
+                            text.push(*_c);
+                        }
+                        self.pos += 2;
+                    }
+                    Some(_c) if *_c == quote => break,
+                    Some('\n') | None => return Err(unclosed(self)),
+                    Some(_c) => {
+                        text.push(*_c);
+                        self.pos += 1;
+                    }
+                }
+            }
+            self.pos += 1;
+            return Ok(text);
+        }
+
+        let word_end = |stop: &str| {
+            let mut end = start;
+            while end < self.chars.len()
+                && !self.chars[end].is_whitespace()
+                && !stop.contains(self.chars[end])
+            {
+                end += 1;
+            }
+            end
+        };
+        if is_key {
+            self.pos = word_end(":{}[],");
+            return Ok(self.chars[start..self.pos].iter().collect());
+        }
+
+        let end = word_end(",]}");
+        let word: String = self.chars[start..end].iter().collect();
+        if word.parse::<f64>().is_ok() || ["true", "false", "null"].contains(&word.as_str()) {
+            self.pos = end;
+            return Ok(word);
+        }
+        self.skip_to_line_end();
+        let text: String = self.chars[start..self.pos].iter().collect();
+        Ok(text.trim().to_string())
+    }
 }
 
-fn find_end_of_peers_fragment(chars: &Vec<char>, from: usize, to: usize) -> usize {
-    let mut cur_pos = from;
+// The positions of the beginning of the 'Peers' key of the root object and of
+// the closing bracket of its array
+fn find_peers_block(chars: &[char]) -> Result<(usize, usize), String> {
+    let mut scanner = Scanner { chars, pos: 0 };
+    // The open brackets
+    let mut stack: Vec<char> = Vec::new();
+    // HJSON allows the root object without braces
+    let mut braceless_root: Option<bool> = None;
+    let mut after_colon = false;
+    // The Peers key whose value comes next, then the depth of its array
+    let mut peers_key: Option<usize> = None;
+    let mut peers_array: Option<(usize, usize)> = None;
 
-    let mut open_count: u8 = 0;
-    let mut close_count: u8 = 0;
+    loop {
+        scanner.skip_blank()?;
+        let pos = scanner.pos;
+        let c = match chars.get(pos) {
+            Some(_c) => *_c,
+            _ => break,
+        };
+        let braceless = *braceless_root.get_or_insert(c != '{');
+        let in_object = match stack.last() {
+            Some(_b) => *_b == '{',
+            _ => braceless,
+        };
+        let at_root = stack.len() == usize::from(!braceless);
 
-    while cur_pos <= to {
-        if let Some(cr) = chars.get(cur_pos) {
-            let cr_ = *cr;
-            if cr_ == '#' {
-                let _a = format!("{}", cr);
-                cur_pos += 1;
-                cur_pos =
-                    find_comment_end_and_continue(chars, &vec![10 as char], cur_pos, to, false);
-            } else if cr_ == '[' {
-                open_count += 1;
-            } else if cr_ == ']' {
-                close_count += 1;
-                if open_count > 0 && open_count == close_count {
-                    return cur_pos;
+        match c {
+            '{' | '[' => {
+                if let Some(_key) = peers_key.take() {
+                    if c != '[' {
+                        return Err(tr!("'Peers' is not an array"));
+                    }
+                    peers_array = Some((_key, stack.len()));
+                }
+                stack.push(c);
+                after_colon = false;
+                scanner.pos += 1;
+            }
+            '}' | ']' => {
+                match stack.pop() {
+                    Some(_o) if (_o == '{') == (c == '}') => {}
+                    _ => return Err(tr!("an unexpected '{}' in line {}", c, scanner.line(pos))),
+                }
+                if let Some((_key, _depth)) = peers_array {
+                    if stack.len() == _depth {
+                        return Ok((_key, pos));
+                    }
+                }
+                after_colon = false;
+                scanner.pos += 1;
+            }
+            ':' => return Err(tr!("an unexpected '{}' in line {}", c, scanner.line(pos))),
+            _ => {
+                let is_key = in_object && !after_colon;
+                let text = scanner.read_string(is_key)?;
+                if is_key {
+                    scanner.skip_blank()?;
+                    if chars.get(scanner.pos) != Some(&':') {
+                        return Err(tr!("no ':' after '{}' in line {}", text, scanner.line(pos)));
+                    }
+                    scanner.pos += 1;
+                    after_colon = true;
+                    if at_root && text == "Peers" && peers_array.is_none() {
+                        peers_key = Some(pos);
+                    }
+                } else {
+                    if peers_key.take().is_some() {
+                        return Err(tr!("'Peers' is not an array"));
+                    }
+                    after_colon = false;
                 }
-            } else if chars[cur_pos..cur_pos + 2].to_vec() == ['/', '/'] {
-                cur_pos += 2;
-                cur_pos =
-                    find_comment_end_and_continue(chars, &vec![10 as char], cur_pos, to, false);
-            } else if chars[cur_pos..cur_pos + 2].to_vec() == ['/', '*'] {
-                cur_pos += 2;
-                cur_pos = find_comment_end_and_continue(chars, &vec!['*', '/'], cur_pos, to, false);
             }
         }
-        cur_pos += 1;
     }
 
-    cur_pos
+    match peers_array {
+        Some(_) => Err(tr!("the Peers array is not closed")),
+        _ => Err(tr!("no Peers section")),
+    }
 }
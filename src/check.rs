@@ -0,0 +1,114 @@
+use crate::cfg_file_modify::Pinned;
+use crate::config_io::ConfigAccess;
+use crate::i18n::tr;
+use crate::logging::log_error;
+use crate::peer::Peer;
+use std::path::PathBuf;
+
+// Compares the peers in the configuration files with the new selection without
+// changing anything. Prints a JSON description of the pending changes and
+// returns the exit code.
+pub fn check_configs(
+    conf_paths: &[PathBuf],
+    access: &ConfigAccess,
+    use_uci: bool,
+    selected: &[&Peer],
+    extra_peers: &[String],
+) -> i32 {
+    let mut changed = false;
+    let mut failed = false;
+    let mut configs: Vec<serde_json::Value> = Vec::new();
+
+    for conf_path in conf_paths {
+        let (current_peers, pinned): (Vec<String>, Pinned) = if use_uci {
+            (crate::uci_config::get_peers(conf_path), Pinned::default())
+        } else {
+            match access.read(conf_path).map(|t| {
+                crate::parse_config::get_peers(&t)
+                    .map(|p| (p, crate::cfg_file_modify::pinned_peers(&t)))
+            }) {
+                Ok(Ok(_p)) => _p,
+                Ok(Err(e)) => {
+                    log_error!(
+                        "{}",
+                        tr!(
+                            "Can't parse the config file {} ({})!",
+                            conf_path.display(),
+                            e
+                        )
+                    );
+                    failed = true;
+                    continue;
+                }
+                Err(e) => {
+                    log_error!(
+                        "{}",
+                        tr!(
+                            "The configuration file {} cannot be read ({}).",
+                            conf_path.display(),
+                            e
+                        )
+                    );
+                    failed = true;
+                    continue;
+                }
+            }
+        };
+
+        let (selected, extra_peers) =
+            crate::cfg_file_modify::apply_pinned(selected, extra_peers, &pinned);
+        let new_peers: Vec<String> = pinned
+            .uris
+            .iter()
+            .cloned()
+            .chain(selected.iter().map(|peer| peer.uri.to_string()))
+            .chain(extra_peers)
+            .collect();
+
+        let add: Vec<&String> = new_peers
+            .iter()
+            .filter(|p| !contains(&current_peers, p))
+            .collect();
+        let remove: Vec<&String> = current_peers
+            .iter()
+            .filter(|p| !contains(&new_peers, p))
+            .collect();
+        let config_changed = !add.is_empty() || !remove.is_empty();
+        changed |= config_changed;
+
+        configs.push(serde_json::json!({
+            "path": conf_path,
+            "changed": config_changed,
+            "current": redacted(&current_peers),
+            "new": redacted(&new_peers),
+            "add": redacted(&add),
+            "remove": redacted(&remove),
+        }));
+    }
+
+    let report = serde_json::json!({
+        "changed": changed,
+        "configs": configs,
+    });
+    println!("{}", report);
+
+    if failed {
+        1
+    } else if changed {
+        crate::defaults::EXIT_CHANGES_PENDING
+    } else {
+        0
+    }
+}
+
+fn contains(list: &[String], uri: &str) -> bool {
+    let uri = crate::uri_normalize::normalize(uri);
+    list.iter()
+        .any(|u| crate::uri_normalize::normalize(u) == uri)
+}
+
+fn redacted(list: &[impl AsRef<str>]) -> Vec<String> {
+    list.iter()
+        .map(|uri| crate::redact::redact(uri.as_ref()))
+        .collect()
+}
@@ -2,13 +2,18 @@ use clap::{arg, value_parser, Arg, ArgAction};
 use std::path::PathBuf;
 
 pub fn build_args() -> clap::ArgMatches {
+    build_command().get_matches()
+}
+
+pub fn build_command() -> clap::Command {
     clap::Command::new("Yggdrasil peers updater")
     .version(crate::version::APP_VERSION)
+    .args_override_self(true)
     .author("YggUser (https://matrix.to/#/@ygguser:matrix.org)")
     .about("The Yggdrasil peers updater automatically updates the peers in the Yggdrasil configuration file and/or calls addPeer/removePeer from the Yggdrasil Admin API.{n}Source code: https://github.com/ygguser/peers_updater")
     .arg(
         arg!(
-            -p --print "Print the peers sorted by latency. When using this parameter, all other parameters will be ignored."
+            -p --print "Print the peers sorted by latency instead of changing anything ('-u', '-a' and '-r' are ignored). The options of the peer list, the probing and the output (e.g. '--format', '--group-by', '--emit-peers', '--report-json') still apply."
         )
         .required(false)
         .action(ArgAction::SetTrue)
@@ -17,11 +22,27 @@ pub fn build_args() -> clap::ArgMatches {
         Arg::new("config")
        .short('c')
        .long("config")
-       .default_value(crate::defaults::DEF_CFG_PATH)
+       .default_value(crate::defaults::cfg_path())
        .value_name("FILE")
-       .help("The path to the Yggdrasil configuration file")
+       .help("The path to the Yggdrasil configuration file (can be specified several times)")
        .required(false)
+       .global(true)
+       .action(ArgAction::Append)
        .value_parser(value_parser!(PathBuf)))
+    .arg(
+        arg!(
+            --settings <FILE> "A settings file (HJSON) with the options by their long names and the named profiles (see README)"
+        )
+        .required(false)
+        .value_parser(value_parser!(PathBuf))
+    )
+    .arg(
+        arg!(
+            --profile <NAME> "The profile from the settings file to use (by default the one matching the connected Wi-Fi network or the default gateway, or 'default')"
+        )
+        .required(false)
+        .requires("settings")
+    )
     .arg(
         arg!(
             -u --update_cfg "Make changes to the Yggdrasil configuration file. If not specified, no changes will be made to the file."
@@ -34,13 +55,101 @@ pub fn build_args() -> clap::ArgMatches {
         )
         .required(false)
     )
+    .arg(
+        Arg::new("api_backend")
+       .long("api-backend")
+       .value_name("BACKEND")
+       .help("How to reach the admin API: 'socket' (directly) or 'yggdrasilctl[:/path/to/yggdrasilctl]' (by running it)")
+       .required(false)
+       .default_value("socket"))
+    .arg(
+        Arg::new("maintain")
+       .long("maintain")
+       .value_name("VALUE")
+       .help("With '-a': don't remove anything, only add as many of the selected peers as needed to have this number of established sessions")
+       .required(false)
+       .requires("api")
+       .value_parser(value_parser!(usize)))
+    .arg(
+        Arg::new("api_settle")
+       .long("api-settle")
+       .value_name("DURATION")
+       .help("With '-a': the old peers are removed only after a few of the new sessions are up; how long to wait for them before keeping the old peers")
+       .required(false)
+       .requires("api")
+       .default_value("30s")
+       .value_parser(crate::duration::parse_duration))
+    .arg(
+        Arg::new("max_peers")
+       .long("max-peers")
+       .value_name("VALUE")
+       .help("With '-a': if the node has more peers than this after the update, remove the ones that are not selected and then the slowest ones")
+       .required(false)
+       .requires("api")
+       .value_parser(value_parser!(usize)))
     .arg(
         arg!(
-            -n --number <VALUE> "The number of peers to add (excluding extra ones)"
+            -n --number <VALUE> "The number of peers to add (excluding extra ones), or a share of the alive peers (e.g. 10%)"
         )
         .required(false)
         .default_value("3")        
     )
+    .arg(
+        arg!(
+            --sni <NAME> "The SNI name to add ('?sni=...') to the URIs of the selected TLS peers; a space-separated string of 'host=name' rules sets it for the individual hosts"
+        )
+        .required(false))
+    .arg(
+        Arg::new("per_region")
+       .long("per-region")
+       .value_name("VALUE")
+       .help("Take this number of the best peers from every region (that passes the filters) instead of the '-n' best peers overall")
+       .required(false)
+       .conflicts_with("number")
+       .value_parser(value_parser!(usize)))
+    .arg(
+        Arg::new("keep_within")
+       .long("keep-within")
+       .value_name("PERCENT")
+       .help("Keep an alive peer that is already in the configuration instead of a new one if it is at most this much slower (e.g. 15)")
+       .required(false)
+       .value_parser(value_parser!(u16)))
+    .arg(
+        Arg::new("min_peers")
+       .long("min-peers")
+       .value_name("VALUE")
+       .help("Leave the configuration unchanged if fewer alive peers than this are found (no more than '-n' are required)")
+       .required(false)
+       .default_value("1")
+       .value_parser(value_parser!(usize)))
+    .arg(
+        Arg::new("only_if_stale")
+       .long("only-if-stale")
+       .help("First check the current peers (the sessions with '-a', otherwise by probing the configured ones) and do nothing if at least '-n' of them are alive and not slower than '--stale-latency'")
+       .required(false)
+       .action(ArgAction::SetTrue))
+    .arg(
+        Arg::new("stale_latency")
+       .long("stale-latency")
+       .value_name("MS")
+       .help("With '--only-if-stale', a current peer slower than this is not counted as healthy")
+       .required(false)
+       .requires("only_if_stale")
+       .default_value("500")
+       .value_parser(value_parser!(u64)))
+    .arg(
+        Arg::new("count_multicast")
+       .long("count-multicast")
+       .help("Count the healthy peers the node found on the LAN through multicast toward '-n' (asked through the admin API), so fewer public peers are added (but no fewer than '--min-peers')")
+       .required(false)
+       .action(ArgAction::SetTrue))
+    .arg(
+        Arg::new("fail_if_fewer_than")
+       .long("fail-if-fewer-than")
+       .value_name("VALUE")
+       .help("Exit with the code 3 without changing anything if fewer alive peers than this pass the filters")
+       .required(false)
+       .value_parser(value_parser!(u16)))
     .arg(
         arg!(
             -e --extra <VALUE> "A space-separated string with the URIs of the peers that should always be in the configuration"
@@ -53,9 +162,454 @@ pub fn build_args() -> clap::ArgMatches {
         .required(false))
     .arg(
         arg!(
-            -r --restart "Restart the Yggdrasil (systemd or windows) service"
+            --country <VALUE> "A space-separated string with the countries (names or ISO codes, e.g. \"germany UK\") to take the peers from"
+        )
+        .required(false))
+    .arg(
+        Arg::new("history")
+       .long("history")
+       .value_name("FILE")
+       .help("The path to the SQLite database where the results of every probe are recorded")
+       .required(false)
+       .global(true)
+       .value_parser(value_parser!(PathBuf)))
+    .arg(
+        Arg::new("report_dead")
+       .long("report-dead")
+       .value_name("FILE")
+       .help("Write the peers that were dead in each of the last '--dead-runs' runs recorded in '--history' to this file, as a Markdown list by the public-peers file (for an issue or a pull request upstream)")
+       .required(false)
+       .requires("history")
+       .value_parser(value_parser!(PathBuf)))
+    .arg(
+        Arg::new("dead_runs")
+       .long("dead-runs")
+       .value_name("VALUE")
+       .help("The number of the consecutive runs a peer must be dead in for '--report-dead'")
+       .required(false)
+       .requires("report_dead")
+       .default_value("3")
+       .value_parser(value_parser!(u16).range(1..)))
+    .arg(
+        Arg::new("connectivity_check")
+       .long("connectivity-check")
+       .value_name("URL")
+       .help(format!("Before doing anything, check that this URL ({} unless given) answers with '204 No Content' (no internet access or a captive portal otherwise); off by default", crate::defaults::DEF_CONNECTIVITY_CHECK_URL))
+       .required(false)
+       .num_args(0..=1)
+       .default_missing_value(crate::defaults::DEF_CONNECTIVITY_CHECK_URL))
+    .arg(
+        Arg::new("no_connectivity_check")
+       .long("no-connectivity-check")
+       .help("Skip the connectivity check even if '--connectivity-check' is given (e.g. in the settings file)")
+       .required(false)
+       .action(ArgAction::SetTrue))
+    .arg(
+        Arg::new("all_families")
+       .long("all-families")
+       .help("Probe the peers of both IP families even if there is no global IPv4 or IPv6 route")
+       .required(false)
+       .global(true)
+       .action(ArgAction::SetTrue))
+    .arg(
+        Arg::new("verbose")
+       .short('v')
+       .long("verbose")
+       .help("Print more details (-vv also shows the DNS, TCP connect and TLS handshake time of every probe)")
+       .required(false)
+       .action(ArgAction::Count))
+    .arg(
+        Arg::new("show_secrets")
+       .long("show-secrets")
+       .help("Don't mask the passwords and other secrets of the peer URIs in the output (they are always written to the configuration, the Nix fragment, '--emit-peers' and '--serve-peers' as they are)")
+       .required(false)
+       .global(true)
+       .action(ArgAction::SetTrue))
+    .arg(
+        Arg::new("prefer_reachable")
+       .long("prefer-reachable")
+       .help("First probe a few peers of every protocol and port kind (443, below 1024, high) and skip the rest of a kind none of them answered in, then put the peers of the kinds that mostly fail from this network after the others, even if they answered")
+       .required(false)
+       .action(ArgAction::SetTrue))
+    .arg(
+        Arg::new("probe_spacing")
+       .long("probe-spacing")
+       .value_name("MS")
+       .help("The delay in milliseconds between starting two consecutive probes")
+       .required(false)
+       .value_parser(value_parser!(u64)))
+    .arg(
+        Arg::new("sample")
+       .long("sample")
+       .value_name("N|PERCENT")
+       .help("Probe only a random part of the peers that pass the filters (e.g. 50 or 20%), and the next part while fewer than '-n' (or '--min-peers') of them are alive; with '--seed' the part is the same every time")
+       .required(false)
+       .value_parser(crate::select::PeerCount::parse))
+    .arg(
+        Arg::new("probe_all")
+       .long("probe-all")
+       .help("Probe all the peers even if '--sample' is given (e.g. in the settings file)")
+       .required(false)
+       .action(ArgAction::SetTrue))
+    .arg(
+        Arg::new("bind")
+       .long("bind")
+       .value_name("IFACE|IP")
+       .help("Probe the peers and download the list through this network interface (Linux) or from this source address, e.g. to rank the peers as seen from a secondary uplink")
+       .required(false)
+       .global(true)
+       .value_parser(crate::bind::Bind::parse))
+    .arg(
+        Arg::new("probe_rate")
+       .long("probe-rate")
+       .value_name("PER_SEC")
+       .help("Open no more than this number of new connections per second while probing (a burst of hundreds of connections may look like a port scan to routers and IDS)")
+       .required(false)
+       .value_parser(value_parser!(u32).range(1..)))
+    .arg(
+        arg!(
+            --seed <VALUE> "Order the peers of equal latency by this seed instead of the order they are listed in, so that the same seed and probe results always give the same selection"
+        )
+        .required(false)
+    )
+    .arg(
+        Arg::new("score_cmd")
+       .long("score-cmd")
+       .value_name("PATH")
+       .help("A program that gets the probed peers as a JSON array on stdin and answers with '[{\"uri\": ..., \"score\": N}]' (lower is better, replaces the latency) or '[{\"uri\": ..., \"reject\": true}]'")
+       .required(false))
+    .arg(
+        arg!(
+            --format <FORMAT> "The output format: 'table' prints all alive peers, 'nix' prints the selected peers as a NixOS configuration fragment, 'template' prints every selected peer with '--template' (implies '-p')"
+        )
+        .required(false)
+        .value_parser(["table", "nix", "template"])
+        .default_value("table"))
+    .arg(
+        arg!(
+            --stream "With '-p', print every alive peer as soon as its probe finishes instead of the sorted table at the end"
+        )
+        .required(false)
+        .requires("print"))
+    .arg(
+        Arg::new("group_by")
+       .long("group-by")
+       .value_name("KEY")
+       .help("With '-p', print the peers in sections by 'region', 'country' or 'protocol' with the counts and the best latency of every section")
+       .required(false)
+       .requires("print")
+       .conflicts_with("stream")
+       .value_parser(["region", "country", "protocol"]))
+    .arg(
+        arg!(
+            --template <TEMPLATE> "The line printed for every selected peer with '--format template'. Placeholders: {uri}, {proto}, {addr}, {port}, {region}, {country}, {country_code}, {latency}"
+        )
+        .required(false)
+        .conflicts_with("template_file"))
+    .arg(
+        Arg::new("template_file")
+       .long("template-file")
+       .value_name("FILE")
+       .help("Read the template for '--format template' from the file")
+       .required(false)
+       .value_parser(value_parser!(PathBuf)))
+    .arg(
+        Arg::new("emit_peers")
+       .long("emit-peers")
+       .value_name("FILE")
+       .help("Write the selected and extra peers with their metadata to a JSON file (whether or not the configuration is updated)")
+       .required(false)
+       .value_parser(value_parser!(PathBuf)))
+    .arg(
+        Arg::new("log_target")
+       .long("log-target")
+       .value_name("TARGET")
+       .help("Where to write the messages: 'stderr', 'syslog', 'journald' (with the PEER= and LATENCY= fields of the selected peers) or, on Windows, 'eventlog'")
+       .required(false)
+       .global(true)
+       .default_value("stderr"))
+    .arg(
+        Arg::new("report_json")
+       .long("report-json")
+       .value_name("FILE")
+       .help("Write a JSON report of the run (options, probe results, selection, changes made, errors) to the file ('-' for the standard output)")
+       .required(false))
+    .arg(
+        Arg::new("report_html")
+       .long("report-html")
+       .value_name("FILE")
+       .help("Write a self-contained HTML page with the options, the sorted peers and their latency bars to the file")
+       .required(false)
+       .value_parser(value_parser!(PathBuf)))
+    .arg(
+        Arg::new("config_format")
+       .long("config-format")
+       .value_name("FORMAT")
+       .help(format!("The format of the Yggdrasil configuration: 'hjson' or 'uci' (OpenWrt, the default path is {})", crate::defaults::DEF_UCI_CFG_PATH))
+       .required(false)
+       .value_parser(["hjson", "uci"])
+       .default_value("hjson"))
+    .arg(
+        arg!(
+            --remote <DEST> "Read and write the configuration file (and restart the service) on a remote machine over SSH: user@host[:/path/to/yggdrasil.conf]. The probing is done locally."
+        )
+        .required(false))
+    .arg(
+        arg!(
+            --check "Make no changes: print a JSON description of the changes that would be made to the configuration files and exit with code 0 if they are up to date or 2 otherwise"
+        )
+        .required(false))
+    .arg(
+        arg!(
+            --lang <LANG> "The language of the messages (by default it is taken from LANG)"
         )
         .required(false)
+        .global(true)
+        .value_parser(["en", "ru"]))
+    .arg(
+        arg!(
+            --escalate <CMD> "Run unprivileged and use this command (sudo, doas, pkexec...) only to write the configuration file and restart the service"
+        )
+        .required(false))
+    .arg(
+        Arg::new("service_name")
+       .long("service-name")
+       .value_name("NAME")
+       .help("The name of the Yggdrasil service restarted by '-r' (the systemd unit, e.g. yggdrasil@main.service, the Windows service or the OpenWrt init script)")
+       .required(false)
+       .global(true)
+       .default_value("yggdrasil"))
+    .arg(
+        arg!(
+            --daemon "Keep running and repeat the update every '--interval'. SIGHUP or SIGUSR1 start an update immediately, SIGTERM stops probing and exits without touching the configuration."
+        )
+        .required(false))
+    .arg(
+        arg!(
+            --interval <DURATION> "The interval between the updates in daemon mode (e.g. 6h, 1d)"
+        )
+        .required(false)
+        .default_value("1d")
+        .value_parser(crate::duration::parse_duration))
+    .arg(
+        Arg::new("watch_routes")
+       .long("watch-routes")
+       .help("Linux: in daemon mode, also start an update when the default route or a global address changes (after the changes settle for 10s)")
+       .required(false)
+       .requires("daemon")
+       .action(ArgAction::SetTrue))
+    .arg(
+        Arg::new("supervise")
+       .long("supervise")
+       .help("With '--daemon' and '-a': between the updates check the sessions of the node and replace a selected peer that stays down or slower than '--supervise-latency' for '--supervise-grace' with the next best peer of the last update")
+       .required(false)
+       .requires("daemon")
+       .requires("api")
+       .conflicts_with("maintain")
+       .action(ArgAction::SetTrue))
+    .arg(
+        Arg::new("supervise_latency")
+       .long("supervise-latency")
+       .value_name("MS")
+       .help("With '--supervise', a session slower than this is degraded")
+       .required(false)
+       .requires("supervise")
+       .default_value("500")
+       .value_parser(value_parser!(u64)))
+    .arg(
+        Arg::new("supervise_grace")
+       .long("supervise-grace")
+       .value_name("DURATION")
+       .help("With '--supervise', how long a peer may stay down or degraded before it is replaced (e.g. 2m)")
+       .required(false)
+       .requires("supervise")
+       .default_value("2m")
+       .value_parser(crate::duration::parse_duration))
+    .arg(
+        Arg::new("status_listen")
+       .long("status-listen")
+       .value_name("ADDR")
+       .help("In daemon mode, serve a JSON status (the last run time, the selected peers with their latencies and the errors) over HTTP on this address, e.g. 127.0.0.1:9876")
+       .required(false)
+       .requires("daemon"))
+    .arg(
+        Arg::new("state")
+       .long("state")
+       .value_name("FILE")
+       .help("The file where the time of the last successful update is kept")
+       .required(false)
+       .default_value(crate::defaults::state_path())
+       .value_parser(value_parser!(PathBuf)))
+    .arg(
+        Arg::new("cache")
+       .long("cache")
+       .value_name("FILE")
+       .help("Where the last downloaded peer list is kept; it is used when the download fails (without it the current peers are probed again)")
+       .required(false)
+       .default_value(crate::defaults::cache_path())
+       .value_parser(value_parser!(PathBuf)))
+    .arg(
+        Arg::new("github_token")
+       .long("github-token")
+       .value_name("TOKEN")
+       .help("A GitHub token raising the API rate limit (also taken from GITHUB_TOKEN)")
+       .required(false))
+    .arg(
+        Arg::new("min_interval")
+       .long("min-interval")
+       .value_name("DURATION")
+       .help("Do nothing if the last successful update (see '--state') was less than this long ago (e.g. 1h)")
+       .required(false)
+       .value_parser(crate::duration::parse_duration))
+    .arg(
+        Arg::new("wait_for_network")
+       .long("wait-for-network")
+       .value_name("DURATION")
+       .help("Wait this long for the network to come up (e.g. at boot) and retry a failed run with a growing delay until this time runs out (e.g. 10m)")
+       .required(false)
+       .value_parser(crate::duration::parse_duration))
+    .arg(
+        Arg::new("max_runtime")
+       .long("max-runtime")
+       .value_name("DURATION")
+       .help("Stop downloading and probing after this time (e.g. 300 or 5m): the peers probed so far are used if at least '--min-peers' of them are alive, otherwise exit with the code 4")
+       .required(false)
+       .value_parser(crate::duration::parse_duration))
+    .arg(
+        Arg::new("influx_url")
+       .long("influx-url")
+       .value_name("URL")
+       .help("Push the run and per-peer measurements to this InfluxDB write URL, e.g. http://127.0.0.1:8086/write?db=yggdrasil or .../api/v2/write?org=home&bucket=yggdrasil")
+       .required(false))
+    .arg(
+        Arg::new("influx_token")
+       .long("influx-token")
+       .value_name("TOKEN")
+       .help("The InfluxDB API token")
+       .required(false)
+       .requires("influx_url"))
+    .arg(
+        Arg::new("graphite")
+       .long("graphite")
+       .value_name("HOST:PORT")
+       .help("Push the run and per-peer measurements to Graphite (the plaintext protocol) at this address")
+       .required(false))
+    .arg(
+        Arg::new("graphite_prefix")
+       .long("graphite-prefix")
+       .value_name("PREFIX")
+       .help("The prefix of the Graphite metric paths")
+       .required(false)
+       .requires("graphite")
+       .default_value("peers_updater"))
+    .arg(
+        Arg::new("serve_peers")
+       .long("serve-peers")
+       .value_name("ADDR")
+       .help("In daemon mode, serve the current best peers over HTTP at /peers.txt and /peers.json on this address, e.g. 0.0.0.0:9877")
+       .required(false)
+       .requires("daemon"))
+    .arg(
+        Arg::new("control_socket")
+       .long("control-socket")
+       .value_name("PATH")
+       .help("Unix: in daemon mode, accept JSON-RPC requests ('update', 'status', 'candidates', 'apply') on a unix socket at this path, e.g. /run/peers_updater.sock")
+       .required(false)
+       .requires("daemon")
+       .value_parser(value_parser!(PathBuf)))
+    .arg(
+        arg!(
+            -r --restart "Restart the Yggdrasil service (systemd, Windows, the OpenWrt init script with '--config-format uci' or termux-services; systemd over SSH with '--remote')"
+        )
+        .required(false)
+    )
+    .subcommand(
+        clap::Command::new("history")
+        .about("Work with the probe history database (see '--history')")
+        .subcommand_required(true)
+        .subcommand(
+            clap::Command::new("export")
+            .about("Print the recorded probe results")
+            .arg(
+                arg!(
+                    --format <FORMAT> "The output format"
+                )
+                .required(false)
+                .value_parser(["csv", "json"])
+                .default_value("csv"))
+            .arg(
+                arg!(
+                    --since <DURATION> "Export only the records not older than this (e.g. 12h, 30d)"
+                )
+                .required(false)
+                .value_parser(crate::duration::parse_duration))
+        )
+    )
+    .subcommand(
+        clap::Command::new("install-task")
+        .about("Windows: register a Scheduled Task running the updater under SYSTEM with the arguments given before 'install-task' (e.g. 'peers_updater -u -r install-task --interval 12h')")
+        .arg(
+            arg!(
+                --interval <DURATION> "How often to run the updater (e.g. 30m, 12h, 7d)"
+            )
+            .required(false)
+            .default_value("7d")
+            .value_parser(crate::duration::parse_duration))
+    )
+    .subcommand(
+        clap::Command::new("install-dispatcher")
+        .about("Linux: install a NetworkManager or networkd-dispatcher hook running the updater with the arguments given before 'install-dispatcher' when the network changes (e.g. 'peers_updater -u -r --min-interval 1h install-dispatcher')")
+        .arg(
+            arg!(
+                --dispatcher <NAME> "Where to install the hook (detected by default)"
+            )
+            .required(false)
+            .value_parser(["networkmanager", "networkd"]))
+        .arg(
+            arg!(
+                --print "Print the hook script instead of installing it"
+            )
+            .required(false))
+    )
+    .subcommand(
+        clap::Command::new("list-regions")
+        .about("Print the regions of the peer list with the number of countries and peers in each (the list is downloaded, or taken from '--cache')")
+    )
+    .subcommand(
+        clap::Command::new("list-countries")
+        .about("Print the countries of the peer list (the values of '--country') with their ISO codes, regions and the number of peers")
+    )
+    .subcommand(
+        clap::Command::new("fleet")
+        .about("Download and probe the peers once and update every target of the inventory (local configuration files, SSH hosts, admin sockets) with its own number of peers and filters, then print a report per target")
+        .arg(
+            arg!(
+                --inventory <FILE> "The inventory file (HJSON) with the targets"
+            )
+            .required(true)
+            .value_parser(clap::value_parser!(PathBuf)))
+    )
+    .subcommand(
+        clap::Command::new("bench")
+        .about("Probe the given peers (or the peers from the configuration file) repeatedly and print their min/avg/max latency and loss")
+        .arg(
+            arg!(
+                --duration <DURATION> "How long to run (e.g. 30m, 1h)"
+            )
+            .required(false)
+            .default_value("1h")
+            .value_parser(crate::duration::parse_duration))
+        .arg(
+            arg!(
+                --interval <DURATION> "The interval between the probes (e.g. 60s)"
+            )
+            .required(false)
+            .default_value("60s")
+            .value_parser(crate::duration::parse_duration))
+        .arg(
+            arg!(
+                [peers] ... "The URIs of the peers to probe"
+            )
+            .required(false))
     )
-    .get_matches()
 }
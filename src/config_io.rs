@@ -0,0 +1,53 @@
+use crate::escalate::Escalation;
+use crate::remote::Remote;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Where and how the configuration files are read and written
+pub struct ConfigAccess<'a> {
+    pub remote: Option<&'a Remote>,
+    pub escalation: Option<&'a Escalation>,
+}
+
+impl ConfigAccess<'_> {
+    pub fn read(&self, conf_path: &PathBuf) -> io::Result<String> {
+        match (self.remote, self.escalation) {
+            (Some(_r), _) => _r.read_file(&conf_path.to_string_lossy(), self.escalation),
+            // The file may well be readable without escalation
+            (None, Some(_e)) => match crate::parse_config::read_config(conf_path) {
+                Ok(_ct) => Ok(_ct),
+                _ => _e.read_file(&conf_path.to_string_lossy()),
+            },
+            (None, None) => crate::parse_config::read_config(conf_path),
+        }
+    }
+
+    // The URIs of the peers currently in the configuration
+    pub fn current_peers(&self, conf_path: &PathBuf, use_uci: bool) -> Option<Vec<String>> {
+        match use_uci {
+            true => Some(crate::uci_config::get_peers(conf_path)),
+            _ => self
+                .read(conf_path)
+                .ok()
+                .and_then(|t| crate::parse_config::get_peers(&t).ok()),
+        }
+    }
+
+    pub fn write(&self, conf_path: &Path, cfg_txt: &str) -> io::Result<()> {
+        match (self.remote, self.escalation) {
+            (Some(_r), _) => _r.write_file(&conf_path.to_string_lossy(), cfg_txt, self.escalation),
+            (None, Some(_e)) => _e.write_file(&conf_path.to_string_lossy(), cfg_txt),
+            (None, None) => crate::cfg_file_modify::write_config(conf_path, cfg_txt),
+        }
+    }
+
+    // Returns whether the service was restarted
+    pub fn restart_service(&self, use_uci: bool) -> bool {
+        match (self.remote, self.escalation) {
+            (Some(_r), _) => _r.restart_service(self.escalation),
+            _ if use_uci => crate::uci_config::reload_service(),
+            (None, Some(_e)) => crate::service::restart_with(_e),
+            (None, None) => crate::service::restart(),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::i18n::tr;
+use crate::logging::log_info;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+// How often the network is checked while waiting for it
+const NETWORK_POLL: Duration = Duration::from_secs(2);
+
+pub struct IpFamilies {
+    pub ipv4: bool,
+    pub ipv6: bool,
+}
+
+impl IpFamilies {
+    pub fn all() -> Self {
+        IpFamilies {
+            ipv4: true,
+            ipv6: true,
+        }
+    }
+
+    pub fn allows(&self, ip: &IpAddr) -> bool {
+        match ip {
+            IpAddr::V4(_) => self.ipv4,
+            IpAddr::V6(_) => self.ipv6,
+        }
+    }
+}
+
+// Checks which IP families have a global route (from the '--bind' interface
+// or address)
+pub fn detect() -> IpFamilies {
+    IpFamilies {
+        ipv4: has_route("8.8.8.8:53"),
+        ipv6: has_route("[2001:4860:4860::8888]:53"),
+    }
+}
+
+fn has_route(target: &str) -> bool {
+    let local_ip = match target
+        .parse::<SocketAddr>()
+        .map_err(io::Error::other)
+        .and_then(|t| crate::bind::source_addr(&t))
+    {
+        Ok(_ip) => _ip,
+        _ => return false,
+    };
+
+    match local_ip {
+        IpAddr::V4(_ip) => !_ip.is_unspecified() && !_ip.is_loopback(),
+        // 200::/7 addresses belong to the Yggdrasil network itself
+        IpAddr::V6(_ip) => {
+            !_ip.is_unspecified() && !_ip.is_loopback() && (_ip.segments()[0] & 0xfe00) != 0x0200
+        }
+    }
+}
+
+pub enum InternetError {
+    Unreachable(String),
+    CaptivePortal(u16),
+}
+
+// Asks an endpoint that answers '204 No Content'. Anything else (a redirect
+// or a login page) means there is a captive portal in between.
+pub fn check_internet(url: &str) -> Result<(), InternetError> {
+    let client = reqwest::blocking::Client::builder()
+        .local_address(crate::bind::local_address())
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| InternetError::Unreachable(e.to_string()))?;
+
+    match client.get(url).send() {
+        Ok(_r) if _r.status() == reqwest::StatusCode::NO_CONTENT => Ok(()),
+        Ok(_r) => Err(InternetError::CaptivePortal(_r.status().as_u16())),
+        Err(e) => Err(InternetError::Unreachable(e.to_string())),
+    }
+}
+
+// Waits until there is a route to the internet and the names resolve
+// ('--wait-for-network'). Returns false if the time ran out.
+pub fn wait_for_network(timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    let mut waiting = false;
+    loop {
+        let families = detect();
+        if (families.ipv4 || families.ipv6) && crate::resolve::resolve_all("github.com").is_some() {
+            return true;
+        }
+        let now = Instant::now();
+        if now >= deadline || crate::daemon::shutdown_requested() {
+            return false;
+        }
+        if !waiting {
+            log_info!("{}", tr!("Waiting for the network..."));
+            waiting = true;
+        }
+        std::thread::sleep(NETWORK_POLL.min(deadline - now));
+    }
+}
@@ -0,0 +1,197 @@
+use crate::i18n::tr;
+use crate::peer::Peer;
+use std::sync::Mutex;
+
+// '--control-socket': a JSON-RPC 2.0 interface for the tray applets and web
+// UIs, a request (or a batch) per line on a unix socket:
+//   update      start an update cycle now
+//   status      the result of the last cycle (as '--status-listen')
+//   candidates  the alive peers of the last cycle, the best first
+//   apply       {"peers": [URI, ...]}: the next cycle selects these peers
+//               instead of the best ones, then the selection is automatic again
+static SELECTION: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+// The selection applied through the socket, taken by the cycle once
+pub fn take_selection() -> Option<Vec<String>> {
+    SELECTION.lock().ok().and_then(|mut s| s.take())
+}
+
+// The peers of the applied selection in its order; the URIs may be given as
+// shown by 'candidates' (with the secrets masked)
+pub fn pick<'a>(peers: &'a [Peer], uris: &[String]) -> Vec<&'a Peer> {
+    uris.iter()
+        .filter_map(|uri| {
+            let uri = crate::uri_normalize::normalize(uri);
+            let peer = peers.iter().find(|peer| {
+                crate::uri_normalize::normalize(&peer.uri) == uri
+                    || crate::uri_normalize::normalize(&crate::redact::redact(&peer.uri)) == uri
+            });
+            if peer.is_none() {
+                crate::logging::log_warn!(
+                    "{}",
+                    tr!(
+                        "The applied peer {} is not in the peer list anymore.",
+                        crate::redact::redact(&uri)
+                    )
+                );
+            }
+            peer
+        })
+        .collect()
+}
+
+// The JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+// Answers a request, None for a notification (no id)
+fn handle(request: &serde_json::Value, trigger: &dyn Fn()) -> Option<serde_json::Value> {
+    let id = request.get("id").cloned();
+    let result = match request["method"].as_str() {
+        _ if request["jsonrpc"] != "2.0" => Err((INVALID_REQUEST, "Invalid Request".to_string())),
+        Some("update") => {
+            trigger();
+            Ok(serde_json::json!({ "queued": true }))
+        }
+        Some("status") => Ok(crate::status::to_json()),
+        Some("candidates") => Ok(crate::status::candidates_json()),
+        Some("apply") => apply(&request["params"]).map(|_| {
+            trigger();
+            serde_json::json!({ "queued": true })
+        }),
+        Some(_m) => Err((METHOD_NOT_FOUND, format!("Method not found: {}", _m))),
+        _ => Err((INVALID_REQUEST, "Invalid Request".to_string())),
+    };
+
+    let id = id?;
+    Some(match result {
+        Ok(_r) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": _r }),
+        Err((code, message)) => serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message },
+        }),
+    })
+}
+
+// 'apply': the peers must be among the candidates of the last cycle
+fn apply(params: &serde_json::Value) -> Result<(), (i64, String)> {
+    let uris: Vec<String> = match params["peers"].as_array() {
+        Some(_p) if !_p.is_empty() => _p
+            .iter()
+            .filter_map(|uri| uri.as_str().map(|u| u.to_string()))
+            .collect(),
+        _ => {
+            return Err((
+                INVALID_PARAMS,
+                "'peers' must be a non-empty array of URIs".to_string(),
+            ))
+        }
+    };
+
+    let candidates = crate::status::candidates_json();
+    let known: Vec<String> = candidates["peers"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p["uri"].as_str())
+        .map(crate::uri_normalize::normalize)
+        .collect();
+    if let Some(_u) = uris
+        .iter()
+        .find(|uri| !known.contains(&crate::uri_normalize::normalize(uri)))
+    {
+        return Err((INVALID_PARAMS, format!("Not a candidate peer: {}", _u)));
+    }
+
+    if let Ok(mut selection) = SELECTION.lock() {
+        *selection = Some(uris);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+pub use unix::listen;
+
+#[cfg(unix)]
+mod unix {
+    use crate::i18n::tr;
+    use crate::logging::log_error;
+    use std::io::{self, Write};
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+    use std::sync::Arc;
+
+    // The socket is accessible to its owner only: it changes the peers
+    pub fn listen(path: &Path, trigger: impl Fn() + Send + Sync + 'static) -> io::Result<()> {
+        // Left over by a previous run; any other file is left alone
+        match std::fs::symlink_metadata(path) {
+            Ok(_m) if _m.file_type().is_socket() => std::fs::remove_file(path)?,
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    "the path exists and is not a socket",
+                ))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        let listener = UnixListener::bind(path)?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+
+        let trigger: Arc<dyn Fn() + Send + Sync> = Arc::new(trigger);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let trigger = trigger.clone();
+                std::thread::spawn(move || {
+                    if let Err(e) = serve(stream, trigger.as_ref()) {
+                        log_error!(
+                            "{}",
+                            tr!("Failed to answer a control socket request ({}).", e)
+                        );
+                    }
+                });
+            }
+        });
+        Ok(())
+    }
+
+    // The requests are answered one by one until the client closes the connection
+    fn serve(mut stream: UnixStream, trigger: &(dyn Fn() + Send + Sync)) -> io::Result<()> {
+        let requests = serde_json::Deserializer::from_reader(stream.try_clone()?)
+            .into_iter::<serde_json::Value>();
+        for request in requests {
+            let response = match request {
+                Ok(serde_json::Value::Array(_batch)) => {
+                    let responses: Vec<serde_json::Value> = _batch
+                        .iter()
+                        .filter_map(|r| super::handle(r, trigger))
+                        .collect();
+                    match responses.is_empty() {
+                        true => None,
+                        _ => Some(serde_json::Value::Array(responses)),
+                    }
+                }
+                Ok(_r) => super::handle(&_r, trigger),
+                // The rest of the stream can't be read after a syntax error
+                Err(e) if e.is_io() => return Err(e.into()),
+                Err(e) => {
+                    let error = serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": null,
+                        "error": { "code": super::PARSE_ERROR, "message": e.to_string() },
+                    });
+                    writeln!(stream, "{}", error)?;
+                    return Ok(());
+                }
+            };
+            if let Some(_r) = response {
+                writeln!(stream, "{}", _r)?;
+            }
+        }
+        Ok(())
+    }
+}
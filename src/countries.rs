@@ -0,0 +1,138 @@
+// ISO 3166-1 alpha-2 codes and the names under which the countries appear
+// in the public-peers repository (file names) or are commonly written by users
+const COUNTRIES: &[(&str, &[&str])] = &[
+    ("AE", &["united-arab-emirates", "uae", "emirates"]),
+    ("AM", &["armenia"]),
+    ("AR", &["argentina"]),
+    ("AT", &["austria"]),
+    ("AU", &["australia"]),
+    ("AZ", &["azerbaijan"]),
+    ("BA", &["bosnia-and-herzegovina", "bosnia"]),
+    ("BD", &["bangladesh"]),
+    ("BE", &["belgium"]),
+    ("BG", &["bulgaria"]),
+    ("BH", &["bahrain"]),
+    ("BR", &["brazil"]),
+    ("BY", &["belarus"]),
+    ("CA", &["canada"]),
+    ("CH", &["switzerland"]),
+    ("CL", &["chile"]),
+    ("CN", &["china"]),
+    ("CO", &["colombia"]),
+    ("CY", &["cyprus"]),
+    ("CZ", &["czechia", "czech-republic", "czech"]),
+    ("DE", &["germany", "deutschland"]),
+    ("DK", &["denmark"]),
+    ("EC", &["ecuador"]),
+    ("EE", &["estonia"]),
+    ("EG", &["egypt"]),
+    ("ES", &["spain"]),
+    ("FI", &["finland"]),
+    ("FR", &["france"]),
+    (
+        "GB",
+        &[
+            "united-kingdom",
+            "uk",
+            "great-britain",
+            "britain",
+            "england",
+        ],
+    ),
+    ("GE", &["georgia"]),
+    ("GR", &["greece"]),
+    ("HK", &["hong-kong", "hongkong"]),
+    ("HR", &["croatia"]),
+    ("HU", &["hungary"]),
+    ("ID", &["indonesia"]),
+    ("IE", &["ireland"]),
+    ("IL", &["israel"]),
+    ("IN", &["india"]),
+    ("IQ", &["iraq"]),
+    ("IR", &["iran"]),
+    ("IS", &["iceland"]),
+    ("IT", &["italy"]),
+    ("JP", &["japan"]),
+    ("KE", &["kenya"]),
+    ("KG", &["kyrgyzstan"]),
+    ("KR", &["south-korea", "korea"]),
+    ("KZ", &["kazakhstan"]),
+    ("LT", &["lithuania"]),
+    ("LU", &["luxembourg"]),
+    ("LV", &["latvia"]),
+    ("MD", &["moldova"]),
+    ("MK", &["north-macedonia", "macedonia"]),
+    ("MN", &["mongolia"]),
+    ("MX", &["mexico"]),
+    ("MY", &["malaysia"]),
+    ("NG", &["nigeria"]),
+    ("NL", &["netherlands", "holland"]),
+    ("NO", &["norway"]),
+    ("NZ", &["new-zealand"]),
+    ("PE", &["peru"]),
+    ("PH", &["philippines"]),
+    ("PK", &["pakistan"]),
+    ("PL", &["poland"]),
+    ("PT", &["portugal"]),
+    ("RO", &["romania"]),
+    ("RS", &["serbia"]),
+    ("RU", &["russia", "russian-federation"]),
+    ("SA", &["saudi-arabia"]),
+    ("SE", &["sweden"]),
+    ("SG", &["singapore"]),
+    ("SI", &["slovenia"]),
+    ("SK", &["slovakia"]),
+    ("TH", &["thailand"]),
+    ("TJ", &["tajikistan"]),
+    ("TR", &["turkey", "turkiye"]),
+    ("TW", &["taiwan"]),
+    ("UA", &["ukraine"]),
+    (
+        "US",
+        &[
+            "united-states",
+            "usa",
+            "united-states-of-america",
+            "america",
+        ],
+    ),
+    ("UY", &["uruguay"]),
+    ("UZ", &["uzbekistan"]),
+    ("VE", &["venezuela"]),
+    ("VN", &["vietnam", "viet-nam"]),
+    ("ZA", &["south-africa"]),
+];
+
+fn normalize_name(name: &str) -> String {
+    name.trim()
+        .to_lowercase()
+        .replace(['_', ' '], "-")
+        .trim_end_matches(".md")
+        .to_string()
+}
+
+// Returns the ISO code for a country name or code (case insensitive)
+pub fn iso_code(name: &str) -> Option<&'static str> {
+    let name = normalize_name(name);
+
+    for (code, names) in COUNTRIES {
+        if code.eq_ignore_ascii_case(&name) || names.contains(&name.as_str()) {
+            return Some(code);
+        }
+    }
+
+    None
+}
+
+// Checks whether the peer country matches one of the space-separated filter values
+pub fn country_matches(filter: &str, country: &str, country_code: &str) -> bool {
+    let country = normalize_name(country);
+
+    filter
+        .split(' ')
+        .filter(|f| !f.is_empty())
+        .any(|f| match iso_code(f) {
+            Some(_code) => _code == country_code,
+            _ => normalize_name(f) == country,
+        })
+}
@@ -0,0 +1,309 @@
+use crate::i18n::tr;
+#[cfg(not(target_os = "linux"))]
+use crate::logging::log_warn;
+use crate::logging::{log_error, log_info};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+#[cfg_attr(not(unix), allow(dead_code))]
+enum Event {
+    Update,
+    Shutdown,
+    // A default route or a global address appeared or went away ('--watch-routes')
+    NetworkChange,
+}
+
+// A burst of route changes (e.g. a Wi-Fi roam) starts a single cycle once the
+// routes have been quiet this long
+const DEBOUNCE: Duration = Duration::from_secs(10);
+
+// Set by SIGTERM/SIGINT: the running cycle stops probing and leaves the
+// configuration untouched, the daemon exits after it
+pub fn shutdown_requested() -> bool {
+    SHUTDOWN.load(Ordering::SeqCst)
+}
+
+// Runs an update cycle every `interval`. SIGHUP or SIGUSR1 start a cycle
+// immediately (the configuration and settings files are read anew on every
+// cycle), so do the network changes with `watch_routes` (Linux) and the
+// 'update' and 'apply' requests of the control socket (Unix).
+pub fn run(
+    interval: Duration,
+    watch_routes: bool,
+    control_socket: Option<&Path>,
+    mut cycle: impl FnMut() -> i32,
+) {
+    let (tx, rx) = mpsc::channel::<Event>();
+    #[cfg(unix)]
+    if let Err(e) = listen_signals(tx.clone()) {
+        log_error!("{}", tr!("Failed to set up the signal handlers ({}).", e));
+    }
+    if watch_routes {
+        #[cfg(target_os = "linux")]
+        if let Err(e) = netlink::listen(tx.clone()) {
+            log_error!("{}", tr!("Failed to watch the network changes ({}).", e));
+        }
+        #[cfg(not(target_os = "linux"))]
+        log_warn!("{}", tr!("'--watch-routes' is only supported on Linux."));
+    }
+    if let Some(_path) = control_socket {
+        #[cfg(unix)]
+        {
+            let tx = tx.clone();
+            let trigger = move || {
+                let _ret = tx.send(Event::Update);
+            };
+            if let Err(e) = crate::control::listen(_path, trigger) {
+                log_error!(
+                    "{}",
+                    tr!(
+                        "Failed to listen on the control socket {} ({}).",
+                        _path.display(),
+                        e
+                    )
+                );
+            }
+        }
+        #[cfg(not(unix))]
+        log_warn!("{}", tr!("'--control-socket' is only supported on Unix."));
+    }
+
+    loop {
+        let code = cycle();
+        if code != 0 {
+            log_error!("{}", tr!("The update cycle failed (exit code {}).", code));
+        }
+        if shutdown_requested() {
+            break;
+        }
+
+        // The updates asked for during the cycle start a single new one right
+        // away; the network changes are the cycle's own (e.g. the restarted
+        // TUN interface)
+        let mut pending_update = false;
+        while let Ok(_e) = rx.try_recv() {
+            match _e {
+                Event::Shutdown => return,
+                Event::Update => pending_update = true,
+                Event::NetworkChange => {}
+            }
+        }
+        if pending_update {
+            continue;
+        }
+
+        // '--supervise' checks the sessions while waiting for the next cycle
+        let next_cycle = Instant::now() + interval;
+        let event = loop {
+            let left = next_cycle.saturating_duration_since(Instant::now());
+            let wait = match crate::supervise::check_interval() {
+                Some(_c) => _c.min(left),
+                _ => left,
+            };
+            match rx.recv_timeout(wait) {
+                Err(mpsc::RecvTimeoutError::Timeout) if wait < left => crate::supervise::check(),
+                _e => break _e,
+            }
+        };
+
+        match event {
+            Ok(Event::Update) => {}
+            Ok(Event::Shutdown) => break,
+            Ok(Event::NetworkChange) => match debounce(&rx) {
+                Some(Event::Shutdown) | None => break,
+                _ => log_info!("{}", tr!("The network has changed, updating the peers.")),
+            },
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    // Keeping the sender alive while waiting
+    drop(tx);
+}
+
+// Waits until the network changes stop; returns the event that ended the
+// wait (none if the channel is gone)
+fn debounce(rx: &mpsc::Receiver<Event>) -> Option<Event> {
+    loop {
+        match rx.recv_timeout(DEBOUNCE) {
+            Ok(Event::NetworkChange) => {}
+            Ok(_e) => return Some(_e),
+            Err(mpsc::RecvTimeoutError::Timeout) => return Some(Event::NetworkChange),
+            Err(mpsc::RecvTimeoutError::Disconnected) => return None,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn listen_signals(tx: mpsc::Sender<Event>) -> std::io::Result<()> {
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM, SIGUSR1};
+    use signal_hook::iterator::Signals;
+
+    let mut signals = Signals::new([SIGHUP, SIGUSR1, SIGTERM, SIGINT])?;
+    std::thread::spawn(move || {
+        for signal in signals.forever() {
+            let event = match signal {
+                SIGHUP | SIGUSR1 => Event::Update,
+                _ => {
+                    SHUTDOWN.store(true, Ordering::SeqCst);
+                    Event::Shutdown
+                }
+            };
+            if tx.send(event).is_err() {
+                return;
+            }
+        }
+    });
+    Ok(())
+}
+
+// The rtnetlink multicast groups of the address and route changes
+#[cfg(target_os = "linux")]
+mod netlink {
+    use super::Event;
+    use std::ffi::{c_int, c_void};
+    use std::fs::File;
+    use std::io::{self, Read};
+    use std::os::fd::FromRawFd;
+    use std::sync::mpsc;
+
+    const AF_NETLINK: c_int = 16;
+    const AF_INET6: u8 = 10;
+    const SOCK_RAW: c_int = 3;
+    const SOCK_CLOEXEC: c_int = 0o2000000;
+    const NETLINK_ROUTE: c_int = 0;
+
+    const RTMGRP_IPV4_IFADDR: u32 = 0x10;
+    const RTMGRP_IPV4_ROUTE: u32 = 0x40;
+    const RTMGRP_IPV6_IFADDR: u32 = 0x100;
+    const RTMGRP_IPV6_ROUTE: u32 = 0x400;
+
+    const RTM_NEWADDR: u16 = 20;
+    const RTM_DELADDR: u16 = 21;
+    const RTM_NEWROUTE: u16 = 24;
+    const RTM_DELROUTE: u16 = 25;
+
+    const IFA_ADDRESS: u16 = 1;
+    const RT_SCOPE_UNIVERSE: u8 = 0;
+
+    // struct nlmsghdr
+    const HEADER_LEN: usize = 16;
+
+    #[repr(C)]
+    struct SockaddrNl {
+        nl_family: u16,
+        nl_pad: u16,
+        nl_pid: u32,
+        nl_groups: u32,
+    }
+
+    extern "C" {
+        fn socket(domain: c_int, kind: c_int, protocol: c_int) -> c_int;
+        fn bind(fd: c_int, addr: *const c_void, len: u32) -> c_int;
+    }
+
+    pub fn listen(tx: mpsc::Sender<Event>) -> io::Result<()> {
+        let fd = unsafe { socket(AF_NETLINK, SOCK_RAW | SOCK_CLOEXEC, NETLINK_ROUTE) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // Closes the socket when dropped
+        let mut sock = unsafe { File::from_raw_fd(fd) };
+
+        let addr = SockaddrNl {
+            nl_family: AF_NETLINK as u16,
+            nl_pad: 0,
+            nl_pid: 0,
+            nl_groups: RTMGRP_IPV4_IFADDR
+                | RTMGRP_IPV4_ROUTE
+                | RTMGRP_IPV6_IFADDR
+                | RTMGRP_IPV6_ROUTE,
+        };
+        let bound = unsafe {
+            bind(
+                fd,
+                &addr as *const SockaddrNl as *const c_void,
+                std::mem::size_of::<SockaddrNl>() as u32,
+            )
+        };
+        if bound < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        std::thread::spawn(move || {
+            let mut buf = vec![0u8; 65536];
+            while let Ok(_n) = sock.read(&mut buf) {
+                if _n > 0
+                    && messages(&buf[.._n]).any(relevant)
+                    && tx.send(Event::NetworkChange).is_err()
+                {
+                    return;
+                }
+            }
+        });
+        Ok(())
+    }
+
+    // The netlink messages of a datagram: (type, payload)
+    fn messages(buf: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+        let mut rest = buf;
+        std::iter::from_fn(move || {
+            if rest.len() < HEADER_LEN {
+                return None;
+            }
+            let len = u32::from_ne_bytes(rest[0..4].try_into().ok()?) as usize;
+            if len < HEADER_LEN || len > rest.len() {
+                return None;
+            }
+            let kind = u16::from_ne_bytes(rest[4..6].try_into().ok()?);
+            let payload = &rest[HEADER_LEN..len];
+            rest = rest.get((len + 3) & !3..).unwrap_or_default();
+            Some((kind, payload))
+        })
+    }
+
+    // A default route, or a global address that is not the Yggdrasil one
+    // (200::/7) the node gets itself
+    fn relevant((kind, payload): (u16, &[u8])) -> bool {
+        match kind {
+            // struct rtmsg: family, dst_len, ...
+            RTM_NEWROUTE | RTM_DELROUTE => payload.get(1) == Some(&0),
+            // struct ifaddrmsg: family, prefixlen, flags, scope, index; then the attributes
+            RTM_NEWADDR | RTM_DELADDR => {
+                let (family, scope) = match (payload.first(), payload.get(3)) {
+                    (Some(_f), Some(_s)) => (*_f, *_s),
+                    _ => return false,
+                };
+                if scope != RT_SCOPE_UNIVERSE {
+                    return false;
+                }
+                family != AF_INET6
+                    || attribute(payload.get(8..).unwrap_or_default(), IFA_ADDRESS)
+                        .and_then(|a| a.first())
+                        .is_none_or(|b| b & 0xfe != 0x02)
+            }
+            _ => false,
+        }
+    }
+
+    // struct rtattr: len, type, value (aligned to 4 bytes)
+    fn attribute(mut attrs: &[u8], wanted: u16) -> Option<&[u8]> {
+        while attrs.len() >= 4 {
+            let len = u16::from_ne_bytes(attrs[0..2].try_into().ok()?) as usize;
+            let kind = u16::from_ne_bytes(attrs[2..4].try_into().ok()?);
+            if len < 4 || len > attrs.len() {
+                return None;
+            }
+            if kind == wanted {
+                return Some(&attrs[4..len]);
+            }
+            attrs = attrs.get((len + 3) & !3..).unwrap_or_default();
+        }
+        None
+    }
+}
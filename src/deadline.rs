@@ -0,0 +1,31 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// '--max-runtime': the end of the run. Downloads and probes don't wait past
+// it, and no new probes are started once it has passed.
+static DEADLINE: Mutex<Option<Instant>> = Mutex::new(None);
+
+pub fn set(max_runtime: Option<Duration>) {
+    if let Ok(mut deadline) = DEADLINE.lock() {
+        *deadline = max_runtime.map(|m| Instant::now() + m);
+    }
+}
+
+// The time left, None without a deadline
+pub fn remaining() -> Option<Duration> {
+    let deadline = *DEADLINE.lock().ok()?;
+    deadline.map(|d| d.saturating_duration_since(Instant::now()))
+}
+
+pub fn expired() -> bool {
+    remaining().is_some_and(|r| r.is_zero())
+}
+
+// A timeout that ends no later than the deadline (a zero timeout is an error
+// for the sockets)
+pub fn cap(timeout: Duration) -> Duration {
+    match remaining() {
+        Some(_r) => timeout.min(_r).max(Duration::from_millis(1)),
+        _ => timeout,
+    }
+}
@@ -1,9 +1,70 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+#[cfg(target_os = "windows")]
+pub const DEF_CFG_PATH: &str = r"C:\ProgramData\Yggdrasil\yggdrasil.conf";
+#[cfg(not(target_os = "windows"))]
+pub const DEF_CFG_PATH: &str = "/etc/yggdrasil.conf";
+
+pub const DEF_UCI_CFG_PATH: &str = "/etc/config/yggdrasil";
+
+#[cfg(target_os = "windows")]
+pub const DEF_SOCKET_ADDR: &str = "localhost:9001";
+#[cfg(not(target_os = "windows"))]
+pub const DEF_SOCKET_ADDR: &str = "/var/run/yggdrasil.sock";
+
 #[cfg(target_os = "windows")]
-pub const DEF_CFG_PATH: &'static str = r"C:\ProgramData\Yggdrasil\yggdrasil.conf";
+pub const DEF_STATE_PATH: &str = r"C:\ProgramData\peers_updater\state.json";
 #[cfg(not(target_os = "windows"))]
-pub const DEF_CFG_PATH: &'static str = "/etc/yggdrasil.conf";
+pub const DEF_STATE_PATH: &str = "/var/lib/peers_updater/state.json";
 
 #[cfg(target_os = "windows")]
-pub const DEF_SOCKET_ADDR: &'static str = "localhost:9001";
+pub const DEF_CACHE_PATH: &str = r"C:\ProgramData\peers_updater\peers.zip";
 #[cfg(not(target_os = "windows"))]
-pub const DEF_SOCKET_ADDR: &'static str = "/var/run/yggdrasil.sock";
+pub const DEF_CACHE_PATH: &str = "/var/cache/peers_updater/peers.zip";
+
+// The default paths, moved under the prefix in Termux
+fn under_prefix(cell: &'static OnceLock<String>, path: &'static str) -> &'static str {
+    cell.get_or_init(|| match crate::termux::prefix() {
+        Some(_p) => _p
+            .join(path.trim_start_matches('/'))
+            .to_string_lossy()
+            .to_string(),
+        _ => path.to_string(),
+    })
+}
+
+pub fn cfg_path() -> &'static str {
+    static PATH: OnceLock<String> = OnceLock::new();
+    under_prefix(&PATH, DEF_CFG_PATH)
+}
+
+pub fn socket_addr() -> &'static str {
+    static PATH: OnceLock<String> = OnceLock::new();
+    under_prefix(&PATH, DEF_SOCKET_ADDR)
+}
+
+pub fn state_path() -> &'static str {
+    static PATH: OnceLock<String> = OnceLock::new();
+    under_prefix(&PATH, DEF_STATE_PATH)
+}
+
+pub fn cache_path() -> &'static str {
+    static PATH: OnceLock<String> = OnceLock::new();
+    under_prefix(&PATH, DEF_CACHE_PATH)
+}
+
+// Without TMPDIR, Termux has no writable /tmp
+pub fn tmp_dir() -> PathBuf {
+    match (std::env::var_os("TMPDIR"), crate::termux::prefix()) {
+        (None, Some(_p)) => _p.join("tmp"),
+        _ => std::env::temp_dir(),
+    }
+}
+
+pub const DEF_CONNECTIVITY_CHECK_URL: &str = "http://connectivitycheck.gstatic.com/generate_204";
+
+// Exit codes besides 0 (success) and 1 (error)
+pub const EXIT_CHANGES_PENDING: i32 = 2;
+pub const EXIT_TOO_FEW_PEERS: i32 = 3;
+pub const EXIT_DEADLINE: i32 = 4;
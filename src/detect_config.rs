@@ -0,0 +1,120 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+// Finding the configuration file the running Yggdrasil node really uses: the
+// '-useconffile' argument of the running process or of the service definition
+pub fn running_config_path() -> Option<PathBuf> {
+    #[cfg(target_os = "linux")]
+    if let Some(_p) = from_proc() {
+        return Some(_p);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    return from_systemd_unit();
+
+    #[cfg(target_os = "windows")]
+    return from_windows_process();
+}
+
+// The value of '-useconffile' ('--useconffile', '-useconffile=...')
+fn useconffile_arg(args: &[String]) -> Option<String> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let flag = arg.trim_start_matches('-');
+        if arg.starts_with('-') && flag == "useconffile" {
+            return args.next().cloned();
+        }
+        if let Some(_v) = flag.strip_prefix("useconffile=") {
+            if arg.starts_with('-') {
+                return Some(_v.to_string());
+            }
+        }
+    }
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn from_proc() -> Option<PathBuf> {
+    let entries = std::fs::read_dir("/proc").ok()?;
+    for entry in entries.flatten() {
+        let proc_dir = entry.path();
+        let comm = match std::fs::read_to_string(proc_dir.join("comm")) {
+            Ok(_c) => _c,
+            _ => continue,
+        };
+        if comm.trim() != "yggdrasil" {
+            continue;
+        }
+
+        let cmdline = match std::fs::read(proc_dir.join("cmdline")) {
+            Ok(_c) => _c,
+            _ => continue,
+        };
+        let args: Vec<String> = cmdline
+            .split(|b| *b == 0)
+            .map(|a| String::from_utf8_lossy(a).to_string())
+            .collect();
+
+        if let Some(_path) = useconffile_arg(&args).map(PathBuf::from) {
+            // A relative path is relative to the working directory of the node
+            return match _path.is_relative() {
+                true => std::fs::read_link(proc_dir.join("cwd"))
+                    .ok()
+                    .map(|cwd| cwd.join(&_path)),
+                _ => Some(_path),
+            };
+        }
+    }
+    None
+}
+
+#[cfg(not(target_os = "windows"))]
+fn from_systemd_unit() -> Option<PathBuf> {
+    let output = Command::new("systemctl")
+        .args([
+            "show",
+            "--property=ExecStart",
+            "--value",
+            crate::service::name(),
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // { path=/usr/bin/yggdrasil ; argv[]=/usr/bin/yggdrasil -useconffile /etc/yggdrasil.conf ; ... }
+    let exec_start = String::from_utf8_lossy(&output.stdout);
+    let argv = exec_start.split("argv[]=").nth(1)?.split(" ;").next()?;
+    let args: Vec<String> = argv.split_whitespace().map(|a| a.to_string()).collect();
+    useconffile_arg(&args).map(PathBuf::from)
+}
+
+#[cfg(target_os = "windows")]
+fn from_windows_process() -> Option<PathBuf> {
+    let output = Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-Command",
+            "(Get-CimInstance Win32_Process -Filter \"Name='yggdrasil.exe'\").CommandLine",
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // Splitting the command line, keeping the quoted parts together
+    let command_line = String::from_utf8_lossy(&output.stdout);
+    let args: Vec<String> = command_line
+        .lines()
+        .next()?
+        .split('"')
+        .enumerate()
+        .flat_map(|(i, part)| match i % 2 {
+            1 => vec![part.to_string()],
+            _ => part.split_whitespace().map(|a| a.to_string()).collect(),
+        })
+        .collect();
+    useconffile_arg(&args).map(PathBuf::from)
+}
@@ -0,0 +1,98 @@
+use crate::i18n::tr;
+use crate::logging::log_warn;
+use crate::peer::Peer;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::mpsc;
+
+// 'list-regions' and 'list-countries': the regions and countries of the peer
+// list (the values of '--country', '--per-region' and '--group-by region')
+// with the number of peers in each
+pub fn list(tmp_dir: &Path, cache_path: &Path, countries: bool) -> io::Result<()> {
+    let peers = read_peer_list(tmp_dir, cache_path)?;
+
+    if countries {
+        // The country with its ISO code and region
+        let mut counts: BTreeMap<(String, String, String), usize> = BTreeMap::new();
+        for peer in &peers {
+            let key = (
+                peer.country.clone(),
+                peer.country_code.clone(),
+                peer.region.clone(),
+            );
+            *counts.entry(key).or_default() += 1;
+        }
+        println!(
+            "{0:<25}|{1:<5}|{2:<15}|{3:<6}",
+            tr!("Country"),
+            tr!("Code"),
+            tr!("Region"),
+            tr!("Peers")
+        );
+        println!("{0:-<54}", "-");
+        for ((country, code, region), count) in counts {
+            println!(
+                "{0:<25}|{1:<5}|{2:<15}|{3:<6}",
+                country, code, region, count
+            );
+        }
+    } else {
+        let mut counts: BTreeMap<String, (BTreeSet<String>, usize)> = BTreeMap::new();
+        for peer in &peers {
+            let entry = counts.entry(peer.region.clone()).or_default();
+            entry.0.insert(peer.country.clone());
+            entry.1 += 1;
+        }
+        println!(
+            "{0:<15}|{1:<10}|{2:<6}",
+            tr!("Region"),
+            tr!("Countries"),
+            tr!("Peers")
+        );
+        println!("{0:-<33}", "-");
+        for (region, (countries, count)) in counts {
+            println!("{0:<15}|{1:<10}|{2:<6}", region, countries.len(), count);
+        }
+    }
+    Ok(())
+}
+
+// Downloads the peer list, or takes the cached one if the download fails
+pub fn read_peer_list(tmp_dir: &Path, cache_path: &Path) -> io::Result<Vec<Peer>> {
+    let archive_path = tmp_dir.join("peers.zip");
+    match crate::github::download_archive(tmp_dir) {
+        Ok(_) => {
+            if let Some(_dir) = cache_path.parent() {
+                let _ret = fs::create_dir_all(_dir);
+            }
+            let _ret = fs::copy(&archive_path, cache_path);
+        }
+        Err(e) if cache_path.exists() => {
+            log_warn!(
+                "{}",
+                tr!(
+                    "Failed to download archive with peers ({}). The cached peer list is used.",
+                    e
+                )
+            );
+            fs::copy(cache_path, &archive_path)?;
+        }
+        Err(e) => return Err(e),
+    }
+    crate::unpack::unpack_archive(tmp_dir)?;
+    let _ret = fs::remove_file(tmp_dir.join("public-peers-master/README.md"));
+    let _ret = fs::remove_dir_all(tmp_dir.join("public-peers-master/other"));
+
+    let (tx, rx) = mpsc::channel::<Peer>();
+    crate::parsing_peers::collect_peers(&tmp_dir.join("public-peers-master"), &tx)?;
+    drop(tx);
+
+    // The same peer may be listed several times
+    let mut seen_uris: BTreeSet<String> = BTreeSet::new();
+    Ok(rx
+        .into_iter()
+        .filter(|peer| seen_uris.insert(crate::uri_normalize::normalize(&peer.uri)))
+        .collect())
+}
@@ -0,0 +1,84 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Where the hooks run on network changes are installed
+#[derive(Clone, Copy)]
+pub enum Dispatcher {
+    NetworkManager,
+    Networkd,
+}
+
+impl Dispatcher {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "networkmanager" => Some(Dispatcher::NetworkManager),
+            "networkd" => Some(Dispatcher::Networkd),
+            _ => None,
+        }
+    }
+
+    // NetworkManager is preferred if both are present
+    pub fn detect() -> Option<Self> {
+        if Path::new("/etc/NetworkManager/dispatcher.d").is_dir() {
+            Some(Dispatcher::NetworkManager)
+        } else if Path::new("/etc/networkd-dispatcher").is_dir() {
+            Some(Dispatcher::Networkd)
+        } else {
+            None
+        }
+    }
+
+    pub fn script_path(&self) -> PathBuf {
+        match self {
+            Dispatcher::NetworkManager => {
+                PathBuf::from("/etc/NetworkManager/dispatcher.d/90-peers_updater")
+            }
+            Dispatcher::Networkd => {
+                PathBuf::from("/etc/networkd-dispatcher/routable.d/90-peers_updater")
+            }
+        }
+    }
+
+    // The script starts the updater in the background: the dispatchers
+    // kill the hooks that run for too long
+    pub fn script(&self, exe: &Path, args: &[String]) -> String {
+        let command: String = std::iter::once(exe.to_string_lossy().to_string())
+            .chain(args.iter().cloned())
+            .map(|a| crate::remote::shell_quote(&a))
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        let filter = match self {
+            // $2 is the action; the default route or the Wi-Fi network changes
+            Dispatcher::NetworkManager => {
+                "case \"$2\" in\n    up|dhcp4-change|dhcp6-change|connectivity-change) ;;\n    *) exit 0 ;;\nesac\n"
+            }
+            // Run for the interfaces that have become routable only
+            Dispatcher::Networkd => "",
+        };
+
+        format!(
+            "#!/bin/sh\n# Installed by 'peers_updater install-dispatcher': updates the Yggdrasil peers\n# when the network changes\n{}{} >/dev/null 2>&1 &\n",
+            filter, command
+        )
+    }
+
+    #[cfg(unix)]
+    pub fn install(&self, script: &str) -> io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = self.script_path();
+        if let Some(_dir) = path.parent() {
+            fs::create_dir_all(_dir)?;
+        }
+        fs::write(&path, script)?;
+        // The dispatchers only run the scripts owned by root and not writable by others
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+    }
+
+    #[cfg(not(unix))]
+    pub fn install(&self, _: &str) -> io::Result<()> {
+        Err(io::Error::other("not supported on this platform"))
+    }
+}
@@ -0,0 +1,20 @@
+use std::time::Duration;
+
+// Parses durations like "90", "60s", "15m", "12h", "30d" or "2w"
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (number, multiplier) = match value.chars().last() {
+        Some('s') => (&value[..value.len() - 1], 1),
+        Some('m') => (&value[..value.len() - 1], 60),
+        Some('h') => (&value[..value.len() - 1], 60 * 60),
+        Some('d') => (&value[..value.len() - 1], 24 * 60 * 60),
+        Some('w') => (&value[..value.len() - 1], 7 * 24 * 60 * 60),
+        _ => (value, 1),
+    };
+
+    match number.parse::<u64>().map(|n| n.checked_mul(multiplier)) {
+        Ok(Some(_s)) => Ok(Duration::from_secs(_s)),
+        Ok(None) => Err(format!("invalid duration '{}' (too large)", value)),
+        Err(e) => Err(format!("invalid duration '{}' ({})", value, e)),
+    }
+}
@@ -0,0 +1,75 @@
+use std::io;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// A privilege escalation tool (sudo, doas, pkexec...) used only for the
+// final configuration write and the service restart
+pub struct Escalation {
+    cmd: Vec<String>,
+}
+
+impl Escalation {
+    pub fn parse(spec: &str) -> Self {
+        Escalation {
+            cmd: spec
+                .split(' ')
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string())
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cmd.is_empty()
+    }
+
+    // The prefix for shell command lines (used on remote machines)
+    pub fn shell_prefix(&self) -> String {
+        self.cmd.iter().map(|c| format!("{} ", c)).collect()
+    }
+
+    pub fn command(&self, program: &str) -> Command {
+        let mut command = Command::new(&self.cmd[0]);
+        command.args(&self.cmd[1..]).arg(program);
+        command
+    }
+
+    pub fn read_file(&self, path: &str) -> io::Result<String> {
+        let output = self
+            .command("cat")
+            .arg(path)
+            .stderr(Stdio::inherit())
+            .output()?;
+
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "{} exited with {}",
+                self.cmd[0], output.status
+            )));
+        }
+
+        String::from_utf8(output.stdout).map_err(io::Error::other)
+    }
+
+    pub fn write_file(&self, path: &str, content: &str) -> io::Result<()> {
+        let mut child = self
+            .command("tee")
+            .arg(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(content.as_bytes())?;
+        }
+
+        let status = child.wait()?;
+        match status.success() {
+            true => Ok(()),
+            _ => Err(io::Error::other(format!(
+                "{} exited with {}",
+                self.cmd[0], status
+            ))),
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use crate::config_io::ConfigAccess;
+use crate::connectivity::IpFamilies;
+use crate::escalate::Escalation;
+use crate::i18n::tr;
+use crate::logging::log_error;
+use crate::peer::Peer;
+use crate::remote::Remote;
+use crate::select::PeerCount;
+use nu_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+// Where the peers of a target are written
+enum Kind {
+    Config(PathBuf),
+    Ssh(Remote),
+    // The admin endpoint of the node (AdminListen)
+    Admin(String),
+}
+
+// A machine of the inventory with its own peer count and filters
+struct Target {
+    name: String,
+    kind: Kind,
+    n_peers: PeerCount,
+    country: Option<String>,
+    ignore: Option<String>,
+    extra: Option<String>,
+    escalation: Option<Escalation>,
+    restart: bool,
+}
+
+// The inventory (HJSON) lists the targets:
+// { targets: [
+//   { name: "kitchen", ssh: "root@192.168.1.2:/etc/yggdrasil.conf", number: 3, restart: true }
+//   { name: "office", admin: "tcp://192.168.2.1:9001", number: "30%", country: "germany" }
+//   { name: "local", config: "/etc/yggdrasil.conf", ignore: "tcp://bad.example.com:1234" }
+// ] }
+fn load(path: &Path) -> Result<Vec<Target>, String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|e| tr!("Can't read the inventory ({}).", e))?;
+    let mut inventory: Map<String, Value> =
+        nu_json::from_str(&text).map_err(|e| tr!("Can't parse the inventory ({}).", e))?;
+
+    let targets = match inventory.remove("targets") {
+        Some(Value::Array(_t)) => _t,
+        _ => return Err(tr!("The inventory must have a 'targets' array.")),
+    };
+    targets.iter().map(target).collect()
+}
+
+fn target(value: &Value) -> Result<Target, String> {
+    let obj = value
+        .as_object()
+        .ok_or_else(|| tr!("Every target of the inventory must be an object."))?;
+    let string = |key: &str| obj.get(key).and_then(|v| v.as_str()).map(|v| v.to_string());
+
+    let name = string("name").ok_or_else(|| tr!("A target of the inventory has no 'name'."))?;
+    let kind = match (string("config"), string("ssh"), string("admin")) {
+        (Some(_c), None, None) => Kind::Config(PathBuf::from(_c)),
+        (None, Some(_s), None) => Kind::Ssh(Remote::parse(&_s)),
+        (None, None, Some(_a)) => Kind::Admin(_a),
+        _ => {
+            return Err(tr!(
+                "The target '{}' must have one of 'config', 'ssh' or 'admin'.",
+                name
+            ))
+        }
+    };
+    let n_peers = match obj.get("number") {
+        Some(Value::String(_n)) => PeerCount::parse(_n),
+        Some(_n) => PeerCount::parse(&_n.to_string()),
+        _ => Ok(PeerCount::Fixed(3)),
+    }
+    .map_err(|e| tr!("The target '{}' has an incorrect 'number' ({}).", name, e))?;
+
+    Ok(Target {
+        n_peers,
+        country: string("country"),
+        ignore: string("ignore"),
+        extra: string("extra"),
+        escalation: string("escalate").map(|e| Escalation::parse(&e)),
+        restart: obj
+            .get("restart")
+            .and_then(|r| r.as_bool())
+            .unwrap_or(false),
+        name,
+        kind,
+    })
+}
+
+// 'fleet': the peer list is downloaded and probed once, then every target of
+// the inventory gets its own selection. Returns the exit code.
+pub fn run(inventory: &Path, tmp_dir: &Path, cache_path: &Path, families: &IpFamilies) -> i32 {
+    let targets = match load(inventory) {
+        Ok(_t) => _t,
+        Err(e) => {
+            log_error!("{}", e);
+            return 1;
+        }
+    };
+
+    let peers = match crate::discover::read_peer_list(tmp_dir, cache_path) {
+        Ok(_p) => _p,
+        Err(e) => {
+            log_error!("{}", tr!("Failed to get the peer list ({}).", e));
+            return 1;
+        }
+    };
+
+    // Only the countries some target wants are probed
+    let wanted = |peer: &Peer| {
+        targets.iter().any(|target| match &target.country {
+            Some(_c) => crate::countries::country_matches(_c, &peer.country, &peer.country_code),
+            _ => true,
+        })
+    };
+    let peers: Vec<Peer> = peers.into_iter().filter(wanted).collect();
+    let mut peers = probe(peers, families);
+    peers.sort_by_key(|peer| peer.latency);
+    let peers = crate::select::best_transport_per_host(peers);
+
+    let mut failed = false;
+    println!(
+        "{0:<20}|{1:<6}|{2}",
+        tr!("Target"),
+        tr!("Peers"),
+        tr!("Result")
+    );
+    println!("{0:-<60}", "-");
+    for target in &targets {
+        let (selected, result) = match update_target(target, &peers) {
+            Ok(_r) => _r,
+            Err((_n, e)) => {
+                failed = true;
+                (_n, tr!("failed: {}", e))
+            }
+        };
+        println!("{0:<20}|{1:<6}|{2}", target.name, selected, result);
+    }
+
+    match failed {
+        true => 1,
+        _ => 0,
+    }
+}
+
+// Probes the peers at the same time
+fn probe(peers: Vec<Peer>, families: &IpFamilies) -> Vec<Peer> {
+    std::thread::scope(|scope| {
+        let probes: Vec<_> = peers
+            .into_iter()
+            .map(|mut peer| {
+                scope.spawn(move || {
+                    crate::latency::set_latency(&mut peer, families, false).then_some(peer)
+                })
+            })
+            .collect();
+        probes
+            .into_iter()
+            .filter_map(|probe| probe.join().ok().flatten())
+            .collect()
+    })
+}
+
+// Returns the number of the selected peers and the result
+fn update_target(target: &Target, peers: &[Peer]) -> Result<(usize, String), (usize, String)> {
+    let candidates: Vec<Peer> = peers
+        .iter()
+        .filter(|peer| peer.is_alive)
+        .filter(|peer| match &target.country {
+            Some(_c) => crate::countries::country_matches(_c, &peer.country, &peer.country_code),
+            _ => true,
+        })
+        .filter(|peer| match &target.ignore {
+            Some(_i) => !crate::uri_normalize::list_contains(_i, &peer.uri),
+            _ => true,
+        })
+        .cloned()
+        .collect();
+    let extra_peers = crate::select::extra_peers(target.extra.as_ref(), target.ignore.as_ref());
+    let n_peers = target.n_peers.resolve(candidates.len());
+    let selected = crate::select::select_peers(&candidates, n_peers, None, &extra_peers);
+    let count = selected.len();
+
+    // Without connectivity all the peers look dead
+    if selected.is_empty() && (n_peers > 0 || candidates.is_empty()) {
+        return Err((
+            count,
+            tr!("no alive peers pass the filters, left unchanged"),
+        ));
+    }
+
+    let (remote, conf_path) = match &target.kind {
+        Kind::Admin(_endpoint) => {
+            let mut conf_obj: Map<String, Value> = Map::new();
+            conf_obj.insert("AdminListen".to_string(), Value::String(_endpoint.clone()));
+            let backend = crate::using_api::ApiBackend::Socket;
+            let mut client = crate::using_api::ApiClient::new(&mut conf_obj, &backend);
+            if crate::using_api::healthy_sessions(&mut client, u128::MAX).is_none() {
+                return Err((count, tr!("the admin API is unreachable")));
+            }
+            crate::using_api::update_peers(
+                &selected,
+                &extra_peers,
+                &mut client,
+                std::time::Duration::from_secs(30),
+            );
+            return Ok((count, tr!("applied through the admin API")));
+        }
+        Kind::Config(_path) => (None, _path.clone()),
+        Kind::Ssh(_remote) => (
+            Some(_remote),
+            PathBuf::from(
+                _remote
+                    .path
+                    .as_deref()
+                    .unwrap_or(crate::defaults::cfg_path()),
+            ),
+        ),
+    };
+
+    let access = ConfigAccess {
+        remote,
+        escalation: target.escalation.as_ref(),
+    };
+    let cfg_txt = access
+        .read(&conf_path)
+        .map_err(|e| (count, format!("{} ({})", conf_path.display(), e)))?;
+    let new_cfg = crate::cfg_file_modify::add_peers_to_conf_new(&selected, &extra_peers, &cfg_txt)
+        .ok_or_else(|| (count, tr!("incorrect configuration file format")))?;
+    if new_cfg == cfg_txt {
+        return Ok((count, tr!("unchanged")));
+    }
+    access
+        .write(&conf_path, &new_cfg)
+        .map_err(|e| (count, e.to_string()))?;
+
+    Ok(match target.restart {
+        true if access.restart_service(false) => (count, tr!("written, restarted")),
+        true => (count, tr!("written, the restart failed")),
+        _ => (count, tr!("written")),
+    })
+}
@@ -0,0 +1,184 @@
+use crate::i18n::tr;
+use crate::logging::log_warn;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+const API_URL: &str = "https://api.github.com/repos/yggdrasil-network/public-peers";
+const ARCHIVE_URL: &str =
+    "https://github.com/yggdrasil-network/public-peers/archive/refs/heads/master.zip";
+const BRANCH: &str = "master";
+
+// With more files the archive is cheaper than the separate requests
+const MAX_FILES: usize = 10;
+
+// A longer Retry-After is not waited for
+const MAX_WAIT: u64 = 60;
+
+// The default of reqwest, shortened by '--max-runtime'
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+// GitHub refused the request because of the rate limit
+#[derive(Debug)]
+pub struct RateLimited {
+    pub retry_after: u64,
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            tr!(
+                "GitHub rate limit exceeded, retry in {} s",
+                self.retry_after
+            )
+        )
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+pub fn is_rate_limited(e: &io::Error) -> bool {
+    e.get_ref().is_some_and(|r| r.is::<RateLimited>())
+}
+
+// The token from '--github-token' or the GITHUB_TOKEN environment variable
+// raises the API rate limit
+pub fn token(matches: &clap::ArgMatches) -> Option<String> {
+    matches
+        .get_one::<String>("github_token")
+        .cloned()
+        .or_else(|| std::env::var("GITHUB_TOKEN").ok())
+        .filter(|t| !t.is_empty())
+}
+
+// How long to wait if the response is a rate limit refusal
+fn rate_limit_wait(resp: &reqwest::blocking::Response) -> Option<u64> {
+    let header = |name: &str| {
+        resp.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+    };
+    let limited = match resp.status().as_u16() {
+        429 => true,
+        403 => header("retry-after").is_some() || header("x-ratelimit-remaining") == Some(0),
+        _ => false,
+    };
+    if !limited {
+        return None;
+    }
+
+    Some(match (header("retry-after"), header("x-ratelimit-reset")) {
+        (Some(_s), _) => _s,
+        (_, Some(_reset)) => _reset.saturating_sub(crate::history::unix_time() as u64),
+        _ => MAX_WAIT,
+    })
+}
+
+// A GET request that waits once for a short rate limit
+fn get(url: &str, accept: &str, token: Option<&str>) -> io::Result<reqwest::blocking::Response> {
+    let client = reqwest::blocking::Client::builder()
+        .local_address(crate::bind::local_address())
+        .user_agent(format!("peers_updater/{}", crate::version::APP_VERSION))
+        .timeout(crate::deadline::cap(TIMEOUT))
+        .build()
+        .map_err(io::Error::other)?;
+
+    let mut waited = false;
+    loop {
+        let mut request = client.get(url).header("Accept", accept);
+        if let Some(_t) = token {
+            request = request.bearer_auth(_t);
+        }
+        let resp = request.send().map_err(io::Error::other)?;
+
+        match rate_limit_wait(&resp) {
+            Some(_wait)
+                if !waited
+                    && _wait <= MAX_WAIT
+                    && crate::deadline::remaining()
+                        .is_none_or(|r| r > Duration::from_secs(_wait)) =>
+            {
+                log_warn!(
+                    "{}",
+                    tr!("GitHub rate limit exceeded, waiting {} s.", _wait)
+                );
+                std::thread::sleep(Duration::from_secs(_wait));
+                waited = true;
+            }
+            Some(_wait) => return Err(io::Error::other(RateLimited { retry_after: _wait })),
+            _ => return resp.error_for_status().map_err(io::Error::other),
+        }
+    }
+}
+
+// Downloads the archive of the whole peer list to '<tmp_dir>/peers.zip'
+pub fn download_archive(tmp_dir: &Path) -> io::Result<()> {
+    let mut resp = get(ARCHIVE_URL, "application/zip", None)?;
+    let mut out = fs::File::create(tmp_dir.join("peers.zip"))?;
+    io::copy(&mut resp, &mut out)?;
+    Ok(())
+}
+
+// Downloads only the files of the countries matching the filter into
+// '<tmp_dir>/public-peers-master/<region>/<country>.md' (the layout of the
+// archive). Returns None if the filter matches nothing or too many files, so
+// that the whole archive should be downloaded instead.
+pub fn fetch_countries(
+    tmp_dir: &Path,
+    country_filter: &str,
+    token: Option<&str>,
+) -> io::Result<Option<usize>> {
+    let tree: serde_json::Value = serde_json::from_reader(get(
+        &format!("{}/git/trees/{}?recursive=1", API_URL, BRANCH),
+        "application/vnd.github+json",
+        token,
+    )?)
+    .map_err(io::Error::other)?;
+
+    let paths: Vec<&str> = tree["tree"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry["type"] == "blob")
+        .filter_map(|entry| entry["path"].as_str())
+        .filter(|path| match path.split_once('/') {
+            Some((_region, _file)) => {
+                _region != "other"
+                    && !_file.contains('/')
+                    && _file.ends_with(".md")
+                    && crate::countries::country_matches(
+                        country_filter,
+                        _file,
+                        crate::countries::iso_code(_file).unwrap_or(""),
+                    )
+            }
+            _ => false,
+        })
+        .collect();
+
+    if paths.is_empty() || paths.len() > MAX_FILES {
+        return Ok(None);
+    }
+
+    let peers_dir = tmp_dir.join("public-peers-master");
+    for path in &paths {
+        let mut resp = get(
+            &format!("{}/contents/{}?ref={}", API_URL, path, BRANCH),
+            "application/vnd.github.raw",
+            token,
+        )?;
+        let out_path = peers_dir.join(path);
+        if let Some(_dir) = out_path.parent() {
+            fs::create_dir_all(_dir)?;
+        }
+        let mut out = fs::File::create(out_path)?;
+        io::copy(&mut resp, &mut out)?;
+    }
+
+    Ok(Some(paths.len()))
+}
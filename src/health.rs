@@ -0,0 +1,111 @@
+use crate::config_io::ConfigAccess;
+use crate::connectivity::IpFamilies;
+use crate::i18n::tr;
+use crate::logging::log_info;
+use crate::peer::Peer;
+use crate::using_api::ApiBackend;
+use nu_json::{Map, Value};
+use std::path::PathBuf;
+
+// '--only-if-stale': whether every configuration already has `needed` peers
+// that are alive and not slower than `max_latency` ms (all of them with a
+// percentage in '-n'). With the API the established sessions of the node are
+// checked, otherwise the configured peers are probed.
+pub fn current_peers_healthy(
+    conf_paths: &[PathBuf],
+    access: &ConfigAccess,
+    use_uci: bool,
+    api_backend: Option<&ApiBackend>,
+    needed: Option<usize>,
+    max_latency: u128,
+    families: &IpFamilies,
+) -> bool {
+    for conf_path in conf_paths {
+        let healthy = match api_backend {
+            Some(_backend) => conf_obj(conf_path, access, use_uci).and_then(|mut _co| {
+                let mut client = crate::using_api::ApiClient::new(&mut _co, _backend);
+                crate::using_api::healthy_sessions(&mut client, max_latency)
+            }),
+            _ => {
+                let uris = access.current_peers(conf_path, use_uci);
+                uris.map(|u| (healthy_peers(&u, max_latency, families), u.len()))
+            }
+        };
+
+        let (healthy, total) = healthy.unwrap_or((0, 0));
+        let needed = needed.unwrap_or(total.max(1));
+        if healthy < needed {
+            log_info!(
+                "{}",
+                tr!(
+                    "{}: {} of the current peers are healthy, {} are needed.",
+                    conf_path.display(),
+                    healthy,
+                    needed
+                )
+            );
+            return false;
+        }
+    }
+
+    true
+}
+
+// '--count-multicast': the healthy LAN multicast peers every node already
+// has (the fewest of all the configurations, 0 if a node can't be asked)
+pub fn lan_links(
+    conf_paths: &[PathBuf],
+    access: &ConfigAccess,
+    use_uci: bool,
+    api_backend: &ApiBackend,
+) -> usize {
+    conf_paths
+        .iter()
+        .map(|conf_path| {
+            conf_obj(conf_path, access, use_uci)
+                .and_then(|mut _co| {
+                    let mut client = crate::using_api::ApiClient::new(&mut _co, api_backend);
+                    crate::using_api::multicast_links(&mut client)
+                })
+                .unwrap_or(0)
+        })
+        .min()
+        .unwrap_or(0)
+}
+
+// The configuration object with the admin endpoint of the node
+fn conf_obj(
+    conf_path: &PathBuf,
+    access: &ConfigAccess,
+    use_uci: bool,
+) -> Option<Map<String, Value>> {
+    match use_uci {
+        true => Some(crate::uci_config::get_conf_obj(conf_path)),
+        _ => access
+            .read(conf_path)
+            .ok()
+            .and_then(|t| crate::parse_config::get_hjson_obj(&t).ok()),
+    }
+}
+
+// Probes the peers of the configuration at the same time
+fn healthy_peers(uris: &[String], max_latency: u128, families: &IpFamilies) -> usize {
+    std::thread::scope(|scope| {
+        let probes: Vec<_> = uris
+            .iter()
+            .filter_map(|uri| crate::parsing_peers::peer_from_uri(uri))
+            .map(|mut peer: Peer| {
+                scope.spawn(move || {
+                    crate::latency::set_latency(&mut peer, families, false)
+                        && peer.is_alive
+                        && peer.latency <= max_latency
+                })
+            })
+            .collect();
+        probes
+            .into_iter()
+            .filter_map(|probe| probe.join().ok())
+            .filter(|healthy| *healthy)
+            .count()
+    })
+}
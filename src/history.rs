@@ -0,0 +1,236 @@
+use crate::peer::Peer;
+use rusqlite::{params, Connection};
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn open(path: &Path) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(path)?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS probes (
+            id INTEGER PRIMARY KEY,
+            time INTEGER NOT NULL,
+            uri TEXT NOT NULL,
+            region TEXT NOT NULL,
+            country TEXT NOT NULL,
+            country_code TEXT NOT NULL,
+            is_alive INTEGER NOT NULL,
+            latency INTEGER
+        );
+        CREATE INDEX IF NOT EXISTS probes_uri_time ON probes (uri, time);",
+    )?;
+    Ok(conn)
+}
+
+// Recording the probe results of a single run (dead peers have no latency)
+pub fn record(conn: &mut Connection, peers: &[Peer]) -> rusqlite::Result<()> {
+    let now = unix_time();
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO probes (time, uri, region, country, country_code, is_alive, latency)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+        for peer in peers {
+            let latency: Option<i64> = match peer.is_alive {
+                true => Some(peer.latency as i64),
+                _ => None,
+            };
+            stmt.execute(params![
+                now,
+                crate::redact::redact(&peer.uri),
+                peer.region,
+                peer.country,
+                peer.country_code,
+                peer.is_alive,
+                latency
+            ])?;
+        }
+    }
+    tx.commit()
+}
+
+pub fn unix_time() -> i64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(_d) => _d.as_secs() as i64,
+        _ => 0,
+    }
+}
+
+pub struct ProbeRecord {
+    pub time: i64,
+    pub uri: String,
+    pub region: String,
+    pub country: String,
+    pub country_code: String,
+    pub is_alive: bool,
+    pub latency: Option<i64>,
+}
+
+pub fn load(conn: &Connection, since: i64) -> rusqlite::Result<Vec<ProbeRecord>> {
+    let mut stmt = conn.prepare(
+        "SELECT time, uri, region, country, country_code, is_alive, latency
+        FROM probes WHERE time >= ?1 ORDER BY time, uri",
+    )?;
+    let rows = stmt.query_map(params![since], |row| {
+        Ok(ProbeRecord {
+            time: row.get(0)?,
+            uri: row.get(1)?,
+            region: row.get(2)?,
+            country: row.get(3)?,
+            country_code: row.get(4)?,
+            is_alive: row.get(5)?,
+            latency: row.get(6)?,
+        })
+    })?;
+    rows.collect()
+}
+
+pub fn write_csv<W: Write>(records: &[ProbeRecord], out: &mut W) -> io::Result<()> {
+    writeln!(out, "time,uri,region,country,country_code,is_alive,latency")?;
+    for r in records {
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{}",
+            r.time,
+            csv_field(&r.uri),
+            csv_field(&r.region),
+            csv_field(&r.country),
+            csv_field(&r.country_code),
+            r.is_alive,
+            r.latency.map_or("".to_string(), |l| l.to_string())
+        )?;
+    }
+    Ok(())
+}
+
+pub fn write_json<W: Write>(records: &[ProbeRecord], out: &mut W) -> io::Result<()> {
+    let arr: Vec<serde_json::Value> = records
+        .iter()
+        .map(|r| {
+            serde_json::json!({
+                "time": r.time,
+                "uri": r.uri,
+                "region": r.region,
+                "country": r.country,
+                "country_code": r.country_code,
+                "is_alive": r.is_alive,
+                "latency": r.latency,
+            })
+        })
+        .collect();
+    serde_json::to_writer_pretty(&mut *out, &arr)?;
+    writeln!(out)
+}
+
+// A peer that didn't answer in any of the last runs
+pub struct DeadPeer {
+    pub uri: String,
+    pub region: String,
+    pub country: String,
+    // The first failed probe after the last successful one
+    pub since: i64,
+}
+
+// The peers probed and found dead in every one of the last `runs` runs (the
+// ones probed in fewer of them are not reported)
+pub struct DeadReport {
+    pub peers: Vec<DeadPeer>,
+    pub runs: usize,
+    // The first and the last of these runs, none until the history has enough
+    pub span: Option<(i64, i64)>,
+}
+
+pub fn dead_peers(conn: &Connection, runs: usize) -> rusqlite::Result<DeadReport> {
+    let mut stmt = conn.prepare("SELECT DISTINCT time FROM probes ORDER BY time DESC LIMIT ?1")?;
+    let times: Vec<i64> = stmt
+        .query_map(params![runs as i64], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    let (first, last) = match (times.last(), times.first()) {
+        (Some(_f), Some(_l)) if times.len() == runs => (*_f, *_l),
+        _ => {
+            return Ok(DeadReport {
+                peers: Vec::new(),
+                runs,
+                span: None,
+            })
+        }
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT p.uri, p.region, p.country,
+            (SELECT MIN(d.time) FROM probes d WHERE d.uri = p.uri AND d.time >
+                COALESCE((SELECT MAX(a.time) FROM probes a WHERE a.uri = p.uri AND a.is_alive), 0))
+        FROM probes p WHERE p.time >= ?1
+        GROUP BY p.uri
+        HAVING COUNT(DISTINCT p.time) = ?2 AND MAX(p.is_alive) = 0
+        ORDER BY p.region, p.country, p.uri",
+    )?;
+    let rows = stmt.query_map(params![first, runs as i64], |row| {
+        Ok(DeadPeer {
+            uri: row.get(0)?,
+            region: row.get(1)?,
+            country: row.get(2)?,
+            since: row.get::<_, Option<i64>>(3)?.unwrap_or(first),
+        })
+    })?;
+    Ok(DeadReport {
+        peers: rows.collect::<rusqlite::Result<_>>()?,
+        runs,
+        span: Some((first, last)),
+    })
+}
+
+// '--report-dead': a Markdown list grouped by the file of the public peers
+// repository, ready to be pasted into an issue or a pull request
+pub fn write_dead_report<W: Write>(report: &DeadReport, out: &mut W) -> io::Result<()> {
+    let runs = report.runs;
+    let (first, last) = match report.span {
+        Some(_s) => _s,
+        _ => {
+            return writeln!(
+                out,
+                "The history has fewer than {} runs, no peers can be reported yet.",
+                runs
+            )
+        }
+    };
+    writeln!(
+        out,
+        "The following peers didn't answer in any of the last {} checks ({} to {}):",
+        runs,
+        crate::report::utc_time(first),
+        crate::report::utc_time(last)
+    )?;
+    if report.peers.is_empty() {
+        writeln!(out)?;
+        return writeln!(out, "None.");
+    }
+
+    let mut file = String::new();
+    for peer in &report.peers {
+        let peer_file = format!("{}/{}.md", peer.region, peer.country);
+        if peer_file != file {
+            writeln!(out)?;
+            writeln!(out, "#### {}", peer_file)?;
+            writeln!(out)?;
+            file = peer_file;
+        }
+        writeln!(
+            out,
+            "- `{}` (not answering since {})",
+            peer.uri,
+            crate::report::utc_time(peer.since)
+        )?;
+    }
+    Ok(())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
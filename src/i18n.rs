@@ -0,0 +1,745 @@
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+// Messages are looked up by their English text (gettext-style), so a message
+// without a translation is simply printed in English
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Lang {
+    En,
+    Ru,
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+const RU: &[(&str, &str)] = &[
+    ("URI", "URI"),
+    ("Proto", "Прот."),
+    ("Region", "Регион"),
+    ("Country", "Страна"),
+    ("Latency", "Задержка"),
+    ("Min", "Мин."),
+    ("Avg", "Сред."),
+    ("Max", "Макс."),
+    ("Loss", "Потери"),
+    (
+        "Round {}: {} of {} peers are alive.",
+        "Раунд {}: доступно {} из {} пиров.",
+    ),
+    ("There are no peers to benchmark.", "Нет пиров для замера."),
+    (
+        "Incorrect configuration file format ({}). The file was not written to.",
+        "Неверный формат конфигурационного файла ({}). Файл не был изменён.",
+    ),
+    (
+        "The changes could not be written to the configuration file ({}).",
+        "Не удалось записать изменения в конфигурационный файл ({}).",
+    ),
+    (
+        "The changes could not be written to the configuration file.",
+        "Не удалось записать изменения в конфигурационный файл.",
+    ),
+    (
+        "Can't parse the config file {} ({})!",
+        "Не удалось разобрать конфигурационный файл {} ({})!",
+    ),
+    (
+        "The configuration file {} cannot be read ({}).",
+        "Не удалось прочитать конфигурационный файл {} ({}).",
+    ),
+    (
+        "The number of peers must be a number or a percentage of the alive peers from 0% to 100% ({}).",
+        "Количество пиров должно быть числом или процентом доступных пиров от 0% до 100% ({}).",
+    ),
+    (
+        "Parameters expected: '-p', '--check' or '-u' and (or) '-a'.",
+        "Ожидаются параметры: '-p', '--check' или '-u' и (или) '-a'.",
+    ),
+    (
+        "For more information try '-h'.",
+        "Для получения дополнительной информации используйте '-h'.",
+    ),
+    ("Nothing to do, exit.", "Делать нечего, выход."),
+    (
+        "The '--remote' parameter can't be used together with '-a' or '--config-format uci'.",
+        "Параметр '--remote' нельзя использовать вместе с '-a' или '--config-format uci'.",
+    ),
+    (
+        "Can't get the configuration file default path.",
+        "Не удалось получить путь к конфигурационному файлу по-умолчанию.",
+    ),
+    (
+        "The Yggdrasil configuration file does not exist ({}).",
+        "Конфигурационный файл Yggdrasil не существует ({}).",
+    ),
+    (
+        "There is no write access to the Yggdrasil configuration file ({}).",
+        "Нет доступа на запись к конфигурационному файлу Yggdrasil ({}).",
+    ),
+    (
+        "Failed to create a temporary directory ({}).",
+        "Не удалось создать временный каталог ({}).",
+    ),
+    (
+        "Failed to download archive with peers ({}).",
+        "Не удалось скачать архив с пирами ({}).",
+    ),
+    (
+        "Failed to unpack archive ({}).",
+        "Не удалось распаковать архив ({}).",
+    ),
+    (
+        "No global IPv4 connectivity detected, IPv4 peers will be skipped.",
+        "Глобальная связность по IPv4 не обнаружена, IPv4-пиры будут пропущены.",
+    ),
+    (
+        "No global IPv6 connectivity detected, IPv6 peers will be skipped.",
+        "Глобальная связность по IPv6 не обнаружена, IPv6-пиры будут пропущены.",
+    ),
+    (
+        "Couldn't get peer addresses from downloaded files ({}).",
+        "Не удалось получить адреса пиров из скачанных файлов ({}).",
+    ),
+    (
+        "Couldn't get peer addresses from downloaded files.",
+        "Не удалось получить адреса пиров из скачанных файлов.",
+    ),
+    (
+        "Failed to save the probe results to the history ({}).",
+        "Не удалось сохранить результаты проверки в историю ({}).",
+    ),
+    (
+        "Failed to open the history database ({}).",
+        "Не удалось открыть базу данных истории ({}).",
+    ),
+    (
+        "Probed {} peers, {} of them are alive.",
+        "Проверено пиров: {}, из них доступно: {}.",
+    ),
+    (
+        "The changes could not be written to the configuration file on {} ({}).",
+        "Не удалось записать изменения в конфигурационный файл на {} ({}).",
+    ),
+    (
+        "{}: DNS {}, TCP connect {}, TLS handshake {}",
+        "{}: DNS {}, TCP-подключение {}, TLS-рукопожатие {}",
+    ),
+    (
+        "The path to the history database is required ('--history').",
+        "Требуется путь к базе данных истории ('--history').",
+    ),
+    (
+        "Failed to read the history database ({}).",
+        "Не удалось прочитать базу данных истории ({}).",
+    ),
+    (
+        "Failed to export the history ({}).",
+        "Не удалось выгрузить историю ({}).",
+    ),
+    (
+        "Failed to parse files ({}).",
+        "Не удалось разобрать файлы ({}).",
+    ),
+    (
+        "Failed to restart the Yggdrasil service on {} ({}).",
+        "Не удалось перезапустить сервис Yggdrasil на {} ({}).",
+    ),
+    (
+        "Incorrect UCI configuration path. The file was not written to.",
+        "Неверный путь к конфигурации UCI. Файл не был изменён.",
+    ),
+    (
+        "Failed to add the peer {} to the UCI configuration.",
+        "Не удалось добавить пир {} в конфигурацию UCI.",
+    ),
+    (
+        "The changes could not be committed to the UCI configuration.",
+        "Не удалось сохранить изменения в конфигурации UCI.",
+    ),
+    (
+        "Failed to restart the Yggdrasil service ({}).",
+        "Не удалось перезапустить сервис Yggdrasil ({}).",
+    ),
+    (
+        "Failed to reload the Yggdrasil service ({}).",
+        "Не удалось перезагрузить сервис Yggdrasil ({}).",
+    ),
+    (
+        "Can't get connected peers ({}).",
+        "Не удалось получить подключенные пиры ({}).",
+    ),
+    ("Socket I/O error ({}).", "Ошибка ввода-вывода сокета ({})."),
+    (
+        "Unable to connect to the administrator socket.",
+        "Не удалось подключиться к сокету администратора.",
+    ),
+    (
+        "Error converting a json string to an object ({}).",
+        "Ошибка преобразования строки json в объект ({}).",
+    ),
+    (
+        "Couldn't get response from the getpeers result.",
+        "Не удалось получить response из результата getpeers.",
+    ),
+    (
+        "Couldn't get peers from the response obj.",
+        "Не удалось получить peers из объекта response.",
+    ),
+    (
+        "Couldn't get peers from the response obj (0002).",
+        "Не удалось получить peers из объекта response (0002).",
+    ),
+    (
+        "Couldn't get peers array from the the response obj.",
+        "Не удалось получить массив peers из объекта response.",
+    ),
+    (
+        "Failed to connect via TCP stream ({}).",
+        "Не удалось подключиться по TCP ({}).",
+    ),
+    (
+        "Failed to connect via unix domain socket ({}).",
+        "Не удалось подключиться через unix-сокет ({}).",
+    ),
+    (
+        "It is not possible to use a unix socket in Windows.",
+        "В Windows невозможно использовать unix-сокет.",
+    ),
+    (
+        "Unable to parse socket URI ({}).",
+        "Не удалось разобрать URI сокета ({}).",
+    ),
+    (
+        "Unable to parse socket URI (failed to get host from URI).",
+        "Не удалось разобрать URI сокета (не удалось получить хост из URI).",
+    ),
+    (
+        "Failed to resolve host from socket URI.",
+        "Не удалось разрешить имя хоста из URI сокета.",
+    ),
+    (
+        "Unable to parse socket URI (failed to get port from URI).",
+        "Не удалось разобрать URI сокета (не удалось получить порт из URI).",
+    ),
+    (
+        "Unknown type of administrative socket address.",
+        "Неизвестный тип адреса сокета администратора.",
+    ),
+    (
+        "The '--escalate' command is empty.",
+        "Команда '--escalate' пуста.",
+    ),
+    (
+        "The '--escalate' parameter can't be used together with '--config-format uci'.",
+        "Параметр '--escalate' нельзя использовать вместе с '--config-format uci'.",
+    ),
+    (
+        "Scheduled Tasks are only available on Windows, use cron or a systemd timer.",
+        "Задания планировщика доступны только в Windows, используйте cron или таймер systemd.",
+    ),
+    (
+        "The interval can't be represented as a schedule ({}s).",
+        "Интервал невозможно представить в виде расписания ({} с).",
+    ),
+    (
+        "Failed to register the Scheduled Task ({}).",
+        "Не удалось зарегистрировать задание планировщика ({}).",
+    ),
+    (
+        "The Scheduled Task '{}' has been registered.",
+        "Задание планировщика '{}' зарегистрировано.",
+    ),
+    (
+        "Failed to set up the signal handlers ({}).",
+        "Не удалось установить обработчики сигналов ({}).",
+    ),
+    (
+        "The update cycle failed (exit code {}).",
+        "Цикл обновления завершился с ошибкой (код выхода {}).",
+    ),
+    (
+        "Failed to answer an HTTP request ({}).",
+        "Не удалось ответить на HTTP-запрос ({}).",
+    ),
+    (
+        "Failed to listen for HTTP requests on {} ({}).",
+        "Не удалось принимать HTTP-запросы на {} ({}).",
+    ),
+    (
+        "The last update was {}s ago, which is less than '--min-interval'. Nothing to do.",
+        "Последнее обновление было {} с назад, это меньше, чем '--min-interval'. Делать нечего.",
+    ),
+    (
+        "Failed to read the state file ({}).",
+        "Не удалось прочитать файл состояния ({}).",
+    ),
+    (
+        "Failed to save the state file ({}).",
+        "Не удалось сохранить файл состояния ({}).",
+    ),
+    (
+        "The running Yggdrasil node uses the configuration file {}.",
+        "Работающий узел Yggdrasil использует конфигурационный файл {}.",
+    ),
+    (
+        "Warning: the running Yggdrasil node uses the configuration file {}, which is not being updated.",
+        "Предупреждение: работающий узел Yggdrasil использует конфигурационный файл {}, который не обновляется.",
+    ),
+    (
+        "Only {} alive peers were found, at least {} are required ('--min-peers'). The configuration was left unchanged.",
+        "Найдено доступных пиров: {}, требуется не менее {} ('--min-peers'). Конфигурация оставлена без изменений.",
+    ),
+    (
+        "{} to ports {}: {} of {} peers are alive.",
+        "{} на порты {}: доступно {} из {} пиров.",
+    ),
+    (
+        "The template file {} cannot be read ({}).",
+        "Не удалось прочитать файл шаблона {} ({}).",
+    ),
+    (
+        "'--format template' requires '--template' or '--template-file'.",
+        "Для '--format template' требуется '--template' или '--template-file'.",
+    ),
+    (
+        "Failed to write the peer list to {} ({}).",
+        "Не удалось записать список пиров в {} ({}).",
+    ),
+    (
+        "Neither NetworkManager nor networkd-dispatcher was found, use '--dispatcher'.",
+        "Не найдены ни NetworkManager, ни networkd-dispatcher, используйте '--dispatcher'.",
+    ),
+    (
+        "Can't get the path of the executable ({}).",
+        "Не удалось получить путь к исполняемому файлу ({}).",
+    ),
+    (
+        "Failed to install the hook {} ({}).",
+        "Не удалось установить хук {} ({}).",
+    ),
+    ("The hook {} has been installed.", "Хук {} установлен."),
+    (
+        "There is no internet connectivity ({}). The configuration was left unchanged.",
+        "Нет доступа в интернет ({}). Конфигурация оставлена без изменений.",
+    ),
+    (
+        "The connectivity check answered with HTTP {}, probably a captive portal. The configuration was left unchanged.",
+        "Проверка связи вернула HTTP {}, вероятно, это страница авторизации в сети. Конфигурация оставлена без изменений.",
+    ),
+    (
+        "{} peers are connected, {} will be added.",
+        "Подключено пиров: {}, будет добавлено: {}.",
+    ),
+    (
+        "{} peers were removed to stay within '--max-peers'.",
+        "Удалено пиров для соблюдения '--max-peers': {}.",
+    ),
+    (
+        "Incorrect '--api-backend' value ({}).",
+        "Неверное значение '--api-backend' ({}).",
+    ),
+    ("Failed to run {} ({}).", "Не удалось запустить {} ({})."),
+    (
+        "Can't read the settings file ({}).",
+        "Не удалось прочитать файл настроек ({}).",
+    ),
+    (
+        "Can't parse the settings file ({}).",
+        "Не удалось разобрать файл настроек ({}).",
+    ),
+    (
+        "'profiles' in the settings file must be an object.",
+        "'profiles' в файле настроек должен быть объектом.",
+    ),
+    (
+        "There is no profile '{}' in the settings file.",
+        "В файле настроек нет профиля '{}'.",
+    ),
+    (
+        "The profile '{}' must be an object.",
+        "Профиль '{}' должен быть объектом.",
+    ),
+    (
+        "Unsupported value of '{}' in the settings file.",
+        "Неподдерживаемое значение '{}' в файле настроек.",
+    ),
+    ("Using the profile '{}'.", "Используется профиль '{}'."),
+    (
+        "Failed to fetch the country files via the GitHub API, downloading the archive ({}).",
+        "Не удалось получить файлы стран через GitHub API, загружается архив ({}).",
+    ),
+    (
+        "GitHub rate limit exceeded, retry in {} s",
+        "Превышен лимит запросов к GitHub, повторите через {} с",
+    ),
+    (
+        "GitHub rate limit exceeded, waiting {} s.",
+        "Превышен лимит запросов к GitHub, ожидание {} с.",
+    ),
+    (
+        "Failed to save the peer list cache ({}).",
+        "Не удалось сохранить кэш списка пиров ({}).",
+    ),
+    (
+        "Failed to read the peer list cache ({}).",
+        "Не удалось прочитать кэш списка пиров ({}).",
+    ),
+    (
+        "{}. The cached peer list is used.",
+        "{}. Используется сохранённый список пиров.",
+    ),
+    (
+        "{}: added {} peers, removed {}, kept {} pinned, {}",
+        "{}: добавлено пиров: {}, удалено: {}, сохранено закреплённых: {}, {}",
+    ),
+    ("config written", "конфигурация записана"),
+    ("config not written", "конфигурация не записана"),
+    ("Service restarted", "Служба перезапущена"),
+    ("Service restart failed", "Не удалось перезапустить службу"),
+    (
+        "Failed to write the report ({}).",
+        "Не удалось записать отчёт ({}).",
+    ),
+    (
+        "Incorrect '--log-target' value ({}).",
+        "Неверное значение '--log-target' ({}).",
+    ),
+    ("Selected {} ({} ms).", "Выбран {} ({} мс)."),
+    (
+        "'notify' in the settings file must be an array.",
+        "'notify' в файле настроек должен быть массивом.",
+    ),
+    (
+        "The '{}' notifier requires '{}'.",
+        "Для уведомлений '{}' требуется '{}'.",
+    ),
+    (
+        "Incorrect port of the '{}' notifier.",
+        "Неверный порт для уведомлений '{}'.",
+    ),
+    ("Unknown notifier type '{}'.", "Неизвестный тип уведомлений '{}'."),
+    (
+        "Unknown notification event '{}'.",
+        "Неизвестное событие для уведомлений '{}'.",
+    ),
+    (
+        "peers_updater on {}: the peers were updated",
+        "peers_updater на {}: пиры обновлены",
+    ),
+    (
+        "peers_updater on {}: the update failed",
+        "peers_updater на {}: обновление не удалось",
+    ),
+    (
+        "Failed to send the {} notification ({}).",
+        "Не удалось отправить уведомление {} ({}).",
+    ),
+    (
+        "Failed to register the event log source ({}).",
+        "Не удалось зарегистрировать источник журнала событий ({}).",
+    ),
+    (
+        "Termux has no service manager by default, restart Yggdrasil manually or install termux-services and run 'sv-enable {}'.",
+        "В Termux по-умолчанию нет менеджера служб, перезапустите Yggdrasil вручную или установите termux-services и выполните 'sv-enable {}'.",
+    ),
+    (
+        "Only {} alive peers pass the filters, fewer than {} ('--fail-if-fewer-than'). Nothing was changed.",
+        "Фильтры проходят только {} живых пиров, меньше чем {} ('--fail-if-fewer-than'). Ничего не изменено.",
+    ),
+    ("Waiting for the network...", "Ожидание сети..."),
+    (
+        "The network did not come up within '--wait-for-network'.",
+        "Сеть не появилась за время '--wait-for-network'.",
+    ),
+    (
+        "The run failed, retrying in {} s.",
+        "Запуск не удался, повтор через {} с.",
+    ),
+    (
+        "'--stream' can only be used with '--format table'.",
+        "'--stream' можно использовать только с '--format table'.",
+    ),
+    (
+        "'--group-by' can only be used with '--format table'.",
+        "'--group-by' можно использовать только с '--format table'.",
+    ),
+    (
+        "{}: {} of {} peers are alive, the best is {} ms",
+        "{}: доступно {} из {} пиров, лучший — {} мс",
+    ),
+    (
+        "{}: none of {} peers are alive",
+        "{}: ни один из {} пиров не доступен",
+    ),
+    ("Yggdrasil peers", "Пиры Yggdrasil"),
+    (
+        "{}, peers_updater {}, exit code {}",
+        "{}, peers_updater {}, код завершения {}",
+    ),
+    ("{} of {} peers are alive", "Доступно {} из {} пиров"),
+    (
+        "Failed to push the metrics to InfluxDB ({}).",
+        "Не удалось отправить метрики в InfluxDB ({}).",
+    ),
+    (
+        "Failed to push the metrics to Graphite ({}).",
+        "Не удалось отправить метрики в Graphite ({}).",
+    ),
+    (
+        "{}: {} of the current peers are healthy, {} are needed.",
+        "{}: исправны {} из текущих пиров, требуется {}.",
+    ),
+    (
+        "The current peers are healthy ('--only-if-stale'). Nothing to do.",
+        "Текущие пиры исправны ('--only-if-stale'). Делать нечего.",
+    ),
+    (
+        "Only {} of the selected peers are connected after {}s, the old peers were kept.",
+        "Подключено только {} из выбранных пиров спустя {}с, старые пиры оставлены.",
+    ),
+    (
+        "Failed to add the peer {} ({}).",
+        "Не удалось добавить пир {} ({}).",
+    ),
+    (
+        "Failed to remove the peer {} ({}).",
+        "Не удалось удалить пир {} ({}).",
+    ),
+    (
+        "The scoring command failed ({}). The configuration was left unchanged.",
+        "Ошибка команды оценки ({}). Конфигурация оставлена без изменений.",
+    ),
+    ("an entry without 'uri' ({})", "запись без 'uri' ({})"),
+    (
+        "Only {} of the {} sampled peers are alive, probing more.",
+        "Доступно только {} из {} проверенных пиров выборки, проверяются ещё.",
+    ),
+    (
+        "Failed to write the dead peer report ({}).",
+        "Не удалось записать отчёт о недоступных пирах ({}).",
+    ),
+    (
+        "Failed to watch the network changes ({}).",
+        "Не удалось отслеживать изменения сети ({}).",
+    ),
+    (
+        "'--watch-routes' is only supported on Linux.",
+        "'--watch-routes' поддерживается только в Linux.",
+    ),
+    (
+        "The network has changed, updating the peers.",
+        "Сеть изменилась, пиры обновляются.",
+    ),
+    (
+        "'{}' is not an IP address (binding to an interface is only supported on Linux).",
+        "'{}' — не IP-адрес (привязка к интерфейсу поддерживается только в Linux).",
+    ),
+    (
+        "an unclosed comment in line {}",
+        "незакрытый комментарий в строке {}",
+    ),
+    (
+        "an unclosed string in line {}",
+        "незакрытая строка в строке {}",
+    ),
+    ("'Peers' is not an array", "'Peers' не является массивом"),
+    (
+        "an unexpected '{}' in line {}",
+        "неожиданный '{}' в строке {}",
+    ),
+    (
+        "no ':' after '{}' in line {}",
+        "нет ':' после '{}' в строке {}",
+    ),
+    (
+        "the Peers array is not closed",
+        "массив Peers не закрыт",
+    ),
+    ("no Peers section", "нет раздела Peers"),
+    (
+        "The run exceeded '--max-runtime' while downloading the peer list ({}). The configuration was left unchanged.",
+        "Запуск превысил '--max-runtime' во время загрузки списка пиров ({}). Конфигурация оставлена без изменений.",
+    ),
+    (
+        "The run exceeded '--max-runtime' with only {} alive peers probed. The configuration was left unchanged.",
+        "Запуск превысил '--max-runtime', проверено только {} доступных пиров. Конфигурация оставлена без изменений.",
+    ),
+    (
+        "The run exceeded '--max-runtime', the {} peers probed so far are used.",
+        "Запуск превысил '--max-runtime', используются {} уже проверенных пиров.",
+    ),
+    (
+        "Failed to download archive with peers ({}). The cached peer list is used.",
+        "Не удалось скачать архив с пирами ({}). Используется сохранённый список пиров.",
+    ),
+    (
+        "Failed to download archive with peers ({}). There is no cached peer list, the current peers are probed again.",
+        "Не удалось скачать архив с пирами ({}). Сохранённого списка пиров нет, текущие пиры проверяются заново.",
+    ),
+    ("Code", "Код"),
+    ("Countries", "Стран"),
+    ("Peers", "Пиров"),
+    (
+        "Failed to get the peer list ({}).",
+        "Не удалось получить список пиров ({}).",
+    ),
+    (
+        "Can't read the inventory ({}).",
+        "Не удалось прочитать файл с целями ({}).",
+    ),
+    (
+        "Can't parse the inventory ({}).",
+        "Не удалось разобрать файл с целями ({}).",
+    ),
+    (
+        "The inventory must have a 'targets' array.",
+        "В файле с целями должен быть массив 'targets'.",
+    ),
+    (
+        "Every target of the inventory must be an object.",
+        "Каждая цель в файле с целями должна быть объектом.",
+    ),
+    (
+        "A target of the inventory has no 'name'.",
+        "У одной из целей в файле с целями нет 'name'.",
+    ),
+    (
+        "The target '{}' must have one of 'config', 'ssh' or 'admin'.",
+        "У цели '{}' должно быть что-то одно из 'config', 'ssh' или 'admin'.",
+    ),
+    (
+        "The target '{}' has an incorrect 'number' ({}).",
+        "У цели '{}' неверный 'number' ({}).",
+    ),
+    ("Target", "Цель"),
+    ("Result", "Результат"),
+    ("failed: {}", "ошибка: {}"),
+    (
+        "no alive peers pass the filters, left unchanged",
+        "нет доступных пиров, подходящих под фильтры, оставлено без изменений",
+    ),
+    (
+        "applied through the admin API",
+        "применено через admin API",
+    ),
+    (
+        "incorrect configuration file format",
+        "неверный формат конфигурационного файла",
+    ),
+    ("unchanged", "без изменений"),
+    ("written, restarted", "записано, перезапущено"),
+    (
+        "written, the restart failed",
+        "записано, перезапуск не удался",
+    ),
+    ("written", "записано"),
+    (
+        "the admin API is unreachable",
+        "admin API недоступен",
+    ),
+    ("operator: {}", "оператор: {}"),
+    ("contact: {}", "контакт: {}"),
+    ("note: {}", "примечание: {}"),
+    (
+        "{} healthy LAN multicast peers count toward '-n', {} public peers are selected.",
+        "{} исправных пиров из локальной сети (multicast) учтены в '-n', выбрано публичных пиров: {}.",
+    ),
+    (
+        "'--supervise': {} has been down or slow for {} s, but no other alive peer is left to replace it with.",
+        "'--supervise': {} недоступен или медленный уже {} с, но других живых пиров для замены не осталось.",
+    ),
+    (
+        "'--supervise': {} has been down or slow for {} s, replaced with {}.",
+        "'--supervise': {} недоступен или медленный уже {} с, заменён на {}.",
+    ),
+    (
+        "The applied peer {} is not in the peer list anymore.",
+        "Применённого пира {} больше нет в списке пиров.",
+    ),
+    (
+        "Failed to answer a control socket request ({}).",
+        "Не удалось ответить на запрос управляющего сокета ({}).",
+    ),
+    (
+        "Failed to listen on the control socket {} ({}).",
+        "Не удалось открыть управляющий сокет {} ({}).",
+    ),
+    (
+        "'--control-socket' is only supported on Unix.",
+        "'--control-socket' поддерживается только в Unix.",
+    ),
+    (
+        "The settings file can't be applied, the previous settings are used ({}).",
+        "Не удалось применить файл настроек, используются прежние настройки ({}).",
+    ),
+    (
+        "{} to ports {} seems to be blocked by this network: none of the {} sampled peers answered, the other {} are not probed.",
+        "{} на порты {}, похоже, блокируется этой сетью: ни один из {} проверенных пиров не ответил, остальные {} не замеряются.",
+    ),
+    (
+        "No peers were selected, the current peers of the node were kept.",
+        "Не выбрано ни одного пира, текущие пиры узла оставлены.",
+    ),
+];
+
+// Selecting the language: the '--lang' value or the locale environment variables
+pub fn init(lang: Option<&str>) {
+    let lang = match lang {
+        Some(_l) => _l.to_string(),
+        _ => ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|v| std::env::var(v).ok())
+            .find(|v| !v.is_empty())
+            .unwrap_or_default(),
+    };
+
+    let _ = LANG.set(match lang.to_lowercase().starts_with("ru") {
+        true => Lang::Ru,
+        _ => Lang::En,
+    });
+}
+
+pub fn translate(msg: &'static str) -> &'static str {
+    let catalog = match LANG.get() {
+        Some(Lang::Ru) => RU,
+        _ => return msg,
+    };
+
+    match catalog.iter().find(|(en, _)| *en == msg) {
+        Some((_, _tr)) => _tr,
+        _ => msg,
+    }
+}
+
+// Substitutes the arguments for the '{}' placeholders in order
+pub fn format(msg: &str, args: &[&dyn Display]) -> String {
+    let mut result = String::with_capacity(msg.len());
+    let mut args = args.iter();
+    let mut parts = msg.split("{}");
+
+    if let Some(_first) = parts.next() {
+        result.push_str(_first);
+    }
+    for part in parts {
+        if let Some(_arg) = args.next() {
+            result.push_str(&_arg.to_string());
+        }
+        result.push_str(part);
+    }
+
+    result
+}
+
+macro_rules! tr {
+    ($msg:expr) => {
+        crate::i18n::translate($msg).to_string()
+    };
+    ($msg:expr, $($arg:expr),+ $(,)?) => {
+        crate::i18n::format(
+            crate::i18n::translate($msg),
+            &[$(&$arg as &dyn std::fmt::Display),+],
+        )
+    };
+}
+
+pub(crate) use tr;
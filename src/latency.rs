@@ -1,35 +1,95 @@
+use crate::connectivity::IpFamilies;
 use crate::peer::Peer;
-use std::net::{SocketAddr, TcpStream};
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+use std::net::TcpStream;
 use std::time;
 
-pub fn set_latency(peer: &mut Peer) {
-    let ip_addr = match crate::resolve::resolve(&peer.addr) {
+// Returns false if the peer was skipped because none of its addresses
+// belong to an IP family reachable from this host, or if the probe was cut
+// off by '--max-runtime'.
+// With `detailed` the stage timings are recorded.
+pub fn set_latency(peer: &mut Peer, families: &IpFamilies, detailed: bool) -> bool {
+    let now = time::Instant::now();
+    let ips = match crate::resolve::resolve_all(&peer.addr) {
         Some(_a) => _a,
         _ => {
             peer.is_alive = false;
-            return;
+            return true;
         }
     };
+    if detailed {
+        peer.timings.dns = Some(now.elapsed());
+    }
+
+    // A link-local address (with a zone ID) needs no global route
+    let zoned = crate::uri_normalize::split_zone(&peer.addr).1.is_some();
+    let ip_addr = match ips.into_iter().find(|ip| zoned || families.allows(ip)) {
+        Some(_ip) => _ip,
+        _ => return false,
+    };
 
-    let addr = match format!("{}:{}", ip_addr, peer.port)
-        .as_str()
-        .parse::<SocketAddr>()
-    {
-        Ok(_a) => _a,
+    let addr = match peer.port.parse::<u16>() {
+        Ok(_p) => match crate::resolve::socket_addr(&peer.addr, ip_addr, _p) {
+            Some(_a) => _a,
+            _ => return true,
+        },
         _ => {
-            return;
+            return true;
         }
     };
 
+    // Not counted in the latency
+    crate::rate_limit::wait();
     let now = time::Instant::now();
 
-    let stream = match TcpStream::connect_timeout(&addr, time::Duration::from_secs(10)) {
-        Ok(_s) => _s,
-        _ => {
-            return;
+    let stream =
+        match crate::bind::connect(&addr, crate::deadline::cap(time::Duration::from_secs(10))) {
+            Ok(_s) => _s,
+            // Cut off by '--max-runtime': not a result
+            _ if crate::deadline::expired() => return false,
+            _ => {
+                return true;
+            }
+        };
+    let latency = now.elapsed();
+    if detailed {
+        peer.timings.connect = Some(latency);
+    }
+
+    // A TLS peer is only usable if the handshake succeeds
+    if peer.proto == "tls" {
+        let now = time::Instant::now();
+        if !tls_handshake(stream, &peer.addr) {
+            return !crate::deadline::expired();
         }
-    };
+        if detailed {
+            peer.timings.tls = Some(now.elapsed());
+        }
+    }
+
     peer.is_alive = true;
-    peer.latency = now.elapsed().as_millis();
-    drop(stream);
+    peer.latency = latency.as_millis();
+
+    true
+}
+
+fn tls_handshake(stream: TcpStream, host: &str) -> bool {
+    let timeout = crate::deadline::cap(time::Duration::from_secs(10));
+    let _ = stream.set_read_timeout(Some(timeout));
+    let _ = stream.set_write_timeout(Some(timeout));
+
+    // Peers use self-signed certificates
+    let mut builder = match SslConnector::builder(SslMethod::tls()) {
+        Ok(_b) => _b,
+        _ => return false,
+    };
+    builder.set_verify(SslVerifyMode::NONE);
+
+    match builder.build().configure() {
+        Ok(_c) => _c
+            .verify_hostname(false)
+            .connect(crate::uri_normalize::split_zone(host).0, stream)
+            .is_ok(),
+        _ => false,
+    }
 }
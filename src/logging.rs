@@ -0,0 +1,243 @@
+use std::sync::OnceLock;
+
+// Where the messages go ('--log-target')
+#[derive(Clone, Copy, PartialEq)]
+pub enum Target {
+    Stderr,
+    #[cfg(unix)]
+    Syslog,
+    #[cfg(unix)]
+    Journald,
+    // The Application event log of Windows
+    #[cfg(windows)]
+    EventLog,
+}
+
+impl Target {
+    pub fn parse(name: &str) -> Result<Target, String> {
+        match name {
+            "stderr" => Ok(Target::Stderr),
+            #[cfg(unix)]
+            "syslog" => Ok(Target::Syslog),
+            #[cfg(unix)]
+            "journald" => Ok(Target::Journald),
+            #[cfg(windows)]
+            "eventlog" => Ok(Target::EventLog),
+            _ => Err(name.to_string()),
+        }
+    }
+}
+
+// The syslog severities
+#[derive(Clone, Copy)]
+pub enum Priority {
+    Err = 3,
+    Warning = 4,
+    Info = 6,
+}
+
+static TARGET: OnceLock<Target> = OnceLock::new();
+
+pub fn init(target: Target) {
+    let _ = TARGET.set(target);
+}
+
+fn target() -> Target {
+    *TARGET.get().unwrap_or(&Target::Stderr)
+}
+
+// A message that used to be printed to stderr
+pub fn message(priority: Priority, msg: &str) {
+    message_with_fields(priority, msg, &[]);
+}
+
+// The fields (e.g. PEER=, LATENCY=) are only kept by journald
+#[cfg_attr(not(unix), allow(unused_variables))]
+pub fn message_with_fields(priority: Priority, msg: &str, fields: &[(&str, String)]) {
+    match target() {
+        Target::Stderr => eprintln!("{}", msg),
+        #[cfg(unix)]
+        Target::Syslog => {
+            if unix::syslog(priority, msg).is_err() {
+                eprintln!("{}", msg);
+            }
+        }
+        #[cfg(unix)]
+        Target::Journald => {
+            if unix::journald(priority, msg, fields).is_err() {
+                eprintln!("{}", msg);
+            }
+        }
+        #[cfg(windows)]
+        Target::EventLog => {
+            if windows::event_log(priority, msg).is_err() {
+                eprintln!("{}", msg);
+            }
+        }
+    }
+}
+
+// The result of the run: printed to stdout, or logged when logging to the system log
+#[cfg_attr(not(any(unix, windows)), allow(unreachable_patterns))]
+pub fn output(msg: &str) {
+    match target() {
+        Target::Stderr => println!("{}", msg),
+        _ => message(Priority::Info, msg),
+    }
+}
+
+// Records that only make sense in the system log (one per selected peer)
+pub fn log_only(priority: Priority, msg: &str, fields: &[(&str, String)]) {
+    if target() != Target::Stderr {
+        message_with_fields(priority, msg, fields);
+    }
+}
+
+macro_rules! log_error {
+    ($($arg:tt)*) => {
+        crate::logging::message(crate::logging::Priority::Err, &format!($($arg)*))
+    };
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        crate::logging::message(crate::logging::Priority::Warning, &format!($($arg)*))
+    };
+}
+
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        crate::logging::message(crate::logging::Priority::Info, &format!($($arg)*))
+    };
+}
+
+pub(crate) use {log_error, log_info, log_warn};
+
+#[cfg(unix)]
+mod unix {
+    use super::Priority;
+    use std::io;
+    use std::os::unix::net::UnixDatagram;
+
+    const IDENTIFIER: &str = "peers_updater";
+    // LOG_DAEMON
+    const FACILITY: u8 = 3;
+
+    pub fn syslog(priority: Priority, msg: &str) -> io::Result<()> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect("/dev/log")?;
+        for line in msg.lines().filter(|l| !l.is_empty()) {
+            socket.send(
+                format!(
+                    "<{}>{}[{}]: {}",
+                    FACILITY * 8 + priority as u8,
+                    IDENTIFIER,
+                    std::process::id(),
+                    line
+                )
+                .as_bytes(),
+            )?;
+        }
+        Ok(())
+    }
+
+    // The native journald protocol: 'KEY=value' lines, the values with line
+    // breaks are sent as 'KEY\n<64-bit little-endian length><value>\n'
+    pub fn journald(priority: Priority, msg: &str, fields: &[(&str, String)]) -> io::Result<()> {
+        let mut datagram: Vec<u8> = Vec::new();
+        let mut add = |key: &str, value: &str| {
+            datagram.extend(key.as_bytes());
+            if value.contains('\n') {
+                datagram.push(b'\n');
+                datagram.extend((value.len() as u64).to_le_bytes());
+            } else {
+                datagram.push(b'=');
+            }
+            datagram.extend(value.as_bytes());
+            datagram.push(b'\n');
+        };
+
+        add("MESSAGE", msg);
+        add("PRIORITY", &(priority as u8).to_string());
+        add("SYSLOG_IDENTIFIER", IDENTIFIER);
+        for (key, value) in fields {
+            add(key, value);
+        }
+
+        let socket = UnixDatagram::unbound()?;
+        socket.send_to(&datagram, "/run/systemd/journal/socket")?;
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use super::Priority;
+    use std::ffi::c_void;
+    use std::io;
+    use std::ptr;
+
+    // The source is registered by 'install-task' with the message file of .NET
+    // that shows the text as it is
+    const EVENT_ID: u32 = 1000;
+
+    const EVENTLOG_ERROR_TYPE: u16 = 0x0001;
+    const EVENTLOG_WARNING_TYPE: u16 = 0x0002;
+    const EVENTLOG_INFORMATION_TYPE: u16 = 0x0004;
+
+    #[link(name = "advapi32")]
+    extern "system" {
+        fn RegisterEventSourceW(server: *const u16, source: *const u16) -> *mut c_void;
+        fn ReportEventW(
+            log: *mut c_void,
+            kind: u16,
+            category: u16,
+            event_id: u32,
+            user_sid: *const c_void,
+            num_strings: u16,
+            data_size: u32,
+            strings: *const *const u16,
+            data: *const c_void,
+        ) -> i32;
+        fn DeregisterEventSource(log: *mut c_void) -> i32;
+    }
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+
+    pub fn event_log(priority: Priority, msg: &str) -> io::Result<()> {
+        let kind = match priority {
+            Priority::Err => EVENTLOG_ERROR_TYPE,
+            Priority::Warning => EVENTLOG_WARNING_TYPE,
+            Priority::Info => EVENTLOG_INFORMATION_TYPE,
+        };
+        let source = wide(crate::task_scheduler::EVENT_SOURCE);
+        let text = wide(msg);
+        let strings = [text.as_ptr()];
+
+        unsafe {
+            let log = RegisterEventSourceW(ptr::null(), source.as_ptr());
+            if log.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let reported = ReportEventW(
+                log,
+                kind,
+                0,
+                EVENT_ID,
+                ptr::null(),
+                1,
+                0,
+                strings.as_ptr(),
+                ptr::null(),
+            );
+            let result = match reported {
+                0 => Err(io::Error::last_os_error()),
+                _ => Ok(()),
+            };
+            DeregisterEventSource(log);
+            result
+        }
+    }
+}
@@ -1,243 +1,1627 @@
+use crate::cfg_file_modify::Pinned;
+use crate::config_io::ConfigAccess;
+use crate::escalate::Escalation;
+use crate::i18n::tr;
+use crate::logging::{log_error, log_info, log_warn};
 use crate::peer::Peer;
+use crate::remote::Remote;
+use clap::parser::ValueSource;
 use nu_json::Map;
+use std::collections::HashSet;
 use std::fs;
-use std::fs::File;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::mpsc;
 use tempfile::Builder;
 
+mod bench;
+mod bind;
 mod cfg_file_modify;
+mod check;
 mod clap_args;
+mod config_io;
+mod connectivity;
+mod control;
+mod countries;
+mod daemon;
+mod deadline;
 mod defaults;
+mod detect_config;
+mod discover;
+mod dispatcher;
+mod duration;
+mod escalate;
+mod fleet;
+mod github;
+mod health;
+mod history;
+mod i18n;
 mod latency;
+mod logging;
+mod metrics;
+mod notify;
+mod output;
 mod parse_config;
 mod parsing_peers;
 mod peer;
+mod rate_limit;
+mod reachability;
+mod redact;
+mod remote;
+mod report;
 mod resolve;
+mod score;
+mod seed;
+mod select;
+mod service;
+mod settings;
+mod sni;
+mod state;
+mod status;
+mod summary;
+mod supervise;
+mod task_scheduler;
+mod termux;
+mod uci_config;
 mod unpack;
+mod uri_normalize;
 mod using_api;
 mod version;
 
 fn main() {
     let matches = clap_args::build_args();
 
+    crate::i18n::init(matches.get_one::<String>("lang").map(|l| l.as_str()));
+
+    // Kept for the daemon: every cycle reads the settings file again
+    let settings_path = matches.get_one::<PathBuf>("settings").cloned();
+    let profile = matches.get_one::<String>("profile").cloned();
+    let (matches, notifiers) = match &settings_path {
+        Some(_path) => match apply_settings(_path, profile.as_ref()) {
+            Ok((_argv, _n)) => (clap_args::build_command().get_matches_from(_argv), _n),
+            Err(e) => {
+                log_error!("{}", e);
+                process::exit(1);
+            }
+        },
+        _ => (matches, Vec::new()),
+    };
+
+    crate::redact::show_secrets(matches.get_flag("show_secrets"));
+
+    if let Some(_lt) = matches.get_one::<String>("log_target") {
+        match crate::logging::Target::parse(_lt) {
+            Ok(_t) => crate::logging::init(_t),
+            Err(e) => {
+                eprintln!("{}", tr!("Incorrect '--log-target' value ({}).", e));
+                process::exit(1);
+            }
+        }
+    }
+
+    if let Some(_sn) = matches.get_one::<String>("service_name") {
+        crate::service::set_name(_sn);
+    }
+
+    // Global: given before or after the subcommand
+    let bind = match matches.subcommand() {
+        Some((_, _sm)) => _sm.get_one::<crate::bind::Bind>("bind"),
+        _ => None,
+    }
+    .or(matches.get_one::<crate::bind::Bind>("bind"));
+    if let Some(_b) = bind {
+        crate::bind::init(_b.clone());
+    }
+
+    if let Some(("history", history_matches)) = matches.subcommand() {
+        history_command(history_matches);
+        process::exit(0);
+    }
+    if let Some(("bench", bench_matches)) = matches.subcommand() {
+        bench_command(bench_matches);
+        process::exit(0);
+    }
+    if let Some(("install-task", task_matches)) = matches.subcommand() {
+        install_task_command(task_matches);
+        process::exit(0);
+    }
+    if let Some(("install-dispatcher", dispatcher_matches)) = matches.subcommand() {
+        install_dispatcher_command(dispatcher_matches);
+        process::exit(0);
+    }
+    if let Some((_list @ ("list-regions" | "list-countries"), _)) = matches.subcommand() {
+        list_command(&matches, _list == "list-countries");
+        process::exit(0);
+    }
+    if let Some(("fleet", fleet_matches)) = matches.subcommand() {
+        process::exit(fleet_command(&matches, fleet_matches));
+    }
+
     let print_only = matches.get_flag("print");
     let update_cfg = matches.get_flag("update_cfg");
+    let check_only = matches.get_flag("check");
     let use_api = matches.get_flag("api");
+    let output_format = match matches.get_one::<String>("format") {
+        Some(_f) => _f.as_str(),
+        _ => "table",
+    };
+    let print_only = print_only || output_format != "table";
 
-    if !(print_only || update_cfg || use_api) {
-        println!("Parameters expected: '-p' or '-u' and (or) '-a'.");
-        println!("For more information try '-h'.");
-        println!("Nothing to do, exit.");
+    let template: Option<String> = match (
+        matches.get_one::<String>("template"),
+        matches.get_one::<PathBuf>("template_file"),
+    ) {
+        (Some(_t), _) => Some(_t.to_string()),
+        (_, Some(_tf)) => match fs::read_to_string(_tf) {
+            Ok(_t) => Some(_t.trim_end_matches(['\r', '\n']).to_string()),
+            Err(e) => {
+                log_error!(
+                    "{}",
+                    tr!(
+                        "The template file {} cannot be read ({}).",
+                        _tf.display(),
+                        e
+                    )
+                );
+                process::exit(1);
+            }
+        },
+        _ => None,
+    };
+    if output_format == "template" && template.is_none() {
+        log_error!(
+            "{}",
+            tr!("'--format template' requires '--template' or '--template-file'.")
+        );
+        process::exit(1);
+    }
+
+    if matches.get_flag("stream") && output_format != "table" {
+        log_error!(
+            "{}",
+            tr!("'--stream' can only be used with '--format table'.")
+        );
+        process::exit(1);
+    }
+    if matches.contains_id("group_by") && output_format != "table" {
+        log_error!(
+            "{}",
+            tr!("'--group-by' can only be used with '--format table'.")
+        );
+        process::exit(1);
+    }
+
+    let n_peers = match matches.get_one::<String>("number") {
+        Some(number) => match crate::select::PeerCount::parse(number) {
+            Ok(_n) => _n,
+            Err(e) => {
+                log_error!(
+                    "{}",
+                    tr!(
+                        "The number of peers must be a number or a percentage of the alive peers from 0% to 100% ({}).",
+                        e
+                    )
+                );
+                process::exit(1);
+            }
+        },
+        _ => crate::select::PeerCount::Fixed(3),
+    };
+    if !(print_only || update_cfg || use_api || check_only) {
+        println!(
+            "{}",
+            tr!("Parameters expected: '-p', '--check' or '-u' and (or) '-a'.")
+        );
+        println!("{}", tr!("For more information try '-h'."));
+        println!("{}", tr!("Nothing to do, exit."));
         process::exit(0);
     }
 
-    let conf_path = match matches.get_one::<PathBuf>("config") {
-        Some(_c) => _c,
+    let use_uci = matches
+        .get_one::<String>("config_format")
+        .map(|f| f.as_str())
+        == Some("uci");
+
+    let remote: Option<Remote> = matches
+        .get_one::<String>("remote")
+        .map(|r| Remote::parse(r));
+    if remote.is_some() && (use_api || use_uci) {
+        log_error!("{}", tr!("The '--remote' parameter can't be used together with '-a' or '--config-format uci'."));
+        process::exit(1);
+    }
+
+    // Only the final write and the restart are done with elevated privileges
+    let escalation: Option<Escalation> = matches
+        .get_one::<String>("escalate")
+        .map(|e| Escalation::parse(e));
+    if escalation.as_ref().is_some_and(|e| e.is_empty()) {
+        log_error!("{}", tr!("The '--escalate' command is empty."));
+        process::exit(1);
+    }
+    if escalation.is_some() && use_uci {
+        log_error!(
+            "{}",
+            tr!("The '--escalate' parameter can't be used together with '--config-format uci'.")
+        );
+        process::exit(1);
+    }
+    let api_backend = match matches.get_one::<String>("api_backend") {
+        Some(_b) => match crate::using_api::ApiBackend::parse(_b) {
+            Ok(_ab) => _ab,
+            Err(e) => {
+                log_error!("{}", tr!("Incorrect '--api-backend' value ({}).", e));
+                process::exit(1);
+            }
+        },
+        _ => crate::using_api::ApiBackend::Socket,
+    };
+    if matches.get_flag("supervise") {
+        crate::supervise::set(
+            api_backend.clone(),
+            *matches.get_one::<u64>("supervise_latency").unwrap_or(&500) as u128,
+            *matches
+                .get_one::<std::time::Duration>("supervise_grace")
+                .unwrap_or(&std::time::Duration::from_secs(120)),
+        );
+    }
+
+    let access = ConfigAccess {
+        remote: remote.as_ref(),
+        escalation: escalation.as_ref(),
+    };
+
+    // Several configuration files can be updated in a single run
+    let remote_path = remote.as_ref().and_then(|r| r.path.as_ref());
+    let conf_paths: Vec<PathBuf> = match matches.get_many::<PathBuf>("config") {
+        _ if remote_path.is_some() => remote_path.map(PathBuf::from).into_iter().collect(),
+        Some(_) if use_uci && matches.value_source("config") == Some(ValueSource::DefaultValue) => {
+            vec![PathBuf::from(crate::defaults::DEF_UCI_CFG_PATH)]
+        }
+        Some(_c) => _c.cloned().collect(),
         _ => {
-            eprintln!("Can't get the configuration file default path.");
+            log_error!("{}", tr!("Can't get the configuration file default path."));
             process::exit(1);
         }
     };
 
-    if !print_only {
-        // Checking if the file exists
-        if !conf_path.exists() {
-            eprintln!("The Yggdrasil configuration file does not exist.");
-            process::exit(1);
-        }
+    // The platform default isn't necessarily the file the node reads
+    let conf_paths: Vec<PathBuf> = match (remote.is_some() || use_uci, print_only) {
+        (false, false) => match crate::detect_config::running_config_path() {
+            Some(_p) if matches.value_source("config") == Some(ValueSource::DefaultValue) => {
+                if !conf_paths.contains(&_p) {
+                    log_info!(
+                        "{}",
+                        tr!(
+                            "The running Yggdrasil node uses the configuration file {}.",
+                            _p.display()
+                        )
+                    );
+                }
+                vec![_p]
+            }
+            Some(_p) if !conf_paths.contains(&_p) => {
+                log_warn!(
+                    "{}",
+                    tr!(
+                        "Warning: the running Yggdrasil node uses the configuration file {}, which is not being updated.",
+                        _p.display()
+                    )
+                );
+                conf_paths
+            }
+            _ => conf_paths,
+        },
+        _ => conf_paths,
+    };
 
-        // Checking write access to the configuration file
-        let _t = match check_permissions(&conf_path) {
-            Ok(_ro) => _ro,
-            Err(e) => {
-                eprintln!(
-                    "There is no write access to the Yggdrasil configuration file ({}).",
-                    e
+    // The remote files are checked when they are read
+    if !print_only && remote.is_none() {
+        for conf_path in &conf_paths {
+            // Checking if the file exists
+            if !conf_path.exists() {
+                log_error!(
+                    "{}",
+                    tr!(
+                        "The Yggdrasil configuration file does not exist ({}).",
+                        conf_path.display()
+                    )
                 );
                 process::exit(1);
             }
-        };
+
+            if check_only || escalation.is_some() {
+                continue;
+            }
+
+            // Checking write access to the configuration file
+            let _t = match check_permissions(conf_path) {
+                Ok(_ro) => _ro,
+                Err(e) => {
+                    log_error!(
+                        "{}",
+                        tr!(
+                            "There is no write access to the Yggdrasil configuration file ({}).",
+                            e
+                        )
+                    );
+                    process::exit(1);
+                }
+            };
+        }
     }
 
-    // Creating a temporary directory
-    let tmp_dir = match create_tmp_dir() {
-        Ok(val) => val,
-        Err(e) => {
-            eprintln!("Failed to create a temporary directory ({}).", e);
-            process::exit(1);
+    let mut settings = RunSettings {
+        print_only,
+        update_cfg,
+        check_only,
+        use_api,
+        api_backend,
+        use_uci,
+        output_format,
+        template,
+        n_peers,
+        conf_paths,
+        access,
+    };
+
+    if matches.get_flag("daemon") {
+        let interval = *matches
+            .get_one::<std::time::Duration>("interval")
+            .unwrap_or(&std::time::Duration::from_secs(24 * 60 * 60));
+        let listeners = [
+            ("status_listen", crate::status::status_handler as _),
+            ("serve_peers", crate::status::peers_handler as _),
+        ];
+        for (arg, handler) in listeners {
+            if let Some(addr) = matches.get_one::<String>(arg) {
+                if let Err(e) = crate::status::serve(addr, handler) {
+                    log_error!(
+                        "{}",
+                        tr!("Failed to listen for HTTP requests on {} ({}).", addr, e)
+                    );
+                    process::exit(1);
+                }
+            }
         }
+        let control_socket = matches.get_one::<PathBuf>("control_socket");
+        // The settings of the last cycle that could read the file
+        let mut current: Option<(clap::ArgMatches, Vec<crate::notify::Notifier>)> = None;
+        crate::daemon::run(
+            interval,
+            matches.get_flag("watch_routes"),
+            control_socket.map(|p| p.as_path()),
+            || {
+                // The edits of the settings file and the profile of the current
+                // network; the mode and the configuration files stay as started
+                if let Some(_path) = &settings_path {
+                    let reloaded = apply_settings(_path, profile.as_ref()).and_then(|(argv, n)| {
+                        clap_args::build_command()
+                            .try_get_matches_from(argv)
+                            .map(|m| (m, n))
+                            .map_err(|e| e.to_string())
+                    });
+                    match reloaded {
+                        Ok(_r) => current = Some(_r),
+                        Err(e) => log_error!(
+                            "{}",
+                            tr!("The settings file can't be applied, the previous settings are used ({}).", e)
+                        ),
+                    }
+                }
+                let (matches, notifiers) = match &current {
+                    Some((_m, _n)) => (_m, _n),
+                    _ => (&matches, &notifiers),
+                };
+                if let Some(_n) = matches
+                    .get_one::<String>("number")
+                    .and_then(|n| crate::select::PeerCount::parse(n).ok())
+                {
+                    settings.n_peers = _n;
+                }
+
+                let code = update_with_retries(matches, &settings);
+                crate::notify::send(notifiers, code);
+                crate::metrics::push(matches, code);
+                crate::report::write(matches, code);
+                crate::status::finish_cycle(code);
+                code
+            },
+        );
+        process::exit(0);
+    }
+
+    let code = update_with_retries(&matches, &settings);
+    crate::notify::send(&notifiers, code);
+    crate::metrics::push(&matches, code);
+    crate::report::write(&matches, code);
+    process::exit(code);
+}
+
+// The options of an update cycle that are checked once at startup
+struct RunSettings<'a> {
+    print_only: bool,
+    update_cfg: bool,
+    check_only: bool,
+    use_api: bool,
+    api_backend: crate::using_api::ApiBackend,
+    use_uci: bool,
+    output_format: &'a str,
+    template: Option<String>,
+    n_peers: crate::select::PeerCount,
+    conf_paths: Vec<PathBuf>,
+    access: ConfigAccess<'a>,
+}
+
+// '--wait-for-network': at boot the uplink and DNS may come up later, so the
+// run waits for them and a failed run is retried with a growing delay until
+// the time runs out
+fn update_with_retries(matches: &clap::ArgMatches, settings: &RunSettings) -> i32 {
+    crate::deadline::set(
+        matches
+            .get_one::<std::time::Duration>("max_runtime")
+            .copied(),
+    );
+    let timeout = match matches.get_one::<std::time::Duration>("wait_for_network") {
+        Some(_t) => *_t,
+        _ => return update(matches, settings),
     };
+    let deadline = std::time::Instant::now() + timeout;
 
-    // Download the archive with peers
-    let _res = match download_archive(&tmp_dir) {
-        Ok(val) => val,
-        Err(e) => {
-            eprintln!("Failed to download archive with peers ({}).", e);
-            process::exit(1);
+    if !crate::connectivity::wait_for_network(timeout) {
+        log_warn!(
+            "{}",
+            tr!("The network did not come up within '--wait-for-network'.")
+        );
+    }
+
+    let mut delay = std::time::Duration::from_secs(5);
+    loop {
+        let code = update(matches, settings);
+        // Only the errors are retried, not the exit codes for monitoring
+        if code != 1
+            || crate::daemon::shutdown_requested()
+            || std::time::Instant::now() + delay > deadline
+            || crate::deadline::remaining().is_some_and(|r| r < delay)
+        {
+            return code;
+        }
+
+        log_warn!(
+            "{}",
+            tr!("The run failed, retrying in {} s.", delay.as_secs())
+        );
+        let resume = std::time::Instant::now() + delay;
+        while std::time::Instant::now() < resume && !crate::daemon::shutdown_requested() {
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        }
+        if crate::daemon::shutdown_requested() {
+            return code;
         }
+        crate::status::discard_cycle_errors();
+        crate::report::discard();
+        delay = (delay * 2).min(std::time::Duration::from_secs(300));
+    }
+}
+
+// A single update cycle: downloading and probing the peers and applying the
+// selection. Returns the exit code.
+fn update(matches: &clap::ArgMatches, settings: &RunSettings) -> i32 {
+    let RunSettings {
+        print_only,
+        update_cfg,
+        check_only,
+        use_api,
+        ref api_backend,
+        use_uci,
+        output_format,
+        ref template,
+        n_peers,
+        ref conf_paths,
+        ref access,
+    } = *settings;
+    let exrta_peers: Option<&String> = matches.get_one::<String>("extra");
+    let ignored_peers: Option<&String> = matches.get_one::<String>("ignore");
+    let state_path: Option<&PathBuf> = matches.get_one::<PathBuf>("state");
+    crate::rate_limit::set(matches.get_one::<u32>("probe_rate").copied());
+
+    // Repeated invocations shouldn't restart the node again and again
+    if let (Some(min_interval), Some(_sp), false, false) = (
+        matches.get_one::<std::time::Duration>("min_interval"),
+        state_path,
+        print_only,
+        check_only,
+    ) {
+        match crate::state::load(_sp) {
+            Ok(crate::state::State {
+                last_update: Some(_lu),
+            }) if crate::history::unix_time() - _lu < min_interval.as_secs() as i64 => {
+                log_info!(
+                    "{}",
+                    tr!(
+                        "The last update was {}s ago, which is less than '--min-interval'. Nothing to do.",
+                        crate::history::unix_time() - _lu
+                    )
+                );
+                return 0;
+            }
+            Ok(_) => {}
+            Err(e) => log_error!("{}", tr!("Failed to read the state file ({}).", e)),
+        };
+    }
+
+    // Without internet access every peer would look dead
+    let connectivity_check = match matches.get_flag("no_connectivity_check") {
+        true => None,
+        _ => matches.get_one::<String>("connectivity_check"),
     };
+    if let Some(check_url) = connectivity_check {
+        match crate::connectivity::check_internet(check_url) {
+            Ok(_) => {}
+            Err(crate::connectivity::InternetError::Unreachable(e)) => {
+                crate::status::error(tr!(
+                    "There is no internet connectivity ({}). The configuration was left unchanged.",
+                    e
+                ));
+                return 1;
+            }
+            Err(crate::connectivity::InternetError::CaptivePortal(code)) => {
+                crate::status::error(tr!(
+                    "The connectivity check answered with HTTP {}, probably a captive portal. The configuration was left unchanged.",
+                    code
+                ));
+                return 1;
+            }
+        }
+    }
 
-    // Unpacking the downloaded archive
-    let _res = match crate::unpack::unpack_archive(&tmp_dir) {
+    // The current peers are still good: no churn and no restart
+    if matches.get_flag("only_if_stale") && !print_only && !check_only {
+        let families = match matches.get_flag("all_families") {
+            true => crate::connectivity::IpFamilies::all(),
+            _ => crate::connectivity::detect(),
+        };
+        let max_latency = *matches.get_one::<u64>("stale_latency").unwrap_or(&500);
+        if crate::health::current_peers_healthy(
+            conf_paths,
+            access,
+            use_uci,
+            use_api.then_some(api_backend),
+            n_peers.fixed(),
+            max_latency as u128,
+            &families,
+        ) {
+            log_info!(
+                "{}",
+                tr!("The current peers are healthy ('--only-if-stale'). Nothing to do.")
+            );
+            return 0;
+        }
+    }
+
+    // Creating a temporary directory
+    let tmp_dir = match create_tmp_dir() {
         Ok(val) => val,
         Err(e) => {
-            eprintln!("Failed to unpack archive ({}).", e);
-            process::exit(1);
+            crate::status::error(tr!("Failed to create a temporary directory ({}).", e));
+            return 1;
         }
     };
 
-    // Deleting unnecessary files
-    let _ret = fs::remove_file(std::path::Path::new(
-        format!("{}/public-peers-master/README.md", &tmp_dir.display()).as_str(),
-    ));
-    let _ret = fs::remove_file(std::path::Path::new(
-        format!("{}/peers.zip", &tmp_dir.display()).as_str(),
-    ));
-    let _ret = fs::remove_dir_all(std::path::Path::new(
-        format!("{}/public-peers-master/other", &tmp_dir.display()).as_str(),
-    ));
-
+    let country_filter: Option<&String> = matches.get_one::<String>("country");
     let peers_dir: PathBuf =
         std::path::Path::new(format!("{}/public-peers-master/", &tmp_dir.display()).as_str())
             .to_path_buf();
 
-    // Collecting peers in a vector
-    let mut peers: Vec<Peer> = Vec::new();
-    match crate::parsing_peers::collect_peers(&peers_dir, &mut peers) {
-        Ok(_r) => _r,
-        Err(e) => {
-            eprintln!("Couldn't get peer addresses from downloaded files ({}).", e);
-            process::exit(1);
+    // With a narrow country filter only the needed files are downloaded
+    let github_token = crate::github::token(matches);
+    let fetched = match country_filter {
+        Some(_cf) => match crate::github::fetch_countries(&tmp_dir, _cf, github_token.as_deref()) {
+            Ok(_n) => _n.is_some(),
+            Err(e) => {
+                log_warn!(
+                    "{}",
+                    tr!(
+                        "Failed to fetch the country files via the GitHub API, downloading the archive ({}).",
+                        e
+                    )
+                );
+                let _ret = fs::remove_dir_all(&peers_dir);
+                false
+            }
+        },
+        _ => false,
+    };
+
+    // Without the peer list the peers of the configuration are probed again,
+    // so that the dead ones are still removed during an outage of GitHub
+    let mut current_peers: Option<Vec<Peer>> = None;
+
+    if !fetched {
+        // Download the archive with peers. The last downloaded archive is kept
+        // to be used when the download fails (e.g. because of the rate limit).
+        let cache_path = match matches.get_one::<PathBuf>("cache") {
+            Some(_c) => _c.as_path(),
+            _ => Path::new(crate::defaults::cache_path()),
+        };
+        let archive_path = tmp_dir.join("peers.zip");
+        let downloaded = match crate::github::download_archive(&tmp_dir) {
+            Ok(_) => {
+                let saved = match cache_path.parent() {
+                    Some(_dir) => fs::create_dir_all(_dir),
+                    _ => Ok(()),
+                }
+                .and_then(|_| fs::copy(&archive_path, cache_path));
+                // The default cache is best-effort (e.g. not writable by a user running '-p')
+                let explicit = matches.value_source("cache") == Some(ValueSource::CommandLine);
+                if let (Err(e), true) = (saved, explicit) {
+                    log_error!("{}", tr!("Failed to save the peer list cache ({}).", e));
+                }
+                Ok(())
+            }
+            Err(e) if crate::deadline::expired() => {
+                crate::status::error(tr!(
+                    "The run exceeded '--max-runtime' while downloading the peer list ({}). The configuration was left unchanged.",
+                    e
+                ));
+                return crate::defaults::EXIT_DEADLINE;
+            }
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = downloaded {
+            let cached = match cache_path.exists() {
+                true => fs::copy(cache_path, &archive_path).map_err(|e| {
+                    log_warn!("{}", tr!("Failed to read the peer list cache ({}).", e));
+                }),
+                _ => Err(()),
+            };
+            match cached {
+                Ok(_) if crate::github::is_rate_limited(&e) => {
+                    log_warn!("{}", tr!("{}. The cached peer list is used.", e))
+                }
+                Ok(_) => log_warn!(
+                    "{}",
+                    tr!(
+                        "Failed to download archive with peers ({}). The cached peer list is used.",
+                        e
+                    )
+                ),
+                _ => {
+                    let peers: Vec<Peer> = conf_paths
+                        .iter()
+                        .filter_map(|conf_path| access.current_peers(conf_path, use_uci))
+                        .flatten()
+                        .filter_map(|uri| crate::parsing_peers::peer_from_uri(&uri))
+                        .collect();
+                    if peers.is_empty() {
+                        crate::status::error(tr!("Failed to download archive with peers ({}).", e));
+                        return 1;
+                    }
+                    log_warn!(
+                        "{}",
+                        tr!(
+                            "Failed to download archive with peers ({}). There is no cached peer list, the current peers are probed again.",
+                            e
+                        )
+                    );
+                    current_peers = Some(peers);
+                }
+            }
         }
+    }
+
+    if !fetched && current_peers.is_none() {
+        // Unpacking the downloaded archive
+        let _res = match crate::unpack::unpack_archive(&tmp_dir) {
+            Ok(val) => val,
+            Err(e) => {
+                crate::status::error(tr!("Failed to unpack archive ({}).", e));
+                return 1;
+            }
+        };
+
+        // Deleting unnecessary files
+        let _ret = fs::remove_file(std::path::Path::new(
+            format!("{}/public-peers-master/README.md", &tmp_dir.display()).as_str(),
+        ));
+        let _ret = fs::remove_file(std::path::Path::new(
+            format!("{}/peers.zip", &tmp_dir.display()).as_str(),
+        ));
+        let _ret = fs::remove_dir_all(std::path::Path::new(
+            format!("{}/public-peers-master/other", &tmp_dir.display()).as_str(),
+        ));
+    }
+
+    // Detecting which IP families can be used for probing
+    let families = match matches.get_flag("all_families") {
+        true => crate::connectivity::IpFamilies::all(),
+        _ => crate::connectivity::detect(),
+    };
+    if !families.ipv4 {
+        log_warn!(
+            "{}",
+            tr!("No global IPv4 connectivity detected, IPv4 peers will be skipped.")
+        );
+    }
+    if !families.ipv6 {
+        log_warn!(
+            "{}",
+            tr!("No global IPv6 connectivity detected, IPv6 peers will be skipped.")
+        );
+    }
+    let families = &families;
+
+    let verbosity = matches.get_count("verbose");
+    let stream = print_only && output_format == "table" && matches.get_flag("stream");
+    if stream {
+        crate::output::print_table_header();
+    }
+    let probe_spacing: u64 = *matches.get_one::<u64>("probe_spacing").unwrap_or(&0);
+
+    // '--sample': a random part of the candidates is probed, the next part only
+    // if too few of them are alive
+    let sample = match matches.get_flag("probe_all") {
+        true => None,
+        _ => matches.get_one::<crate::select::PeerCount>("sample"),
     };
+    let sample_target =
+        (*matches.get_one::<usize>("min_peers").unwrap_or(&1)).max(n_peers.fixed().unwrap_or(0));
+
+    let reprobing = current_peers.is_some();
+
+    // Collecting peers and calculating latency at the same time:
+    // each peer is probed as soon as it has been parsed
+    let (tx, rx) = mpsc::channel::<Peer>();
+    let mut seen_uris: HashSet<String> = HashSet::new();
+    let peers: Option<Vec<Peer>> = std::thread::scope(|scope| {
+        let parser = scope.spawn(move || match current_peers {
+            Some(_cp) => {
+                for peer in _cp {
+                    let _ret = tx.send(peer);
+                }
+                Ok(true)
+            }
+            _ => crate::parsing_peers::collect_peers(&peers_dir, &tx),
+        });
+
+        let probe = |i: usize, mut peer: Peer| {
+            // Spreading the connection attempts over time
+            if i > 0 && probe_spacing > 0 {
+                std::thread::sleep(std::time::Duration::from_millis(probe_spacing));
+            }
+            // Don't start new probes once the shutdown is requested or the
+            // run is out of time
+            if crate::daemon::shutdown_requested() || crate::deadline::expired() {
+                return None;
+            }
+            Some(scope.spawn(move || {
+                match crate::latency::set_latency(&mut peer, families, verbosity >= 2) {
+                    true => {
+                        if stream && peer.is_alive {
+                            crate::output::print_table_row(&peer);
+                        }
+                        Some(peer)
+                    }
+                    _ => None,
+                }
+            }))
+        };
+
+        let candidates = rx
+            .into_iter()
+            // The country of the current peers is unknown
+            .filter(|peer| match country_filter {
+                Some(_cf) if !reprobing => {
+                    crate::countries::country_matches(_cf, &peer.country, &peer.country_code)
+                }
+                _ => true,
+            })
+            // The same peer may be listed several times
+            .filter(|peer| seen_uris.insert(crate::uri_normalize::normalize(&peer.uri)));
+
+        // '--prefer-reachable': a few peers of every protocol and port class
+        // are probed first, the rest of the classes blocked by this network
+        // are skipped
+        let prefer_reachable = matches.get_flag("prefer_reachable");
+
+        let peers: Vec<Peer> = match sample {
+            Some(_s) => {
+                let (mut probed, mut candidates) = match prefer_reachable {
+                    true => crate::reachability::preflight(candidates.collect(), families),
+                    _ => (Vec::new(), candidates.collect()),
+                };
+                if stream {
+                    probed
+                        .iter()
+                        .filter(|peer| peer.is_alive)
+                        .for_each(crate::output::print_table_row);
+                }
+                let seed = match matches.get_one::<String>("seed") {
+                    Some(_seed) => _seed.to_string(),
+                    _ => format!("{:?}-{}", std::time::SystemTime::now(), process::id()),
+                };
+                candidates.sort_by_cached_key(|peer| crate::seed::tiebreak(&seed, &peer.uri));
+
+                let batch = _s.resolve(candidates.len()).max(1);
+                let mut candidates = candidates.into_iter().enumerate();
+                let mut started = probed.len();
+                loop {
+                    let probes: Vec<_> = candidates
+                        .by_ref()
+                        .take(batch)
+                        .map(|(i, peer)| probe(i, peer))
+                        .collect();
+                    if probes.is_empty() {
+                        break;
+                    }
+                    started += probes.len();
+                    probed.extend(
+                        probes
+                            .into_iter()
+                            .flatten()
+                            .filter_map(|probe| probe.join().ok().flatten()),
+                    );
+                    let alive = probed.iter().filter(|peer| peer.is_alive).count();
+                    if alive >= sample_target
+                        || candidates.len() == 0
+                        || crate::daemon::shutdown_requested()
+                        || crate::deadline::expired()
+                    {
+                        break;
+                    }
+                    if verbosity >= 1 {
+                        log_info!(
+                            "{}",
+                            tr!(
+                                "Only {} of the {} sampled peers are alive, probing more.",
+                                alive,
+                                started
+                            )
+                        );
+                    }
+                }
+                probed
+            }
+            _ if prefer_reachable => crate::reachability::probe_gated(candidates, probe),
+            _ => {
+                let probes: Vec<_> = candidates
+                    .enumerate()
+                    .map(|(i, peer)| probe(i, peer))
+                    .collect();
+                probes
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|probe| probe.join().ok().flatten())
+                    .collect()
+            }
+        };
+
+        let parsed = match parser.join() {
+            Ok(Ok(_)) => true,
+            Ok(Err(e)) => {
+                crate::status::error(tr!(
+                    "Couldn't get peer addresses from downloaded files ({}).",
+                    e
+                ));
+                false
+            }
+            Err(_) => {
+                crate::status::error(tr!("Couldn't get peer addresses from downloaded files."));
+                false
+            }
+        };
+
+        match parsed {
+            true => Some(peers),
+            _ => None,
+        }
+    });
 
     // Deleting unnecessary files
     let _ret = fs::remove_dir_all(std::path::Path::new(tmp_dir.as_path()));
 
-    // Calculating latency
-    std::thread::scope(|scope| {
-        for peer in &mut peers {
-            scope.spawn(move || {
-                crate::latency::set_latency(peer);
-            });
+    let mut peers = match peers {
+        Some(_p) => _p,
+        _ => return 1,
+    };
+
+    // Probing was interrupted, the configuration is left as it is
+    if crate::daemon::shutdown_requested() {
+        return 0;
+    }
+
+    // '--max-runtime' cut the probing short: the peers probed so far are used
+    // if enough of them are alive
+    if crate::deadline::expired() {
+        let alive = peers.iter().filter(|peer| peer.is_alive).count();
+        let min_alive = *matches.get_one::<usize>("min_peers").unwrap_or(&1);
+        if alive < min_alive {
+            crate::status::error(tr!(
+                "The run exceeded '--max-runtime' with only {} alive peers probed. The configuration was left unchanged.",
+                alive
+            ));
+            return crate::defaults::EXIT_DEADLINE;
         }
-    });
+        log_warn!(
+            "{}",
+            tr!(
+                "The run exceeded '--max-runtime', the {} peers probed so far are used.",
+                peers.len()
+            )
+        );
+    }
+
+    // Saving the probe results
+    if let Some(history_path) = matches.get_one::<PathBuf>("history") {
+        match crate::history::open(history_path) {
+            Ok(mut conn) => {
+                if let Err(e) = crate::history::record(&mut conn, &peers) {
+                    crate::status::error(tr!(
+                        "Failed to save the probe results to the history ({}).",
+                        e
+                    ));
+                }
+                // The peers dead for several runs, to be reported to public-peers
+                if let Some(_rd) = matches.get_one::<PathBuf>("report_dead") {
+                    let runs = *matches.get_one::<u16>("dead_runs").unwrap_or(&3) as usize;
+                    let written = crate::history::dead_peers(&conn, runs)
+                        .map_err(io::Error::other)
+                        .and_then(|report| {
+                            let mut file = fs::File::create(_rd)?;
+                            crate::history::write_dead_report(&report, &mut file)
+                        });
+                    if let Err(e) = written {
+                        crate::status::error(tr!("Failed to write the dead peer report ({}).", e));
+                    }
+                }
+            }
+            Err(e) => {
+                crate::status::error(tr!("Failed to open the history database ({}).", e));
+            }
+        };
+    }
 
     //Sorting the vector
-    peers.sort_by(|a, b| a.latency.cmp(&b.latency));
+    match matches.get_one::<String>("seed") {
+        Some(_seed) => {
+            peers.sort_by_cached_key(|a| (a.latency, crate::seed::tiebreak(_seed, &a.uri)))
+        }
+        _ => peers.sort_by_key(|a| a.latency),
+    }
+
+    crate::report::record_probes(&peers);
+
+    // The user's policy (trust lists, ASN preferences) on top of the latency
+    if let Some(_cmd) = matches.get_one::<String>("score_cmd") {
+        if let Err(e) = crate::score::apply(_cmd, &mut peers) {
+            crate::status::error(tr!(
+                "The scoring command failed ({}). The configuration was left unchanged.",
+                e
+            ));
+            return 1;
+        }
+    }
+
+    // Which protocols and ports get through from this network
+    let reachability = crate::reachability::Reachability::from_probes(&peers);
+    if verbosity >= 1 {
+        reachability.print();
+    }
+    if matches.get_flag("prefer_reachable") {
+        peers.sort_by_key(|a| (!a.is_alive, reachability.is_filtered(a)));
+    }
+
+    // A host may be listed with several transports
+    let peers = crate::select::best_transport_per_host(peers);
+
+    if verbosity >= 1 {
+        log_info!(
+            "{}",
+            tr!(
+                "Probed {} peers, {} of them are alive.",
+                peers.len(),
+                peers.iter().filter(|p| p.is_alive).count()
+            )
+        );
+    }
+    if verbosity >= 2 {
+        for peer in &peers {
+            print_timings(peer);
+        }
+    }
+
+    // The alive peers that pass the filters
+    let alive = peers
+        .iter()
+        .filter(|peer| peer.is_alive)
+        .filter(|peer| match ignored_peers {
+            Some(_ip) => !crate::uri_normalize::list_contains(_ip, &peer.uri),
+            _ => true,
+        })
+        .count();
+
+    // The peer ecosystem is degraded (for monitoring): nothing is written or printed
+    if let Some(_min) = matches.get_one::<u16>("fail_if_fewer_than") {
+        if alive < *_min as usize {
+            crate::status::error(tr!(
+                "Only {} alive peers pass the filters, fewer than {} ('--fail-if-fewer-than'). Nothing was changed.",
+                alive,
+                _min
+            ));
+            return crate::defaults::EXIT_TOO_FEW_PEERS;
+        }
+    }
+    let mut n_peers = n_peers.resolve(alive);
+
+    // The LAN links already connect the node, only the rest is public
+    if matches.get_flag("count_multicast") && !print_only {
+        let lan = crate::health::lan_links(conf_paths, access, use_uci, api_backend);
+        if lan > 0 {
+            let min_public = (*matches.get_one::<usize>("min_peers").unwrap_or(&1)).min(n_peers);
+            n_peers = n_peers.saturating_sub(lan).max(min_public);
+            log_info!(
+                "{}",
+                tr!(
+                    "{} healthy LAN multicast peers count toward '-n', {} public peers are selected.",
+                    lan,
+                    n_peers
+                )
+            );
+        }
+    }
+
+    // Precedence: ignored > extra > selected, every URI is added once
+    let extra_peers = crate::select::extra_peers(exrta_peers, ignored_peers);
+    crate::status::record_candidates(&peers, ignored_peers);
+    // A selection applied through '--control-socket' is used once
+    let applied = crate::control::take_selection();
+    let selected = match (&applied, matches.get_one::<usize>("per_region")) {
+        (Some(_a), _) => crate::control::pick(&peers, _a),
+        (_, Some(_pr)) => {
+            crate::select::select_per_region(&peers, *_pr, ignored_peers, &extra_peers)
+        }
+        _ => crate::select::select_peers(&peers, n_peers, ignored_peers, &extra_peers),
+    };
+
+    // Swapping the current peers for a marginal gain breaks the established sessions
+    let selected = match matches.get_one::<u16>("keep_within") {
+        Some(_kw) if applied.is_none() => {
+            let current: Vec<String> = conf_paths
+                .iter()
+                .filter_map(|conf_path| access.current_peers(conf_path, use_uci))
+                .flatten()
+                .collect();
+            crate::select::keep_current(
+                selected,
+                &peers,
+                &current,
+                *_kw,
+                matches.contains_id("per_region"),
+                ignored_peers,
+                &extra_peers,
+            )
+        }
+        _ => selected,
+    };
+
+    // The SNI names are put into the URIs of the selected peers only
+    let sni_peers: Vec<Peer> = match matches.get_one::<String>("sni") {
+        Some(_s) => {
+            let rules = crate::sni::SniRules::parse(_s);
+            selected.iter().map(|peer| rules.apply(peer)).collect()
+        }
+        _ => selected.iter().map(|peer| (*peer).clone()).collect(),
+    };
+    let selected: Vec<&Peer> = sni_peers.iter().collect();
+    crate::status::record_selection(&selected, &extra_peers);
+    crate::report::record_selection(&selected, &extra_peers);
+    for peer in &selected {
+        crate::logging::log_only(
+            crate::logging::Priority::Info,
+            &tr!(
+                "Selected {} ({} ms).",
+                crate::redact::redact(&peer.uri),
+                peer.latency
+            ),
+            &[
+                ("PEER", crate::redact::redact(&peer.uri)),
+                ("LATENCY", peer.latency.to_string()),
+            ],
+        );
+        if verbosity >= 1 {
+            print_operator_info(peer);
+        }
+    }
+
+    if let Some(emit_path) = matches.get_one::<PathBuf>("emit_peers") {
+        if let Err(e) = crate::output::write_peers_json(emit_path, &selected, &extra_peers) {
+            crate::status::error(tr!(
+                "Failed to write the peer list to {} ({}).",
+                emit_path.display(),
+                e
+            ));
+        }
+    }
 
     // Printing data
     if print_only {
-        println!(
-            "{0:<60}|{1:<15}|{2:<15}|{3:<10}",
-            "URI", "Region", "Country", "Latency"
-        );
-        println!("{0:-<100}", "-");
-        for peer in peers {
-            if !peer.is_alive {
-                break;
+        match output_format {
+            "nix" => crate::output::print_nix(&selected, &extra_peers),
+            "template" => {
+                crate::output::print_template(&selected, template.as_deref().unwrap_or_default())
             }
-            println!(
-                "{0:<60}|{1:<15}|{2:<15}|{3:<10}",
-                peer.uri, peer.region, peer.country, peer.latency
-            );
+            // The rows have already been printed
+            _ if stream => {}
+            _ => match matches.get_one::<String>("group_by") {
+                Some(_g) => crate::output::print_table_grouped(&peers, _g),
+                _ => crate::output::print_table(&peers),
+            },
         }
-        process::exit(0);
-    } else if update_cfg || use_api {
-        if let Some(number) = matches.get_one::<String>("number") {
-            let n_peers: u8 = match number.parse() {
-                Ok(_n) => _n,
+        return 0;
+    }
+
+    // Without connectivity all the peers look dead: keeping the current
+    // configuration instead of isolating the node
+    let min_alive = (*matches.get_one::<usize>("min_peers").unwrap_or(&1)).min(n_peers);
+    let alive = selected.iter().filter(|peer| peer.is_alive).count();
+    if alive < min_alive {
+        crate::status::error(tr!(
+            "Only {} alive peers were found, at least {} are required ('--min-peers'). The configuration was left unchanged.",
+            alive,
+            min_alive
+        ));
+        return 1;
+    }
+
+    if check_only {
+        return crate::check::check_configs(conf_paths, access, use_uci, &selected, &extra_peers);
+    }
+
+    let mut failed = false;
+    let mut api_conf_objs: Vec<(Map<String, nu_json::Value>, Pinned)> = Vec::new();
+    let mut config_changes: Vec<crate::summary::ConfigChange> = Vec::new();
+
+    for conf_path in conf_paths {
+        if use_uci {
+            if update_cfg {
+                crate::uci_config::update_peers(&selected, &extra_peers, conf_path);
+            }
+            if use_api {
+                api_conf_objs.push((
+                    crate::uci_config::get_conf_obj(conf_path),
+                    Pinned::default(),
+                ));
+            }
+            continue;
+        }
+
+        //Reading the configuration file
+        let cfg_txt = match access.read(conf_path) {
+            Ok(_ct) => _ct,
+            Err(e) => {
+                crate::status::error(tr!(
+                    "The configuration file {} cannot be read ({}).",
+                    conf_path.display(),
+                    e
+                ));
+                failed = true;
+                continue;
+            }
+        };
+
+        // Adding peers to the configuration file
+        if update_cfg {
+            if let Some(new_cfg) =
+                cfg_file_modify::add_peers_to_conf_new(&selected, &extra_peers, &cfg_txt)
+            {
+                let written = match access.write(conf_path, &new_cfg) {
+                    Ok(_) => true,
+                    Err(e) => {
+                        match access.remote {
+                            Some(_r) => crate::status::error(tr!("The changes could not be written to the configuration file on {} ({}).", _r.host, e)),
+                            _ => crate::status::error(tr!("The changes could not be written to the configuration file ({}).", e)),
+                        }
+                        failed = true;
+                        false
+                    }
+                };
+                let change = crate::summary::ConfigChange::new(
+                    conf_path.display().to_string(),
+                    &cfg_txt,
+                    &new_cfg,
+                    written,
+                );
+                crate::report::record_config_change(&change);
+                config_changes.push(change);
+            }
+        }
+
+        if use_api {
+            //Parsing the configuration file
+            match parse_config::get_hjson_obj(&cfg_txt) {
+                Ok(co) => api_conf_objs.push((co, cfg_file_modify::pinned_peers(&cfg_txt))),
                 Err(e) => {
-                    eprintln!(
-                        "The number of peers must be in the range from 0 to 255 ({}).",
+                    crate::status::error(tr!(
+                        "Can't parse the config file {} ({})!",
+                        conf_path.display(),
                         e
-                    );
-                    process::exit(1);
+                    ));
+                    failed = true;
                 }
             };
+        }
+    }
 
-            //Reading the configuration file
-            let cfg_txt = match parse_config::read_config(conf_path) {
-                Ok(_ct) => _ct,
-                Err(e) => {
-                    eprintln!("The configuration file cannot be read ({}).", e);
-                    process::exit(1);
-                }
-            };
+    //Restart if required
+    let restarted = match matches.get_flag("restart") {
+        true => {
+            let restarted = access.restart_service(use_uci);
+            crate::report::record_restart(restarted);
+            Some(restarted)
+        }
+        _ => None,
+    };
 
-            let exrta_peers: Option<&String> = matches.get_one::<String>("extra");
-            let ignored_peers: Option<&String> = matches.get_one::<String>("ignore");
+    crate::summary::print(&config_changes, restarted);
 
-            // Adding peers to the configuration file
-            if update_cfg {
-                cfg_file_modify::add_peers_to_conf_new(
-                    &peers,
-                    conf_path,
-                    n_peers,
-                    exrta_peers,
-                    ignored_peers,
-                    &cfg_txt,
-                );
+    // Adding peers during execution
+    let mut supervised: Vec<crate::supervise::Node> = Vec::new();
+    for (mut conf_obj, pinned) in api_conf_objs {
+        let (selected, extra_peers) =
+            cfg_file_modify::apply_pinned(&selected, &extra_peers, &pinned);
+        let extra_peers: Vec<String> = pinned.uris.into_iter().chain(extra_peers).collect();
+        if crate::supervise::enabled() {
+            supervised.push(crate::supervise::Node::new(
+                conf_obj.clone(),
+                &selected,
+                &extra_peers,
+            ));
+        }
+        let mut client = using_api::ApiClient::new(&mut conf_obj, api_backend);
+        match matches.get_one::<usize>("maintain") {
+            Some(_m) => using_api::top_up_peers(&selected, &extra_peers, &mut client, *_m),
+            _ => using_api::update_peers(
+                &selected,
+                &extra_peers,
+                &mut client,
+                *matches
+                    .get_one::<std::time::Duration>("api_settle")
+                    .unwrap_or(&std::time::Duration::from_secs(30)),
+            ),
+        }
+        if let Some(_mp) = matches.get_one::<usize>("max_peers") {
+            using_api::enforce_max_peers(&selected, &extra_peers, &mut client, *_mp);
+        }
+    }
+    if crate::supervise::enabled() {
+        crate::supervise::record(
+            supervised,
+            &peers,
+            ignored_peers,
+            matches.get_one::<String>("sni"),
+        );
+    }
+
+    if !failed {
+        if let Some(_sp) = state_path {
+            let state = crate::state::State {
+                last_update: Some(crate::history::unix_time()),
+            };
+            if let Err(e) = crate::state::save(_sp, &state) {
+                log_error!("{}", tr!("Failed to save the state file ({}).", e));
             }
+        }
+    }
 
-            //Restart if required
-            if matches.get_flag("restart") {
-                #[cfg(not(target_os = "windows"))]
-                let _ = std::process::Command::new("systemctl")
-                    .arg("restart")
-                    .arg("yggdrasil")
-                    .spawn();
+    match failed {
+        true => 1,
+        _ => 0,
+    }
+}
 
-                #[cfg(target_os = "windows")]
-                {
-                    let _ = std::process::Command::new("net")
-                        .arg("stop")
-                        .arg("yggdrasil")
-                        .output();
-                    let _ = std::process::Command::new("net")
-                        .arg("start")
-                        .arg("yggdrasil")
-                        .spawn();
+fn print_timings(peer: &Peer) {
+    let fmt = |d: Option<std::time::Duration>| match d {
+        Some(_d) => format!("{}ms", _d.as_millis()),
+        _ => "-".to_string(),
+    };
+    log_info!(
+        "{}",
+        tr!(
+            "{}: DNS {}, TCP connect {}, TLS handshake {}",
+            crate::redact::redact(&peer.uri),
+            fmt(peer.timings.dns),
+            fmt(peer.timings.connect),
+            fmt(peer.timings.tls)
+        )
+    );
+}
+
+// Who runs the peer, from the notes of the peer list
+fn print_operator_info(peer: &Peer) {
+    let details: Vec<String> = [
+        peer.operator.as_ref().map(|o| tr!("operator: {}", o)),
+        peer.contact.as_ref().map(|c| tr!("contact: {}", c)),
+        peer.note.as_ref().map(|n| tr!("note: {}", n)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if !details.is_empty() {
+        log_info!(
+            "{}: {}",
+            crate::redact::redact(&peer.uri),
+            details.join(", ")
+        );
+    }
+}
+
+fn bench_command(matches: &clap::ArgMatches) {
+    // The given peers or the ones from the configuration files
+    let uris: Vec<String> = match matches.get_many::<String>("peers") {
+        Some(_p) => _p.cloned().collect(),
+        _ => matches
+            .get_many::<PathBuf>("config")
+            .into_iter()
+            .flatten()
+            .filter_map(|conf_path| match parse_config::read_config(conf_path) {
+                Ok(_ct) => parse_config::get_peers(&_ct).ok(),
+                Err(e) => {
+                    log_error!(
+                        "{}",
+                        tr!(
+                            "The configuration file {} cannot be read ({}).",
+                            conf_path.display(),
+                            e
+                        )
+                    );
+                    None
                 }
+            })
+            .flatten()
+            .collect(),
+    };
+    if uris.is_empty() {
+        log_error!("{}", tr!("There are no peers to benchmark."));
+        process::exit(1);
+    }
+
+    let families = match matches.get_flag("all_families") {
+        true => crate::connectivity::IpFamilies::all(),
+        _ => crate::connectivity::detect(),
+    };
+
+    crate::bench::run(
+        &uris,
+        *matches
+            .get_one::<std::time::Duration>("duration")
+            .unwrap_or(&std::time::Duration::from_secs(3600)),
+        *matches
+            .get_one::<std::time::Duration>("interval")
+            .unwrap_or(&std::time::Duration::from_secs(60)),
+        &families,
+        matches.get_one::<PathBuf>("history").map(|h| h.as_path()),
+    );
+}
+
+// The command line with the options of the settings file and of the profile
+// (chosen for the current network unless given) inserted before the given ones
+fn apply_settings(
+    path: &Path,
+    profile: Option<&String>,
+) -> Result<(Vec<std::ffi::OsString>, Vec<crate::notify::Notifier>), String> {
+    let settings = settings::load(path)?;
+    let profile = settings.profile(profile.map(|p| p.as_str()))?;
+    let args = settings.args(profile.as_deref())?;
+    let notifiers = settings.notifiers()?;
+    if let Some(_p) = &profile {
+        log_info!("{}", tr!("Using the profile '{}'.", _p));
+    }
+
+    let mut argv = std::env::args_os();
+    let argv: Vec<std::ffi::OsString> = argv
+        .next()
+        .into_iter()
+        .chain(args.into_iter().map(Into::into))
+        .chain(argv)
+        .collect();
+    Ok((argv, notifiers))
+}
+
+fn install_task_command(matches: &clap::ArgMatches) {
+    if !cfg!(target_os = "windows") {
+        log_error!(
+            "{}",
+            tr!("Scheduled Tasks are only available on Windows, use cron or a systemd timer.")
+        );
+        process::exit(1);
+    }
+
+    let mut args = args_before_subcommand("install-task");
+
+    // The output of a task is lost, so the messages go to the event log
+    if !args
+        .iter()
+        .any(|a| a == "--log-target" || a.starts_with("--log-target="))
+    {
+        match crate::task_scheduler::register_event_source() {
+            Ok(_) => args.extend(["--log-target".to_string(), "eventlog".to_string()]),
+            Err(e) => log_warn!(
+                "{}",
+                tr!("Failed to register the event log source ({}).", e)
+            ),
+        }
+    }
+
+    let interval = *matches
+        .get_one::<std::time::Duration>("interval")
+        .unwrap_or(&std::time::Duration::from_secs(7 * 24 * 60 * 60));
+    if let Err(e) = crate::task_scheduler::install(interval, &args) {
+        log_error!("{}", tr!("Failed to register the Scheduled Task ({}).", e));
+        process::exit(1);
+    }
+    println!(
+        "{}",
+        tr!(
+            "The Scheduled Task '{}' has been registered.",
+            crate::task_scheduler::TASK_NAME
+        )
+    );
+}
+
+fn install_dispatcher_command(matches: &clap::ArgMatches) {
+    let dispatcher = match matches.get_one::<String>("dispatcher") {
+        Some(_d) => crate::dispatcher::Dispatcher::parse(_d),
+        _ => crate::dispatcher::Dispatcher::detect(),
+    };
+    let dispatcher =
+        match dispatcher {
+            Some(_d) => _d,
+            _ => {
+                log_error!(
+                "{}",
+                tr!("Neither NetworkManager nor networkd-dispatcher was found, use '--dispatcher'.")
+            );
+                process::exit(1);
             }
+        };
 
-            // Adding peers during execution
-            if use_api {
-                //Parsing the configuration file
-                let mut conf_obj: Map<String, nu_json::Value> =
-                    match parse_config::get_hjson_obj(&cfg_txt) {
-                        Ok(co) => co,
-                        Err(e) => {
-                            eprintln!("Can't parse the config file ({})!", e);
-                            process::exit(1);
-                        }
-                    };
+    let exe = match std::env::current_exe() {
+        Ok(_e) => _e,
+        Err(e) => {
+            log_error!("{}", tr!("Can't get the path of the executable ({}).", e));
+            process::exit(1);
+        }
+    };
+    let script = dispatcher.script(&exe, &args_before_subcommand("install-dispatcher"));
+
+    if matches.get_flag("print") {
+        print!("{}", script);
+        return;
+    }
+    if let Err(e) = dispatcher.install(&script) {
+        log_error!(
+            "{}",
+            tr!(
+                "Failed to install the hook {} ({}).",
+                dispatcher.script_path().display(),
+                e
+            )
+        );
+        process::exit(1);
+    }
+    println!(
+        "{}",
+        tr!(
+            "The hook {} has been installed.",
+            dispatcher.script_path().display()
+        )
+    );
+}
 
-                using_api::update_peers(&peers, &mut conf_obj, n_peers, exrta_peers, ignored_peers);
+// The installed tasks and hooks run the updater with the arguments given
+// before the subcommand
+fn args_before_subcommand(subcommand: &str) -> Vec<String> {
+    std::env::args()
+        .skip(1)
+        .take_while(|a| a != subcommand)
+        .collect()
+}
+
+// The cache is given before the subcommand
+fn list_command(matches: &clap::ArgMatches, countries: bool) {
+    let cache_path = match matches.get_one::<PathBuf>("cache") {
+        Some(_c) => _c.as_path(),
+        _ => Path::new(crate::defaults::cache_path()),
+    };
+    let listed = create_tmp_dir().and_then(|tmp_dir| {
+        let listed = crate::discover::list(&tmp_dir, cache_path, countries);
+        let _ret = fs::remove_dir_all(&tmp_dir);
+        listed
+    });
+    if let Err(e) = listed {
+        log_error!("{}", tr!("Failed to get the peer list ({}).", e));
+        process::exit(1);
+    }
+}
+
+fn fleet_command(matches: &clap::ArgMatches, fleet_matches: &clap::ArgMatches) -> i32 {
+    let inventory = match fleet_matches.get_one::<PathBuf>("inventory") {
+        Some(_i) => _i,
+        _ => return 1,
+    };
+    let cache_path = match matches.get_one::<PathBuf>("cache") {
+        Some(_c) => _c.as_path(),
+        _ => Path::new(crate::defaults::cache_path()),
+    };
+    let families = match fleet_matches.get_flag("all_families") {
+        true => crate::connectivity::IpFamilies::all(),
+        _ => crate::connectivity::detect(),
+    };
+    let tmp_dir = match create_tmp_dir() {
+        Ok(_t) => _t,
+        Err(e) => {
+            log_error!("{}", tr!("Failed to create a temporary directory ({}).", e));
+            return 1;
+        }
+    };
+    let code = crate::fleet::run(inventory, &tmp_dir, cache_path, &families);
+    let _ret = fs::remove_dir_all(&tmp_dir);
+    code
+}
+
+fn history_command(matches: &clap::ArgMatches) {
+    let history_path = match matches.get_one::<PathBuf>("history") {
+        Some(_h) => _h,
+        _ => {
+            log_error!(
+                "{}",
+                tr!("The path to the history database is required ('--history').")
+            );
+            process::exit(1);
+        }
+    };
+
+    if let Some(("export", export_matches)) = matches.subcommand() {
+        let since = match export_matches.get_one::<std::time::Duration>("since") {
+            Some(_s) => crate::history::unix_time() - _s.as_secs() as i64,
+            _ => 0,
+        };
+
+        let records = match crate::history::open(history_path)
+            .and_then(|conn| crate::history::load(&conn, since))
+        {
+            Ok(_r) => _r,
+            Err(e) => {
+                log_error!("{}", tr!("Failed to read the history database ({}).", e));
+                process::exit(1);
             }
+        };
+
+        let mut out = io::stdout().lock();
+        let res = match export_matches
+            .get_one::<String>("format")
+            .map(|f| f.as_str())
+        {
+            Some("json") => crate::history::write_json(&records, &mut out),
+            _ => crate::history::write_csv(&records, &mut out),
+        };
+        if let Err(e) = res {
+            log_error!("{}", tr!("Failed to export the history ({}).", e));
+            process::exit(1);
         }
     }
 }
 
-fn check_permissions(path: &PathBuf) -> io::Result<bool> {
+fn check_permissions(path: &Path) -> io::Result<bool> {
     let md = fs::metadata(path)?;
     let permissions = md.permissions();
     Ok(permissions.readonly())
 }
 
 fn create_tmp_dir() -> io::Result<PathBuf> {
-    let tmp_dir = Builder::new().prefix("peers_updater_").tempdir()?;
-    Ok(tmp_dir.into_path())
-}
-
-fn download_archive(tmp_dir: &PathBuf) -> io::Result<bool> {
-    let mut resp = reqwest::blocking::get(
-        "https://github.com/yggdrasil-network/public-peers/archive/refs/heads/master.zip",
-    )
-    .expect("request failed");
-    let mut out = File::create(format!("{}/peers.zip", tmp_dir.display()))?;
-    io::copy(&mut resp, &mut out)?;
-    Ok(true)
+    let tmp_dir = Builder::new()
+        .prefix("peers_updater_")
+        .tempdir_in(crate::defaults::tmp_dir())?;
+    Ok(tmp_dir.keep())
 }
@@ -0,0 +1,163 @@
+use crate::i18n::tr;
+use crate::logging::log_error;
+use std::io::{self, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// A measurement of the finished cycle: the run totals or a probed peer
+struct Point {
+    measurement: &'static str,
+    tags: Vec<(&'static str, String)>,
+    fields: Vec<(&'static str, u64)>,
+}
+
+// Pushes the measurements of the finished cycle to InfluxDB ('--influx-url')
+// and Graphite ('--graphite')
+pub fn push(matches: &clap::ArgMatches, exit_code: i32) {
+    let influx_url = matches.get_one::<String>("influx_url");
+    let graphite = matches.get_one::<String>("graphite");
+    if influx_url.is_none() && graphite.is_none() {
+        return;
+    }
+
+    let time = crate::history::unix_time();
+    let points = points(exit_code);
+
+    if let Some(_url) = influx_url {
+        let token = matches.get_one::<String>("influx_token");
+        if let Err(e) = push_influx(_url, token.map(|t| t.as_str()), &points, time) {
+            log_error!("{}", tr!("Failed to push the metrics to InfluxDB ({}).", e));
+        }
+    }
+    if let Some(_addr) = graphite {
+        let prefix = matches
+            .get_one::<String>("graphite_prefix")
+            .map(|p| p.as_str())
+            .unwrap_or("peers_updater");
+        if let Err(e) = push_graphite(_addr, prefix, &points, time) {
+            log_error!("{}", tr!("Failed to push the metrics to Graphite ({}).", e));
+        }
+    }
+}
+
+fn points(exit_code: i32) -> Vec<Point> {
+    let probes = crate::report::cycle_probes();
+    let alive: Vec<&serde_json::Value> = probes.iter().filter(|p| p["alive"] == true).collect();
+
+    let mut points = vec![Point {
+        measurement: "peers_updater_run",
+        tags: Vec::new(),
+        fields: vec![
+            ("probed", probes.len() as u64),
+            ("alive", alive.len() as u64),
+            ("selected", crate::report::cycle_selected() as u64),
+            ("changed", crate::report::changed() as u64),
+            ("exit_code", exit_code as u64),
+        ],
+    }];
+    for peer in alive {
+        let tag = |key: &str| peer[key].as_str().unwrap_or("").to_string();
+        points.push(Point {
+            measurement: "peers_updater_peer",
+            tags: vec![
+                ("uri", tag("uri")),
+                ("proto", tag("proto")),
+                ("region", tag("region")),
+                ("country", tag("country")),
+            ],
+            fields: vec![("latency_ms", peer["latency_ms"].as_u64().unwrap_or(0))],
+        });
+    }
+    points
+}
+
+// The line protocol: 'measurement,tag=value field=1i timestamp'
+fn influx_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace('=', "\\=")
+        .replace(' ', "\\ ")
+}
+
+fn push_influx(url: &str, token: Option<&str>, points: &[Point], time: i64) -> io::Result<()> {
+    let body: String = points
+        .iter()
+        .map(|point| {
+            let tags: String = point
+                .tags
+                .iter()
+                .filter(|(_, v)| !v.is_empty())
+                .map(|(k, v)| format!(",{}={}", k, influx_escape(v)))
+                .collect();
+            let fields: Vec<String> = point
+                .fields
+                .iter()
+                .map(|(k, v)| format!("{}={}i", k, v))
+                .collect();
+            format!(
+                "{}{} {} {}\n",
+                point.measurement,
+                tags,
+                fields.join(","),
+                time
+            )
+        })
+        .collect();
+
+    // The timestamps are in seconds (InfluxDB 1.x /write and 2.x /api/v2/write)
+    let url = match url.contains("precision=") {
+        true => url.to_string(),
+        _ if url.contains('?') => format!("{}&precision=s", url),
+        _ => format!("{}?precision=s", url),
+    };
+
+    let client = reqwest::blocking::Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .map_err(io::Error::other)?;
+    let mut request = client.post(url).body(body);
+    if let Some(_t) = token {
+        request = request.header("Authorization", format!("Token {}", _t));
+    }
+    request
+        .send()
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| io::Error::other(e.without_url()))?;
+    Ok(())
+}
+
+// Graphite paths take only letters, digits, '-' and '_' in a component
+fn graphite_component(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() || c == '-' {
+            true => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+// The plaintext protocol: 'prefix.peers.<uri>.latency_ms 42 <timestamp>'
+fn push_graphite(addr: &str, prefix: &str, points: &[Point], time: i64) -> io::Result<()> {
+    let mut lines = String::new();
+    for point in points {
+        let path = match point.tags.first() {
+            Some((_, _uri)) => format!("{}.peers.{}", prefix, graphite_component(_uri)),
+            _ => format!("{}.run", prefix),
+        };
+        for (field, value) in &point.fields {
+            lines.push_str(&format!("{}.{} {} {}\n", path, field, value, time));
+        }
+    }
+
+    let addr = addr
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, addr.to_string()))?;
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    stream.write_all(lines.as_bytes())
+}
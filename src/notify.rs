@@ -0,0 +1,411 @@
+use crate::i18n::tr;
+use crate::logging::log_error;
+use nu_json::{Map, Value};
+use openssl::ssl::{SslConnector, SslMethod};
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+// The CA bundles of the common distributions (the vendored OpenSSL has no
+// default paths)
+const CA_FILES: [&str; 3] = [
+    "/etc/ssl/certs/ca-certificates.crt",
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    "/etc/ssl/cert.pem",
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Event {
+    // The peers of a configuration were changed or the service was restarted
+    Update,
+    // The run ended with an error
+    Failure,
+}
+
+enum Backend {
+    Webhook {
+        url: String,
+    },
+    Telegram {
+        token: String,
+        chat_id: String,
+    },
+    Gotify {
+        url: String,
+        token: String,
+        priority: u64,
+    },
+    Ntfy {
+        url: String,
+        token: Option<String>,
+    },
+    Email(Smtp),
+}
+
+struct Smtp {
+    server: String,
+    port: u16,
+    // false: a local relay without TLS
+    tls: bool,
+    username: Option<String>,
+    password: Option<String>,
+    from: String,
+    to: Vec<String>,
+}
+
+// An entry of the 'notify' array of the settings file:
+// { type: "telegram", token: "123:ABC", chat_id: "42", on: ["update", "failure"] }
+pub struct Notifier {
+    backend: Backend,
+    on: Vec<Event>,
+}
+
+pub fn parse(value: &Value) -> Result<Vec<Notifier>, String> {
+    let entries = match value {
+        Value::Array(_a) => _a,
+        _ => return Err(tr!("'notify' in the settings file must be an array.")),
+    };
+    entries
+        .iter()
+        .map(|entry| match entry {
+            Value::Object(_o) => parse_notifier(_o),
+            _ => Err(tr!("'notify' in the settings file must be an array.")),
+        })
+        .collect()
+}
+
+fn parse_notifier(entry: &Map<String, Value>) -> Result<Notifier, String> {
+    let kind = entry.get("type").and_then(|v| v.as_str()).unwrap_or("");
+    let string = |key: &str| -> Option<String> {
+        match entry.get(key) {
+            Some(Value::String(_s)) => Some(_s.clone()),
+            Some(Value::I64(_n)) => Some(_n.to_string()),
+            Some(Value::U64(_n)) => Some(_n.to_string()),
+            _ => None,
+        }
+    };
+    let required = |key: &str| -> Result<String, String> {
+        string(key).ok_or_else(|| tr!("The '{}' notifier requires '{}'.", kind, key))
+    };
+
+    let backend = match kind {
+        "webhook" => Backend::Webhook {
+            url: required("url")?,
+        },
+        "telegram" => Backend::Telegram {
+            token: required("token")?,
+            chat_id: required("chat_id")?,
+        },
+        "gotify" => Backend::Gotify {
+            url: required("url")?,
+            token: required("token")?,
+            priority: entry.get("priority").and_then(|v| v.as_u64()).unwrap_or(5),
+        },
+        "ntfy" => Backend::Ntfy {
+            url: required("url")?,
+            token: string("token"),
+        },
+        "email" => {
+            let port = match entry.get("port").map(|v| v.as_u64()) {
+                Some(Some(_p)) if _p > 0 && _p <= u16::MAX as u64 => _p as u16,
+                Some(_) => return Err(tr!("Incorrect port of the '{}' notifier.", kind)),
+                _ => 587,
+            };
+            let to = match entry.get("to") {
+                Some(Value::Array(_a)) => _a
+                    .iter()
+                    .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect(),
+                _ => vec![required("to")?],
+            };
+            Backend::Email(Smtp {
+                server: required("server")?,
+                port,
+                tls: entry.get("tls").and_then(|v| v.as_bool()).unwrap_or(true),
+                username: string("username"),
+                password: string("password"),
+                from: required("from")?,
+                to,
+            })
+        }
+        _ => return Err(tr!("Unknown notifier type '{}'.", kind)),
+    };
+
+    let on = match entry.get("on") {
+        Some(_v) => {
+            let names: Vec<&str> = match _v {
+                Value::String(_s) => vec![_s.as_str()],
+                Value::Array(_a) => _a.iter().filter_map(|v| v.as_str()).collect(),
+                _ => Vec::new(),
+            };
+            names
+                .into_iter()
+                .map(|name| match name {
+                    "update" => Ok(Event::Update),
+                    "failure" => Ok(Event::Failure),
+                    _ => Err(tr!("Unknown notification event '{}'.", name)),
+                })
+                .collect::<Result<Vec<Event>, String>>()?
+        }
+        _ => vec![Event::Update, Event::Failure],
+    };
+
+    Ok(Notifier { backend, on })
+}
+
+// Sends the notifications about the finished cycle: on a failure, or when the
+// peers were changed
+pub fn send(notifiers: &[Notifier], exit_code: i32) {
+    let event = match exit_code {
+        0 if crate::report::changed() => Event::Update,
+        0 => return,
+        _ => Event::Failure,
+    };
+    let notifiers: Vec<&Notifier> = notifiers.iter().filter(|n| n.on.contains(&event)).collect();
+    if notifiers.is_empty() {
+        return;
+    }
+
+    let host = hostname();
+    let title = match event {
+        Event::Update => tr!("peers_updater on {}: the peers were updated", host),
+        _ => tr!("peers_updater on {}: the update failed", host),
+    };
+    let summary = crate::report::cycle_summary();
+    let errors = crate::status::cycle_errors();
+    let text = summary
+        .iter()
+        .chain(errors.iter())
+        .map(|s| s.as_str())
+        .collect::<Vec<&str>>()
+        .join("\n");
+
+    for notifier in notifiers {
+        let result = match &notifier.backend {
+            Backend::Webhook { url } => post(
+                url,
+                &[("Content-Type", "application/json")],
+                serde_json::json!({
+                    "event": match event {
+                        Event::Update => "update",
+                        _ => "failure",
+                    },
+                    "host": host,
+                    "exit_code": exit_code,
+                    "summary": summary,
+                    "errors": errors,
+                })
+                .to_string(),
+            ),
+            Backend::Telegram { token, chat_id } => post(
+                &format!("https://api.telegram.org/bot{}/sendMessage", token),
+                &[("Content-Type", "application/json")],
+                serde_json::json!({
+                    "chat_id": chat_id,
+                    "text": format!("{}\n\n{}", title, text),
+                })
+                .to_string(),
+            ),
+            Backend::Gotify {
+                url,
+                token,
+                priority,
+            } => post(
+                &format!("{}/message", url.trim_end_matches('/')),
+                &[
+                    ("Content-Type", "application/json"),
+                    ("X-Gotify-Key", token),
+                ],
+                serde_json::json!({
+                    "title": title,
+                    "message": text,
+                    "priority": priority,
+                })
+                .to_string(),
+            ),
+            Backend::Ntfy { url, token } => {
+                let auth = token.as_ref().map(|t| format!("Bearer {}", t));
+                let mut headers = vec![("Title", title.as_str())];
+                if let Some(_a) = &auth {
+                    headers.push(("Authorization", _a));
+                }
+                post(url, &headers, text.clone())
+            }
+            Backend::Email(_smtp) => send_email(_smtp, &host, &title, &text),
+        };
+        if let Err(e) = result {
+            log_error!(
+                "{}",
+                tr!(
+                    "Failed to send the {} notification ({}).",
+                    notifier.backend.name(),
+                    e
+                )
+            );
+        }
+    }
+}
+
+impl Backend {
+    fn name(&self) -> &str {
+        match self {
+            Backend::Webhook { .. } => "webhook",
+            Backend::Telegram { .. } => "telegram",
+            Backend::Gotify { .. } => "gotify",
+            Backend::Ntfy { .. } => "ntfy",
+            Backend::Email(_) => "email",
+        }
+    }
+}
+
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+fn post(url: &str, headers: &[(&str, &str)], body: String) -> io::Result<()> {
+    let client = reqwest::blocking::Client::builder()
+        .user_agent(format!("peers_updater/{}", crate::version::APP_VERSION))
+        .timeout(TIMEOUT)
+        .build()
+        .map_err(io::Error::other)?;
+
+    let mut request = client.post(url).body(body);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    // The URL may contain the token (Telegram)
+    request
+        .send()
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| io::Error::other(e.without_url()))?;
+    Ok(())
+}
+
+// A minimal SMTP client: implicit TLS on port 465, STARTTLS otherwise
+fn send_email(smtp: &Smtp, host: &str, subject: &str, text: &str) -> io::Result<()> {
+    let addr = (smtp.server.as_str(), smtp.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, smtp.server.clone()))?;
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    let message = email_message(smtp, subject, text);
+    if !smtp.tls {
+        reply(&mut stream, '2')?;
+        command(&mut stream, &format!("EHLO {}", host), '2')?;
+        return deliver(&mut stream, smtp, &message);
+    }
+
+    let mut builder = SslConnector::builder(SslMethod::tls()).map_err(io::Error::other)?;
+    if let Some(_ca) = CA_FILES.iter().find(|f| std::path::Path::new(f).exists()) {
+        builder.set_ca_file(_ca).map_err(io::Error::other)?;
+    }
+    let connector = builder.build();
+
+    if smtp.port != 465 {
+        reply(&mut stream, '2')?;
+        command(&mut stream, &format!("EHLO {}", host), '2')?;
+        command(&mut stream, "STARTTLS", '2')?;
+    }
+    let mut stream = connector
+        .connect(&smtp.server, stream)
+        .map_err(io::Error::other)?;
+    if smtp.port == 465 {
+        reply(&mut stream, '2')?;
+    }
+    command(&mut stream, &format!("EHLO {}", host), '2')?;
+    deliver(&mut stream, smtp, &message)
+}
+
+fn deliver<S: Read + Write>(stream: &mut S, smtp: &Smtp, message: &str) -> io::Result<()> {
+    if let (Some(_user), Some(_password)) = (&smtp.username, &smtp.password) {
+        let credentials = format!("\0{}\0{}", _user, _password);
+        command(
+            stream,
+            &format!(
+                "AUTH PLAIN {}",
+                openssl::base64::encode_block(credentials.as_bytes())
+            ),
+            '2',
+        )?;
+    }
+    command(stream, &format!("MAIL FROM:<{}>", smtp.from), '2')?;
+    for to in &smtp.to {
+        command(stream, &format!("RCPT TO:<{}>", to), '2')?;
+    }
+    command(stream, "DATA", '3')?;
+    stream.write_all(message.as_bytes())?;
+    command(stream, ".", '2')?;
+    let _ = command(stream, "QUIT", '2');
+    Ok(())
+}
+
+fn email_message(smtp: &Smtp, subject: &str, text: &str) -> String {
+    let subject = match subject.is_ascii() {
+        true => subject.to_string(),
+        _ => format!(
+            "=?UTF-8?B?{}?=",
+            openssl::base64::encode_block(subject.as_bytes())
+        ),
+    };
+    let mut message = format!(
+        "From: <{}>\r\nTo: {}\r\nSubject: {}\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+        smtp.from,
+        smtp.to
+            .iter()
+            .map(|t| format!("<{}>", t))
+            .collect::<Vec<String>>()
+            .join(", "),
+        subject
+    );
+    // Dot-stuffing: a line starting with '.' gets another one
+    for line in text.lines() {
+        if line.starts_with('.') {
+            message.push('.');
+        }
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message
+}
+
+fn command<S: Read + Write>(stream: &mut S, line: &str, expected: char) -> io::Result<()> {
+    stream.write_all(format!("{}\r\n", line).as_bytes())?;
+    reply(stream, expected)
+}
+
+// Reads a (multiline) reply and checks the first digit of its code
+fn reply<S: Read>(stream: &mut S, expected: char) -> io::Result<()> {
+    let mut data: Vec<u8> = Vec::new();
+    let mut buf = [0u8; 512];
+    loop {
+        let n = stream.read(&mut buf)?;
+        if n == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+        data.extend(&buf[..n]);
+        let text = String::from_utf8_lossy(&data);
+        if !text.ends_with("\r\n") {
+            continue;
+        }
+        // The last line is 'NNN text', the others are 'NNN-text'
+        let last = text.trim_end().rsplit("\r\n").next().unwrap_or("");
+        if last.as_bytes().get(3) == Some(&b'-') {
+            continue;
+        }
+        return match last.starts_with(expected) {
+            true => Ok(()),
+            _ => Err(io::Error::other(last.to_string())),
+        };
+    }
+}
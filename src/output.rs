@@ -0,0 +1,185 @@
+use crate::i18n::tr;
+use crate::peer::Peer;
+use crate::redact::redact;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+pub fn print_table(peers: &[Peer]) {
+    print_table_header();
+    for peer in peers {
+        if !peer.is_alive {
+            break;
+        }
+        print_table_row(peer);
+    }
+}
+
+pub fn print_table_header() {
+    println!(
+        "{0:<60}|{1:<6}|{2:<15}|{3:<15}|{4:<10}",
+        tr!("URI"),
+        tr!("Proto"),
+        tr!("Region"),
+        tr!("Country"),
+        tr!("Latency")
+    );
+    println!("{0:-<107}", "-");
+}
+
+// A row of the table; with '--stream' the rows are printed as the probes finish
+pub fn print_table_row(peer: &Peer) {
+    println!(
+        "{0:<60}|{1:<6}|{2:<15}|{3:<15}|{4:<10}",
+        redact(&peer.uri),
+        peer.proto,
+        peer.region,
+        peer.country,
+        peer.latency
+    );
+}
+
+// '--group-by': a section per region, country or protocol. The peers are
+// sorted by latency, so the sections with the best peers come first.
+pub fn print_table_grouped(peers: &[Peer], group_by: &str) {
+    let key = |peer: &Peer| match group_by {
+        "country" => peer.country.clone(),
+        "protocol" => peer.proto.clone(),
+        _ => peer.region.clone(),
+    };
+    let mut groups: Vec<(String, Vec<&Peer>)> = Vec::new();
+    for peer in peers {
+        let name = key(peer);
+        match groups.iter_mut().find(|(g, _)| *g == name) {
+            Some((_, _list)) => _list.push(peer),
+            _ => groups.push((name, vec![peer])),
+        }
+    }
+
+    print_table_header();
+    for (name, list) in &groups {
+        let alive: Vec<&Peer> = list.iter().filter(|p| p.is_alive).copied().collect();
+        println!();
+        match alive.first() {
+            Some(_best) => println!(
+                "{}",
+                tr!(
+                    "{}: {} of {} peers are alive, the best is {} ms",
+                    name,
+                    alive.len(),
+                    list.len(),
+                    _best.latency
+                )
+            ),
+            _ => println!(
+                "{}",
+                tr!("{}: none of {} peers are alive", name, list.len())
+            ),
+        }
+        for peer in alive {
+            print_table_row(peer);
+        }
+    }
+}
+
+// A fragment for configuration.nix
+pub fn print_nix(selected: &[&Peer], extra_peers: &[String]) {
+    println!("services.yggdrasil.settings.Peers = [");
+    for peer in selected {
+        println!(
+            "  \"{}\" # {}/{}, {} ms",
+            nix_escape(&peer.uri),
+            peer.region,
+            peer.country,
+            peer.latency
+        );
+    }
+    for uri in extra_peers {
+        println!("  \"{}\" # extra", nix_escape(uri));
+    }
+    println!("];");
+}
+
+fn nix_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("${", "\\${")
+}
+
+// Renders the template for every selected peer, e.g. "{uri} {latency}ms {country}"
+pub fn print_template(selected: &[&Peer], template: &str) {
+    for peer in selected {
+        println!("{}", render(template, peer));
+    }
+}
+
+fn render(template: &str, peer: &Peer) -> String {
+    [
+        ("{uri}", redact(&peer.uri)),
+        ("{proto}", peer.proto.to_string()),
+        ("{addr}", peer.addr.to_string()),
+        ("{port}", peer.port.to_string()),
+        ("{region}", peer.region.to_string()),
+        ("{country}", peer.country.to_string()),
+        ("{country_code}", peer.country_code.to_string()),
+        ("{latency}", peer.latency.to_string()),
+    ]
+    .iter()
+    .fold(template.to_string(), |text, (placeholder, value)| {
+        text.replace(placeholder, value)
+    })
+}
+
+// The selected peers with their metadata and the extra peers. The secrets
+// are masked unless the list is applied elsewhere ('--emit-peers',
+// '--serve-peers').
+pub fn peers_json(
+    selected: &[&Peer],
+    extra_peers: &[String],
+    mask_secrets: bool,
+) -> serde_json::Value {
+    let uri = |uri: &str| match mask_secrets {
+        true => redact(uri),
+        _ => uri.to_string(),
+    };
+    let peers: Vec<serde_json::Value> = selected
+        .iter()
+        .map(|peer| {
+            serde_json::json!({
+                "uri": uri(&peer.uri),
+                "proto": peer.proto,
+                "region": peer.region,
+                "country": peer.country,
+                "country_code": peer.country_code,
+                "latency_ms": peer.latency,
+                "operator": peer.operator,
+                "contact": peer.contact,
+                "note": peer.note,
+                "extra": false,
+            })
+        })
+        .chain(
+            extra_peers
+                .iter()
+                .map(|_u| serde_json::json!({ "uri": uri(_u), "extra": true })),
+        )
+        .collect();
+
+    serde_json::json!({
+        "time": crate::history::unix_time(),
+        "peers": peers,
+    })
+}
+
+// The file is replaced at once so that its readers never see a partial list
+pub fn write_peers_json(path: &Path, selected: &[&Peer], extra_peers: &[String]) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(_d) if !_d.as_os_str().is_empty() => _d,
+        _ => Path::new("."),
+    };
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    writeln!(tmp, "{}", peers_json(selected, extra_peers, false))?;
+    tmp.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
@@ -9,10 +9,23 @@ pub fn read_config(path: &PathBuf) -> io::Result<String> {
     let mut buffer = String::new();
     f.read_to_string(&mut buffer)?;
 
-    Ok(format!(r#"{}"#, buffer))
-    //Ok(format!("{}", buffer))
+    Ok(buffer)
 }
 
+// The UTF-8 byte order mark some Windows editors add is not a part of the HJSON
 pub fn get_hjson_obj(cfg_txt: &str) -> nu_json::Result<Map<String, Value>> {
-    return nu_json::from_str(&cfg_txt);
+    nu_json::from_str(cfg_txt.trim_start_matches('\u{feff}'))
+}
+
+// The URIs currently listed in the Peers section
+pub fn get_peers(cfg_txt: &str) -> nu_json::Result<Vec<String>> {
+    let conf_obj = get_hjson_obj(cfg_txt)?;
+
+    Ok(match conf_obj.get("Peers").and_then(|p| p.as_array()) {
+        Some(_peers) => _peers
+            .iter()
+            .filter_map(|p| p.as_str().map(|s| s.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    })
 }
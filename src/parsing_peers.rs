@@ -1,17 +1,36 @@
+use crate::i18n::tr;
+use crate::logging::log_error;
 use crate::peer::Peer;
-use regex::Regex;
+use regex::{Captures, Regex};
 use std::fs::File;
 use std::io;
 use std::io::BufRead;
 use std::path::PathBuf;
 use std::process;
+use std::sync::mpsc::Sender;
+use std::sync::LazyLock;
 use walkdir::WalkDir;
 
-pub fn collect_peers(path: &PathBuf, v: &mut Vec<Peer>) -> io::Result<bool> {
-    let re = match Regex::new(r"(tcp|tls)://([a-z0-9\.\-:\[\]]+):([0-9]+)") {
+// A markdown link: '[text](target)' or '[text](mailto:address)'
+static LINK_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[([^\]]*)\]\((?:mailto:([^)]*)|[^)]*)\)").unwrap());
+static OPERATOR_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(?:operated|maintained|run|hosted|provided) by:?\s*([^,;()]+)|(?:operator|maintainer|owner|admin):\s*([^,;()]+)").unwrap()
+});
+// A mail address or a Matrix ID
+static CONTACT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[\w.+-]+@[\w-]+(?:\.[\w-]+)+|@[\w.=-]+:[\w.-]+\.[a-z]{2,}").unwrap()
+});
+
+// The host is a name, an IP address or an IPv6 address with a zone ID ([fe80::1%25eth0])
+const PEER_URI_RE: &str =
+    r"(tcp|tls)://(\[[0-9a-fA-F:\.]+%[0-9A-Za-z_\.\-]+\]|[a-z0-9\.\-:\[\]]+):([0-9]+)";
+
+pub fn collect_peers(path: &PathBuf, tx: &Sender<Peer>) -> io::Result<bool> {
+    let re = match Regex::new(PEER_URI_RE) {
         Ok(_r) => _r,
         Err(e) => {
-            eprintln!("Failed to parse files ({}).", e);
+            log_error!("{}", tr!("Failed to parse files ({}).", e));
             process::exit(1);
         }
     };
@@ -38,29 +57,33 @@ pub fn collect_peers(path: &PathBuf, v: &mut Vec<Peer>) -> io::Result<bool> {
                 _ => "Unknown".to_string(),
             };
 
-            // Reading a file
+            // Reading a file. The text of the URI line and of the list item
+            // the URI is nested in describe the host.
             if let Ok(lines) = read_lines(file.path()) {
-                for line in lines {
-                    if let Ok(str) = line {
-                        for peer_ in re.captures_iter(str.as_str()) {
-                            v.push(Peer::new(
-                                peer_
-                                    .get(0)
-                                    .map_or("".to_string(), |m| m.as_str().to_string()),
-                                peer_
-                                    .get(2)
-                                    .map_or("".to_string(), |m| m.as_str().to_string()),
-                                peer_
-                                    .get(3)
-                                    .map_or("".to_string(), |m| m.as_str().to_string()),
-                                // peer_
-                                //     .get(1)
-                                //     .map_or("".to_string(), |m| m.as_str().to_string()),
-                                region.to_owned(),
-                                country.to_owned(),
-                                false,
-                                99999,
-                            ));
+                let mut context: Option<(usize, String)> = None;
+                for str in lines.map_while(Result::ok) {
+                    let indent = str.len() - str.trim_start().len();
+                    let own_text = note_text(&re.replace_all(&str, ""));
+                    if !re.is_match(&str) {
+                        if let Some(_t) = own_text {
+                            context = Some((indent, _t));
+                        }
+                        continue;
+                    }
+                    let parent = context
+                        .as_ref()
+                        .filter(|(_i, _)| *_i < indent)
+                        .map(|(_, _t)| _t.clone());
+                    let note = match (parent, own_text) {
+                        (Some(_p), Some(_o)) => Some(format!("{}; {}", _p, _o)),
+                        (_p, _o) => _p.or(_o),
+                    };
+                    for peer_ in re.captures_iter(str.as_str()) {
+                        let mut peer = peer_from_captures(&peer_, &region, &country);
+                        set_operator_info(&mut peer, note.as_deref());
+                        // Passing the peer on to probing right away
+                        if tx.send(peer).is_err() {
+                            return Ok(false);
                         }
                     }
                 }
@@ -71,6 +94,79 @@ pub fn collect_peers(path: &PathBuf, v: &mut Vec<Peer>) -> io::Result<bool> {
     Ok(true)
 }
 
+// A peer that is not taken from the public peers list (e.g. from the configuration)
+pub fn peer_from_uri(uri: &str) -> Option<Peer> {
+    let re = Regex::new(PEER_URI_RE).ok()?;
+    let peer_ = re.captures(uri)?;
+
+    let mut peer = peer_from_captures(&peer_, "Unknown", "Unknown");
+    peer.uri = crate::uri_normalize::encode_zone(uri);
+    Some(peer)
+}
+
+fn peer_from_captures(peer_: &Captures, region: &str, country: &str) -> Peer {
+    let country_code = crate::countries::iso_code(country).unwrap_or("");
+
+    Peer::new(
+        peer_.get(0).map_or("".to_string(), |m| {
+            crate::uri_normalize::encode_zone(m.as_str())
+        }),
+        peer_.get(2).map_or("".to_string(), |m| {
+            crate::uri_normalize::encode_zone(m.as_str())
+        }),
+        peer_
+            .get(3)
+            .map_or("".to_string(), |m| m.as_str().to_string()),
+        peer_
+            .get(1)
+            .map_or("".to_string(), |m| m.as_str().to_string()),
+        region.to_owned(),
+        country.to_owned(),
+        country_code.to_string(),
+    )
+}
+
+// The text of a markdown line without the list marker, the heading marks and
+// the code spans left from the URIs
+fn note_text(line: &str) -> Option<String> {
+    let text = line
+        .replace("``", "")
+        .trim()
+        .trim_start_matches(['*', '-', '+', '#'])
+        .trim()
+        .trim_matches([',', ';', ':'])
+        .trim()
+        .to_string();
+    match text.chars().any(|c| c.is_alphanumeric()) {
+        true => Some(text),
+        _ => None,
+    }
+}
+
+// 'operated by [Name](https://...)', 'maintainer: Name', a mail address or a
+// Matrix ID in the note
+fn set_operator_info(peer: &mut Peer, note: Option<&str>) {
+    let note = match note {
+        Some(_n) => _n,
+        _ => return,
+    };
+    // The links are shown by their text, the mail links by the address
+    let plain = LINK_RE
+        .replace_all(note, |c: &regex::Captures| match c.get(2) {
+            Some(_m) => _m.as_str().to_string(),
+            _ => c[1].to_string(),
+        })
+        .to_string();
+
+    peer.operator = OPERATOR_RE
+        .captures(&plain)
+        .and_then(|c| c.get(1).or_else(|| c.get(2)))
+        .map(|m| m.as_str().trim().trim_end_matches('.').to_string())
+        .filter(|o| !o.is_empty());
+    peer.contact = CONTACT_RE.find(&plain).map(|m| m.as_str().to_string());
+    peer.note = Some(plain);
+}
+
 fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
 where
     P: AsRef<std::path::Path>,
@@ -1,13 +1,30 @@
+use std::time::Duration;
+
+// Time spent in the individual probe stages (filled in only when requested)
+#[derive(Default, Clone)]
+pub struct ProbeTimings {
+    pub dns: Option<Duration>,
+    pub connect: Option<Duration>,
+    pub tls: Option<Duration>,
+}
+
 //#[derive(Debug)]
+#[derive(Clone)]
 pub struct Peer {
     pub uri: String,
     pub addr: String,
     pub port: String,
-    //proto: String,
+    pub proto: String,
     pub region: String,
     pub country: String,
+    pub country_code: String,
     pub is_alive: bool,
     pub latency: u128,
+    pub timings: ProbeTimings,
+    // The operator notes next to the URI in the peer list
+    pub operator: Option<String>,
+    pub contact: Option<String>,
+    pub note: Option<String>,
 }
 
 impl Peer {
@@ -15,21 +32,25 @@ impl Peer {
         uri: String,
         addr: String,
         port: String,
-        //proto: String,
+        proto: String,
         region: String,
         country: String,
-        is_alive: bool,
-        latency: u128,
+        country_code: String,
     ) -> Self {
         Peer {
             uri,
             addr,
             port,
-            //proto,
+            proto,
             region,
             country,
-            is_alive,
-            latency,
+            country_code,
+            is_alive: false,
+            latency: 99999,
+            timings: ProbeTimings::default(),
+            operator: None,
+            contact: None,
+            note: None,
         }
     }
 }
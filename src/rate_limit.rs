@@ -0,0 +1,40 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// '--probe-rate': the probes open their connections no faster than this many
+// per second (a burst of SYNs looks like a port scan to some routers and IDS)
+struct Limit {
+    interval: Option<Duration>,
+    next: Option<Instant>,
+}
+
+static LIMIT: Mutex<Limit> = Mutex::new(Limit {
+    interval: None,
+    next: None,
+});
+
+pub fn set(per_sec: Option<u32>) {
+    if let Ok(mut limit) = LIMIT.lock() {
+        limit.interval = per_sec
+            .filter(|r| *r > 0)
+            .map(|r| Duration::from_secs(1) / r);
+        limit.next = None;
+    }
+}
+
+// Waits for the turn of the next connection
+pub fn wait() {
+    let slot = match LIMIT.lock() {
+        Ok(mut limit) => match limit.interval {
+            Some(_i) => {
+                let now = Instant::now();
+                let slot = limit.next.map_or(now, |n| n.max(now));
+                limit.next = Some(slot + _i);
+                slot
+            }
+            _ => return,
+        },
+        _ => return,
+    };
+    std::thread::sleep(slot.saturating_duration_since(Instant::now()));
+}
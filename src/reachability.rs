@@ -0,0 +1,334 @@
+use crate::connectivity::IpFamilies;
+use crate::i18n::tr;
+use crate::logging::log_info;
+use crate::peer::Peer;
+use std::collections::BTreeMap;
+use std::thread::ScopedJoinHandle;
+
+// The share of alive peers below which a class is considered filtered
+const MIN_ALIVE_RATIO: f64 = 0.2;
+// Classes with fewer probed peers tell nothing
+const MIN_PROBED: usize = 5;
+// The peers of every class probed by the pre-flight check
+const PREFLIGHT_SAMPLE: usize = 3;
+
+// Peers are grouped by the protocol and the kind of the destination port:
+// some networks only let through the well-known ones
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PortKind {
+    Https,
+    WellKnown,
+    High,
+}
+
+impl PortKind {
+    fn of(port: &str) -> Self {
+        match port.parse::<u16>() {
+            Ok(443) => PortKind::Https,
+            Ok(_p) if _p < 1024 => PortKind::WellKnown,
+            _ => PortKind::High,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            PortKind::Https => "443",
+            PortKind::WellKnown => "<1024",
+            PortKind::High => ">=1024",
+        }
+    }
+}
+
+#[derive(Default)]
+struct ClassStats {
+    probed: usize,
+    alive: usize,
+}
+
+// The protocol/port classes and how many of their peers answered
+pub struct Reachability {
+    classes: BTreeMap<(String, PortKind), ClassStats>,
+}
+
+impl Reachability {
+    pub fn from_probes(peers: &[Peer]) -> Self {
+        let mut classes: BTreeMap<(String, PortKind), ClassStats> = BTreeMap::new();
+        for peer in peers {
+            let stats = classes.entry(class_of(peer)).or_default();
+            stats.probed += 1;
+            if peer.is_alive {
+                stats.alive += 1;
+            }
+        }
+        Reachability { classes }
+    }
+
+    // A class is filtered when almost none of its peers answered while
+    // other classes work
+    pub fn is_filtered(&self, peer: &Peer) -> bool {
+        let any_working = self
+            .classes
+            .values()
+            .any(|s| s.probed >= MIN_PROBED && ratio(s) >= MIN_ALIVE_RATIO);
+        match self.classes.get(&class_of(peer)) {
+            Some(_s) => any_working && _s.probed >= MIN_PROBED && ratio(_s) < MIN_ALIVE_RATIO,
+            _ => false,
+        }
+    }
+
+    pub fn print(&self) {
+        for ((proto, kind), stats) in &self.classes {
+            log_info!(
+                "{}",
+                tr!(
+                    "{} to ports {}: {} of {} peers are alive.",
+                    proto,
+                    kind.name(),
+                    stats.alive,
+                    stats.probed
+                )
+            );
+        }
+    }
+}
+
+fn class_of(peer: &Peer) -> (String, PortKind) {
+    (peer.proto.to_string(), PortKind::of(&peer.port))
+}
+
+fn ratio(stats: &ClassStats) -> f64 {
+    match stats.probed {
+        0 => 0.0,
+        _ => stats.alive as f64 / stats.probed as f64,
+    }
+}
+
+// '--prefer-reachable': the first peers of every class are probed before the
+// rest of the class, which waits for their answers. A class none of whose
+// sampled peers answered while another class works is blocked by this
+// network, and the rest of its peers are not probed (they stay dead).
+#[derive(Default)]
+struct Preflight {
+    gates: BTreeMap<(String, PortKind), Gate>,
+}
+
+#[derive(Default)]
+struct Gate {
+    // The sampled peers still being probed, the answered and the alive ones
+    pending: usize,
+    answered: usize,
+    alive: usize,
+    // The peers waiting for the samples
+    held: Vec<Peer>,
+    // Whether the class works, once it is known
+    open: Option<bool>,
+    skipped: usize,
+}
+
+enum Admission {
+    Sample(Peer),
+    Probe(Peer),
+    Skip(Peer),
+    Held,
+}
+
+impl Preflight {
+    fn admit(&mut self, peer: Peer) -> Admission {
+        let gate = self.gates.entry(class_of(&peer)).or_default();
+        match gate.open {
+            Some(true) => Admission::Probe(peer),
+            Some(false) => {
+                gate.skipped += 1;
+                Admission::Skip(peer)
+            }
+            _ if gate.pending + gate.answered < PREFLIGHT_SAMPLE => {
+                gate.pending += 1;
+                Admission::Sample(peer)
+            }
+            _ => {
+                gate.held.push(peer);
+                Admission::Held
+            }
+        }
+    }
+
+    // The result of a sampled peer, None if it was not probed (e.g. it has no
+    // address of a reachable IP family): another peer is sampled instead
+    fn record(&mut self, sampled: &Peer, result: Option<&Peer>) {
+        if let Some(_g) = self.gates.get_mut(&class_of(sampled)) {
+            _g.pending -= 1;
+            if let Some(_r) = result {
+                _g.answered += 1;
+                if _r.is_alive {
+                    _g.alive += 1;
+                }
+            }
+        }
+    }
+
+    // Decides the classes whose samples have answered and lets their held
+    // peers through. Once all the peers are admitted (`last`), every class is
+    // decided: without another working class nothing is blocked.
+    fn settle(&mut self, last: bool) -> Vec<Admission> {
+        let any_working = self.gates.values().any(|g| g.alive > 0);
+        let mut admitted = Vec::new();
+        for gate in self.gates.values_mut().filter(|g| g.open.is_none()) {
+            let sampled = gate.pending == 0 && gate.answered >= PREFLIGHT_SAMPLE;
+            if gate.alive > 0 {
+                gate.open = Some(true);
+            } else if sampled && any_working {
+                gate.open = Some(false);
+            } else if last && gate.pending == 0 && (sampled || gate.held.is_empty()) {
+                gate.open = Some(true);
+            }
+
+            match gate.open {
+                Some(true) => admitted.extend(gate.held.drain(..).map(Admission::Probe)),
+                Some(false) => {
+                    gate.skipped += gate.held.len();
+                    admitted.extend(gate.held.drain(..).map(Admission::Skip));
+                }
+                _ => {
+                    while gate.pending + gate.answered < PREFLIGHT_SAMPLE && !gate.held.is_empty() {
+                        gate.pending += 1;
+                        admitted.push(Admission::Sample(gate.held.remove(0)));
+                    }
+                }
+            }
+        }
+        admitted
+    }
+
+    fn log_blocked(&self) {
+        for ((proto, kind), gate) in &self.gates {
+            if gate.open == Some(false) {
+                log_info!(
+                    "{}",
+                    tr!(
+                        "{} to ports {} seems to be blocked by this network: none of the {} sampled peers answered, the other {} are not probed.",
+                        proto,
+                        kind.name(),
+                        gate.answered,
+                        gate.skipped
+                    )
+                );
+            }
+        }
+    }
+}
+
+// Probes the peers through `probe` as they come (the probing pipeline), the
+// rest of every class after its samples. Returns the probed peers and the
+// ones of the blocked classes.
+pub fn probe_gated<'s>(
+    candidates: impl Iterator<Item = Peer>,
+    probe: impl Fn(usize, Peer) -> Option<ScopedJoinHandle<'s, Option<Peer>>>,
+) -> Vec<Peer> {
+    let mut preflight = Preflight::default();
+    let mut candidates = candidates.fuse();
+    let mut samples: Vec<(Peer, Option<ScopedJoinHandle<'s, Option<Peer>>>)> = Vec::new();
+    let mut probes = Vec::new();
+    let mut done: Vec<Peer> = Vec::new();
+    let mut started = 0;
+    let mut last = false;
+
+    loop {
+        let mut admitted = Vec::new();
+        match candidates.next() {
+            Some(_p) => admitted.push(preflight.admit(_p)),
+            _ => last = true,
+        }
+
+        let mut answered = Vec::new();
+        let mut i = 0;
+        while i < samples.len() {
+            match &samples[i].1 {
+                Some(_h) if !_h.is_finished() => i += 1,
+                _ => answered.push(samples.swap_remove(i)),
+            }
+        }
+        // All the peers are parsed: waiting for the next answer
+        if last && answered.is_empty() && !samples.is_empty() {
+            answered.push(samples.remove(0));
+        }
+        for (sampled, handle) in answered {
+            let result = handle.and_then(|h| h.join().ok().flatten());
+            preflight.record(&sampled, result.as_ref());
+            done.extend(result);
+        }
+
+        admitted.extend(preflight.settle(last));
+        for admission in admitted {
+            match admission {
+                Admission::Sample(_p) => samples.push((_p.clone(), probe(started, _p))),
+                Admission::Probe(_p) => probes.push(probe(started, _p)),
+                Admission::Skip(_p) => {
+                    done.push(_p);
+                    continue;
+                }
+                Admission::Held => continue,
+            }
+            started += 1;
+        }
+
+        if last && samples.is_empty() {
+            break;
+        }
+    }
+
+    preflight.log_blocked();
+    done.extend(
+        probes
+            .into_iter()
+            .flatten()
+            .filter_map(|probe| probe.join().ok().flatten()),
+    );
+    done
+}
+
+// The pre-flight check of the peers that are all known beforehand
+// ('--sample'): the samples of every class are probed at once. Returns the
+// peers that are done (the sampled and the blocked ones) and the peers left
+// to probe.
+pub fn preflight(peers: Vec<Peer>, families: &IpFamilies) -> (Vec<Peer>, Vec<Peer>) {
+    let mut preflight = Preflight::default();
+    let mut admitted: Vec<Admission> = peers.into_iter().map(|p| preflight.admit(p)).collect();
+    let (mut done, mut rest) = (Vec::new(), Vec::new());
+
+    while !admitted.is_empty() {
+        let mut samples = Vec::new();
+        for admission in admitted {
+            match admission {
+                Admission::Sample(_p) => samples.push(_p),
+                Admission::Probe(_p) => rest.push(_p),
+                Admission::Skip(_p) => done.push(_p),
+                Admission::Held => {}
+            }
+        }
+
+        let results: Vec<(Peer, Option<Peer>)> = std::thread::scope(|scope| {
+            let probes: Vec<_> = samples
+                .into_iter()
+                .map(|mut peer| {
+                    let sampled = peer.clone();
+                    let probe = scope.spawn(move || {
+                        crate::latency::set_latency(&mut peer, families, false).then_some(peer)
+                    });
+                    (sampled, probe)
+                })
+                .collect();
+            probes
+                .into_iter()
+                .map(|(sampled, probe)| (sampled, probe.join().ok().flatten()))
+                .collect()
+        });
+        for (sampled, result) in results {
+            preflight.record(&sampled, result.as_ref());
+            done.extend(result);
+        }
+        admitted = preflight.settle(true);
+    }
+
+    preflight.log_blocked();
+    (done, rest)
+}
@@ -0,0 +1,111 @@
+use crate::escalate::Escalation;
+use crate::i18n::tr;
+use crate::logging::log_error;
+use std::io;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// A machine whose configuration is accessed over SSH (`user@host[:/path]`)
+pub struct Remote {
+    pub host: String,
+    pub path: Option<String>,
+}
+
+impl Remote {
+    pub fn parse(spec: &str) -> Self {
+        match spec.split_once(':') {
+            Some((_h, _p)) if !_p.is_empty() => Remote {
+                host: _h.to_string(),
+                path: Some(_p.to_string()),
+            },
+            Some((_h, _)) => Remote {
+                host: _h.to_string(),
+                path: None,
+            },
+            _ => Remote {
+                host: spec.to_string(),
+                path: None,
+            },
+        }
+    }
+
+    pub fn read_file(&self, path: &str, escalation: Option<&Escalation>) -> io::Result<String> {
+        let output = Command::new("ssh")
+            .arg(&self.host)
+            .arg(format!("{}cat {}", prefix(escalation), shell_quote(path)))
+            .stderr(Stdio::inherit())
+            .output()?;
+
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "ssh exited with {}",
+                output.status
+            )));
+        }
+
+        String::from_utf8(output.stdout).map_err(io::Error::other)
+    }
+
+    pub fn write_file(
+        &self,
+        path: &str,
+        content: &str,
+        escalation: Option<&Escalation>,
+    ) -> io::Result<()> {
+        // Writing through a temporary file so that an interrupted transfer
+        // does not leave a truncated configuration
+        let path = shell_quote(path);
+        let script = format!("cat > {0}.new && cat {0}.new > {0} && rm -f {0}.new", path);
+        let mut child = Command::new("ssh")
+            .arg(&self.host)
+            .arg(match escalation {
+                Some(_e) => format!("{}sh -c {}", _e.shell_prefix(), shell_quote(&script)),
+                _ => script,
+            })
+            .stdin(Stdio::piped())
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(content.as_bytes())?;
+        }
+
+        let status = child.wait()?;
+        match status.success() {
+            true => Ok(()),
+            _ => Err(io::Error::other(format!("ssh exited with {}", status))),
+        }
+    }
+
+    pub fn restart_service(&self, escalation: Option<&Escalation>) -> bool {
+        let error = match Command::new("ssh")
+            .arg(&self.host)
+            .arg(format!(
+                "{}systemctl restart {}",
+                prefix(escalation),
+                shell_quote(crate::service::name())
+            ))
+            .status()
+        {
+            Ok(_s) if _s.success() => return true,
+            Ok(_s) => _s.to_string(),
+            Err(e) => e.to_string(),
+        };
+        log_error!(
+            "{}",
+            tr!(
+                "Failed to restart the Yggdrasil service on {} ({}).",
+                self.host,
+                error
+            )
+        );
+        false
+    }
+}
+
+fn prefix(escalation: Option<&Escalation>) -> String {
+    escalation.map_or("".to_string(), |e| e.shell_prefix())
+}
+
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
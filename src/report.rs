@@ -0,0 +1,335 @@
+use crate::i18n::tr;
+use crate::logging::log_error;
+use crate::peer::Peer;
+use crate::redact::redact;
+use crate::summary::ConfigChange;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+// What the running cycle has done so far, written with '--report-json'
+struct Report {
+    probes: Vec<serde_json::Value>,
+    selection: serde_json::Value,
+    configs: Vec<serde_json::Value>,
+    restarted: Option<bool>,
+    // The lines of the end-of-run summary (for the notifications)
+    summary: Vec<String>,
+}
+
+const EMPTY: Report = Report {
+    probes: Vec::new(),
+    selection: serde_json::Value::Null,
+    configs: Vec::new(),
+    restarted: None,
+    summary: Vec::new(),
+};
+
+static REPORT: Mutex<Report> = Mutex::new(EMPTY);
+
+pub fn record_probes(peers: &[Peer]) {
+    if let Ok(mut report) = REPORT.lock() {
+        report.probes = peers
+            .iter()
+            .map(|peer| {
+                serde_json::json!({
+                    "uri": redact(&peer.uri),
+                    "proto": peer.proto,
+                    "region": peer.region,
+                    "country": peer.country,
+                    "country_code": peer.country_code,
+                    "alive": peer.is_alive,
+                    "latency_ms": match peer.is_alive {
+                        true => Some(peer.latency),
+                        _ => None,
+                    },
+                    "operator": peer.operator,
+                    "contact": peer.contact,
+                })
+            })
+            .collect();
+    }
+}
+
+pub fn record_selection(selected: &[&Peer], extra_peers: &[String]) {
+    if let Ok(mut report) = REPORT.lock() {
+        report.selection = crate::output::peers_json(selected, extra_peers, true)["peers"].take();
+    }
+}
+
+pub fn record_config_change(change: &ConfigChange) {
+    if let Ok(mut report) = REPORT.lock() {
+        report.configs.push(serde_json::json!({
+            "path": change.path,
+            "added": change.added.iter().map(|uri| redact(uri)).collect::<Vec<_>>(),
+            "removed": change.removed.iter().map(|uri| redact(uri)).collect::<Vec<_>>(),
+            "pinned": change.pinned,
+            "written": change.written,
+        }));
+    }
+}
+
+pub fn record_restart(restarted: bool) {
+    if let Ok(mut report) = REPORT.lock() {
+        report.restarted = Some(restarted);
+    }
+}
+
+pub fn record_summary(lines: Vec<String>) {
+    if let Ok(mut report) = REPORT.lock() {
+        report.summary = lines;
+    }
+}
+
+// Whether the running cycle has changed the peers of a configuration or
+// restarted the service
+pub fn changed() -> bool {
+    match REPORT.lock() {
+        Ok(report) => {
+            report.restarted == Some(true)
+                || report.configs.iter().any(|c| {
+                    c["written"] == true
+                        && (c["added"].as_array().is_some_and(|a| !a.is_empty())
+                            || c["removed"].as_array().is_some_and(|a| !a.is_empty()))
+                })
+        }
+        _ => false,
+    }
+}
+
+pub fn cycle_summary() -> Vec<String> {
+    match REPORT.lock() {
+        Ok(report) => report.summary.clone(),
+        _ => Vec::new(),
+    }
+}
+
+pub fn cycle_probes() -> Vec<serde_json::Value> {
+    match REPORT.lock() {
+        Ok(report) => report.probes.clone(),
+        _ => Vec::new(),
+    }
+}
+
+pub fn cycle_selected() -> usize {
+    match REPORT.lock() {
+        Ok(report) => report.selection.as_array().map_or(0, |s| s.len()),
+        _ => 0,
+    }
+}
+
+// Forgets what a failed attempt has recorded before it is retried
+pub fn discard() {
+    if let Ok(mut report) = REPORT.lock() {
+        *report = EMPTY;
+    }
+}
+
+// The options the run was started with
+fn inputs(matches: &clap::ArgMatches) -> serde_json::Value {
+    let uris = |id: &str| -> Vec<String> {
+        matches
+            .get_one::<String>(id)
+            .map(|list| {
+                list.split(' ')
+                    .filter(|u| !u.is_empty())
+                    .map(redact)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    serde_json::json!({
+        "config": matches
+            .get_many::<PathBuf>("config")
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>(),
+        "update_cfg": matches.get_flag("update_cfg"),
+        "api": matches.get_flag("api"),
+        "restart": matches.get_flag("restart"),
+        "number": matches.get_one::<String>("number"),
+        "per_region": matches.get_one::<usize>("per_region"),
+        "country": matches.get_one::<String>("country"),
+        "extra": uris("extra"),
+        "ignore": uris("ignore"),
+    })
+}
+
+// Writes the report of the finished cycle ('-' is the standard output) and
+// starts a new one
+pub fn write(matches: &clap::ArgMatches, exit_code: i32) {
+    let report = match REPORT.lock() {
+        Ok(mut _r) => std::mem::replace(&mut *_r, EMPTY),
+        _ => return,
+    };
+    let json_path = matches.get_one::<String>("report_json");
+    let html_path = matches.get_one::<PathBuf>("report_html");
+    if json_path.is_none() && html_path.is_none() {
+        return;
+    }
+
+    let json = serde_json::json!({
+        "time": crate::history::unix_time(),
+        "version": crate::version::APP_VERSION,
+        "exit_code": exit_code,
+        "inputs": inputs(matches),
+        "probes": report.probes,
+        "selected": report.selection,
+        "actions": {
+            "configs": report.configs,
+            "restarted": report.restarted,
+        },
+        "errors": crate::status::cycle_errors(),
+    });
+
+    let result = match json_path.map(|p| p.as_str()) {
+        Some("-") => {
+            println!("{}", json);
+            Ok(())
+        }
+        Some(_p) => std::fs::write(_p, format!("{}\n", json)),
+        _ => Ok(()),
+    };
+    if let Err(e) = result {
+        log_error!("{}", tr!("Failed to write the report ({}).", e));
+    }
+
+    if let Some(_p) = html_path {
+        if let Err(e) = std::fs::write(_p, html(&json)) {
+            log_error!("{}", tr!("Failed to write the report ({}).", e));
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// 'YYYY-MM-DD HH:MM UTC' (the civil date from the days since the epoch)
+pub fn utc_time(unix: i64) -> String {
+    let days = unix.div_euclid(86400);
+    let secs = unix.rem_euclid(86400);
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60
+    )
+}
+
+const STYLE: &str = "body{font-family:sans-serif;margin:1em 2em;color:#222}\
+table{border-collapse:collapse}td,th{padding:2px 8px;text-align:left;border-bottom:1px solid #ddd}\
+.bar{background:#4a90d9;height:10px}.selected{font-weight:bold}.error{color:#b00}";
+
+// The page for '--report-html': the options, the alive peers sorted by latency
+// with a bar each (the selected ones in bold) and the errors
+fn html(report: &serde_json::Value) -> String {
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title><style>{}</style></head><body>\n<h1>{}</h1>\n<p>{}</p>\n",
+        escape(&tr!("Yggdrasil peers")),
+        STYLE,
+        escape(&tr!("Yggdrasil peers")),
+        escape(&tr!(
+            "{}, peers_updater {}, exit code {}",
+            utc_time(report["time"].as_i64().unwrap_or(0)),
+            report["version"].as_str().unwrap_or(""),
+            report["exit_code"]
+        ))
+    );
+
+    // The filters and the options of the run
+    page.push_str("<table>\n");
+    if let Some(_inputs) = report["inputs"].as_object() {
+        for (name, value) in _inputs {
+            let value = match value {
+                serde_json::Value::Null => continue,
+                serde_json::Value::Array(_a) if _a.is_empty() => continue,
+                serde_json::Value::Array(_a) => _a
+                    .iter()
+                    .map(|v| v.as_str().map(|s| s.to_string()).unwrap_or(v.to_string()))
+                    .collect::<Vec<String>>()
+                    .join(" "),
+                serde_json::Value::String(_s) => _s.clone(),
+                _ => value.to_string(),
+            };
+            page.push_str(&format!(
+                "<tr><th>{}</th><td>{}</td></tr>\n",
+                escape(name),
+                escape(&value)
+            ));
+        }
+    }
+    page.push_str("</table>\n");
+
+    let probes: Vec<&serde_json::Value> =
+        report["probes"].as_array().into_iter().flatten().collect();
+    let alive: Vec<&serde_json::Value> = probes
+        .iter()
+        .copied()
+        .filter(|p| p["alive"] == true)
+        .collect();
+    let selected: Vec<&str> = report["selected"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p["uri"].as_str())
+        .collect();
+    let max_latency = alive
+        .iter()
+        .filter_map(|p| p["latency_ms"].as_u64())
+        .max()
+        .unwrap_or(1)
+        .max(1);
+
+    page.push_str(&format!(
+        "<h2>{}</h2>\n<table>\n<tr><th>{}</th><th>{}</th><th>{}</th><th>{}</th><th colspan=\"2\">{}</th></tr>\n",
+        escape(&tr!("{} of {} peers are alive", alive.len(), probes.len())),
+        escape(&tr!("URI")),
+        escape(&tr!("Proto")),
+        escape(&tr!("Region")),
+        escape(&tr!("Country")),
+        escape(&tr!("Latency"))
+    ));
+    for peer in &alive {
+        let uri = peer["uri"].as_str().unwrap_or("");
+        let latency = peer["latency_ms"].as_u64().unwrap_or(0);
+        page.push_str(&format!(
+            "<tr{}><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{} ms</td><td><div class=\"bar\" style=\"width:{}px\"></div></td></tr>\n",
+            match selected.contains(&uri) {
+                true => " class=\"selected\"",
+                _ => "",
+            },
+            escape(uri),
+            escape(peer["proto"].as_str().unwrap_or("")),
+            escape(peer["region"].as_str().unwrap_or("")),
+            escape(peer["country"].as_str().unwrap_or("")),
+            latency,
+            (latency * 300 / max_latency).max(1)
+        ));
+    }
+    page.push_str("</table>\n");
+
+    for error in report["errors"].as_array().into_iter().flatten() {
+        page.push_str(&format!(
+            "<p class=\"error\">{}</p>\n",
+            escape(error.as_str().unwrap_or(""))
+        ));
+    }
+    page.push_str("</body></html>\n");
+
+    page
+}
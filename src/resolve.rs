@@ -1,4 +1,5 @@
 use dns_lookup::lookup_host;
+use std::net::{IpAddr, SocketAddr, SocketAddrV6};
 
 pub fn resolve(name: &String) -> Option<String> {
     let addr = match name.starts_with('[') {
@@ -14,8 +15,57 @@ pub fn resolve(name: &String) -> Option<String> {
         _ => return None,
     };
 
-    match ips.first() {
-        Some(_ip) => Some(_ip.to_string()),
+    ips.first().map(|_ip| _ip.to_string())
+}
+
+// Returns all the addresses of the host (or the address itself for IP literals,
+// with or without a zone ID)
+pub fn resolve_all(name: &str) -> Option<Vec<IpAddr>> {
+    let addr = crate::uri_normalize::split_zone(name).0;
+
+    if let Ok(_ip) = addr.parse::<IpAddr>() {
+        return Some(vec![_ip]);
+    }
+
+    match lookup_host(addr) {
+        Ok(_ips) if !_ips.is_empty() => Some(_ips),
         _ => None,
     }
 }
+
+// The address to connect to: the zone ID of a link-local address becomes the
+// scope, so that the connection goes through that interface
+pub fn socket_addr(name: &str, ip: IpAddr, port: u16) -> Option<SocketAddr> {
+    match (ip, crate::uri_normalize::split_zone(name).1) {
+        (IpAddr::V6(_ip), Some(_zone)) => Some(SocketAddr::V6(SocketAddrV6::new(
+            _ip,
+            port,
+            0,
+            scope_id(_zone)?,
+        ))),
+        _ => Some(SocketAddr::new(ip, port)),
+    }
+}
+
+#[cfg(unix)]
+extern "C" {
+    fn if_nametoindex(ifname: *const std::ffi::c_char) -> std::ffi::c_uint;
+}
+
+// The zone is an interface name (Unix) or index
+fn scope_id(zone: &str) -> Option<u32> {
+    if let Ok(_index) = zone.parse::<u32>() {
+        return Some(_index);
+    }
+
+    #[cfg(unix)]
+    {
+        let name = std::ffi::CString::new(zone).ok()?;
+        match unsafe { if_nametoindex(name.as_ptr()) } {
+            0 => None,
+            _index => Some(_index),
+        }
+    }
+    #[cfg(not(unix))]
+    None
+}
@@ -0,0 +1,88 @@
+use crate::i18n::tr;
+use crate::peer::Peer;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// The verdict of '--score-cmd' on a peer
+enum Verdict {
+    Score(u128),
+    Reject,
+}
+
+// '--score-cmd': the probed peers are written once to the command as a JSON
+// array on stdin, it answers on stdout with an array of
+// '{"uri": ..., "score": N}' (lower is better, used instead of the latency of
+// an alive peer) or '{"uri": ..., "reject": true}'. The peers it doesn't
+// mention keep their latency. The vector is sorted again.
+pub fn apply(cmd: &str, peers: &mut Vec<Peer>) -> Result<(), String> {
+    let input: Vec<serde_json::Value> = peers
+        .iter()
+        .map(|peer| {
+            serde_json::json!({
+                "uri": peer.uri,
+                "addr": peer.addr,
+                "port": peer.port,
+                "proto": peer.proto,
+                "region": peer.region,
+                "country": peer.country,
+                "country_code": peer.country_code,
+                "alive": peer.is_alive,
+                "latency_ms": match peer.is_alive {
+                    true => Some(peer.latency),
+                    _ => None,
+                },
+                "operator": peer.operator,
+                "contact": peer.contact,
+                "note": peer.note,
+            })
+        })
+        .collect();
+    let verdicts = run(cmd, &serde_json::Value::from(input).to_string())?;
+
+    let verdict = |peer: &Peer| verdicts.get(&crate::uri_normalize::normalize(&peer.uri));
+    peers.retain(|peer| !matches!(verdict(peer), Some(Verdict::Reject)));
+    peers.sort_by_cached_key(|peer| match verdict(peer) {
+        Some(Verdict::Score(_s)) if peer.is_alive => *_s,
+        _ => peer.latency,
+    });
+    Ok(())
+}
+
+fn run(cmd: &str, input: &str) -> Result<HashMap<String, Verdict>, String> {
+    let mut child = Command::new(cmd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    // Written from another thread: the command may answer before reading everything
+    let mut stdin = child.stdin.take();
+    let input = input.to_string();
+    let writer = std::thread::spawn(move || match stdin.as_mut() {
+        Some(_s) => _s.write_all(input.as_bytes()),
+        _ => Ok(()),
+    });
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    let _ret = writer.join();
+    if !output.status.success() {
+        return Err(output.status.to_string());
+    }
+
+    let answer: Vec<serde_json::Value> =
+        serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+    let mut verdicts: HashMap<String, Verdict> = HashMap::new();
+    for entry in answer {
+        let uri = match entry["uri"].as_str() {
+            Some(_u) => crate::uri_normalize::normalize(_u),
+            _ => return Err(tr!("an entry without 'uri' ({})", entry)),
+        };
+        let verdict = match (entry["reject"].as_bool(), entry["score"].as_f64()) {
+            (Some(true), _) => Verdict::Reject,
+            (_, Some(_s)) if _s >= 0.0 => Verdict::Score(_s as u128),
+            _ => continue,
+        };
+        verdicts.insert(uri, verdict);
+    }
+    Ok(verdicts)
+}
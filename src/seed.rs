@@ -0,0 +1,12 @@
+// A position of the peer among the peers of equal latency that depends only on
+// the seed and the URI, so that the same seed and the same probe results give
+// the same selection on any machine (FNV-1a: stable across platforms and builds)
+pub fn tiebreak(seed: &str, uri: &str) -> u64 {
+    let uri = crate::uri_normalize::normalize(uri);
+    seed.bytes()
+        .chain(std::iter::once(0))
+        .chain(uri.bytes())
+        .fold(0xcbf29ce484222325, |hash, b| {
+            (hash ^ b as u64).wrapping_mul(0x100000001b3)
+        })
+}
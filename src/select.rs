@@ -0,0 +1,190 @@
+use crate::peer::Peer;
+use std::collections::{HashMap, HashSet};
+
+// '-n': a number of peers or a share of the alive ones ('10%')
+#[derive(Clone, Copy)]
+pub enum PeerCount {
+    Fixed(usize),
+    Percent(u8),
+}
+
+impl PeerCount {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().strip_suffix('%') {
+            Some(_p) => match _p.trim().parse::<u8>() {
+                Ok(_n) if _n <= 100 => Ok(PeerCount::Percent(_n)),
+                _ => Err(value.to_string()),
+            },
+            _ => value
+                .trim()
+                .parse::<usize>()
+                .map(PeerCount::Fixed)
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    // The number of peers to select out of `alive` (a share is rounded up)
+    pub fn resolve(self, alive: usize) -> usize {
+        match self {
+            PeerCount::Fixed(_n) => _n,
+            PeerCount::Percent(_p) => (alive * _p as usize).div_ceil(100),
+        }
+    }
+
+    // Without the probe results a share is not known yet
+    pub fn fixed(self) -> Option<usize> {
+        match self {
+            PeerCount::Fixed(_n) => Some(_n),
+            _ => None,
+        }
+    }
+}
+
+// Picks the best peers (the vector is expected to be sorted by latency)
+pub fn select_peers<'a>(
+    peers: &'a [Peer],
+    n_peers: usize,
+    ignored_peers: Option<&String>,
+    extra_peers: &[String],
+) -> Vec<&'a Peer> {
+    let mut selected: Vec<&Peer> = Vec::new();
+
+    if n_peers == 0 {
+        return selected;
+    }
+
+    for peer in peers {
+        if let Some(ignored_peers_p) = ignored_peers {
+            if crate::uri_normalize::list_contains(ignored_peers_p, &peer.uri) {
+                continue;
+            }
+        }
+        if is_extra(extra_peers, &peer.uri) {
+            continue;
+        }
+        selected.push(peer);
+        if selected.len() == n_peers {
+            break;
+        }
+    }
+
+    selected
+}
+
+// Picks the best alive peers of every region (the vector is expected to be
+// sorted by latency, so is the result)
+pub fn select_per_region<'a>(
+    peers: &'a [Peer],
+    per_region: usize,
+    ignored_peers: Option<&String>,
+    extra_peers: &[String],
+) -> Vec<&'a Peer> {
+    let mut per_region_count: HashMap<&str, usize> = HashMap::new();
+
+    peers
+        .iter()
+        .filter(|peer| peer.is_alive)
+        .filter(|peer| match ignored_peers {
+            Some(_ip) => !crate::uri_normalize::list_contains(_ip, &peer.uri),
+            _ => true,
+        })
+        .filter(|peer| !is_extra(extra_peers, &peer.uri))
+        .filter(|peer| {
+            let count = per_region_count.entry(peer.region.as_str()).or_insert(0);
+            *count += 1;
+            *count <= per_region
+        })
+        .collect()
+}
+
+// '--keep-within': a newly selected peer gives its place back to an alive
+// peer from the current configuration that is at most `percent` % slower.
+// The worst newcomers are replaced first; with '--per-region' only by a peer
+// of the same region. The result is sorted by latency.
+pub fn keep_current<'a>(
+    selected: Vec<&'a Peer>,
+    peers: &'a [Peer],
+    current: &[String],
+    percent: u16,
+    same_region: bool,
+    ignored_peers: Option<&String>,
+    extra_peers: &[String],
+) -> Vec<&'a Peer> {
+    let current: HashSet<String> = current.iter().map(|uri| identity(uri)).collect();
+    let is_current = |peer: &Peer| current.contains(&identity(&peer.uri));
+
+    let mut candidates: Vec<&Peer> = peers
+        .iter()
+        .filter(|peer| peer.is_alive && is_current(peer))
+        .filter(|peer| match ignored_peers {
+            Some(_ip) => !crate::uri_normalize::list_contains(_ip, &peer.uri),
+            _ => true,
+        })
+        .filter(|peer| !is_extra(extra_peers, &peer.uri))
+        .filter(|peer| !selected.iter().any(|s| s.uri == peer.uri))
+        .collect();
+
+    let mut kept = selected;
+    let mut newcomers: Vec<usize> = (0..kept.len()).filter(|i| !is_current(kept[*i])).collect();
+    newcomers.sort_by_key(|i| std::cmp::Reverse(kept[*i].latency));
+    for i in newcomers {
+        let limit = kept[i].latency * (100 + percent as u128) / 100;
+        let replacement = candidates
+            .iter()
+            .position(|c| c.latency <= limit && (!same_region || c.region == kept[i].region));
+        if let Some(_r) = replacement {
+            kept[i] = candidates.remove(_r);
+        }
+    }
+
+    kept.sort_by_key(|peer| peer.latency);
+    kept
+}
+
+// The same peer with or without the added parameters (e.g. '?sni=')
+fn identity(uri: &str) -> String {
+    let uri = crate::uri_normalize::normalize(uri);
+    match uri.split_once('?') {
+        Some((_u, _)) => _u.to_string(),
+        _ => uri,
+    }
+}
+
+// The URIs of the peers that should always be added. Ignoring a peer takes
+// precedence over adding it, and a peer listed twice is added once.
+pub fn extra_peers(always_in_p: Option<&String>, ignored_peers: Option<&String>) -> Vec<String> {
+    let mut seen_uris: HashSet<String> = HashSet::new();
+
+    match always_in_p {
+        Some(always_in) => always_in
+            .split(' ')
+            .filter(|ai| !ai.is_empty())
+            .filter(|ai| match ignored_peers {
+                Some(_ip) => !crate::uri_normalize::list_contains(_ip, ai),
+                _ => true,
+            })
+            .filter(|ai| seen_uris.insert(crate::uri_normalize::normalize(ai)))
+            .map(|ai| ai.to_string())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// The selection must not repeat the extra peers: these are always added anyway
+fn is_extra(extra_peers: &[String], uri: &str) -> bool {
+    let uri = crate::uri_normalize::normalize(uri);
+    extra_peers
+        .iter()
+        .any(|ep| crate::uri_normalize::normalize(ep) == uri)
+}
+
+// Leaves a single URI per host: the best working transport (or port) wins.
+// The vector is expected to be sorted by latency.
+pub fn best_transport_per_host(peers: Vec<Peer>) -> Vec<Peer> {
+    let mut seen_hosts: HashSet<String> = HashSet::new();
+
+    peers
+        .into_iter()
+        .filter(|peer| seen_hosts.insert(peer.addr.to_lowercase()))
+        .collect()
+}
@@ -0,0 +1,241 @@
+use crate::escalate::Escalation;
+use crate::i18n::tr;
+use crate::logging::log_error;
+use std::sync::OnceLock;
+
+static NAME: OnceLock<String> = OnceLock::new();
+
+// '--service-name': the systemd unit, the Windows service, the OpenWrt init
+// script or the termux-services service
+pub fn set_name(name: &str) {
+    let _ = NAME.set(name.to_string());
+}
+
+pub fn name() -> &'static str {
+    NAME.get().map_or("yggdrasil", |n| n.as_str())
+}
+
+// 'yggdrasil-go' is 'yggdrasil-go.service', 'yggdrasil@main.service' is taken as it is
+#[cfg(target_os = "linux")]
+fn unit() -> String {
+    match name().contains('.') {
+        true => name().to_string(),
+        _ => format!("{}.service", name()),
+    }
+}
+
+// Restarting the Yggdrasil service (systemd, windows or termux-services)
+// Returns whether the service was restarted
+pub fn restart() -> bool {
+    #[cfg(not(target_os = "windows"))]
+    if let Some(_prefix) = crate::termux::prefix() {
+        return crate::termux::restart(_prefix);
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        match dbus::restart_unit(&unit()) {
+            Ok(_) => return true,
+            Err(dbus::RestartError::Failed(e)) => {
+                log_error!(
+                    "{}",
+                    tr!("Failed to restart the Yggdrasil service ({}).", e)
+                );
+                return false;
+            }
+            // No system bus (or no systemd on it), trying systemctl
+            Err(dbus::RestartError::Unavailable) => {}
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    return systemctl_restart(std::process::Command::new("systemctl"));
+
+    #[cfg(target_os = "windows")]
+    return match winsvc::restart_service(name()) {
+        Ok(_) => true,
+        Err(e) => {
+            log_error!(
+                "{}",
+                tr!("Failed to restart the Yggdrasil service ({}).", e)
+            );
+            false
+        }
+    };
+}
+
+// Restarting through sudo/doas/pkexec when running unprivileged
+pub fn restart_with(escalation: &Escalation) -> bool {
+    #[cfg(not(target_os = "windows"))]
+    if let Some(_prefix) = crate::termux::prefix() {
+        return crate::termux::restart(_prefix);
+    }
+    systemctl_restart(escalation.command("systemctl"))
+}
+
+fn systemctl_restart(mut systemctl: std::process::Command) -> bool {
+    match systemctl.arg("restart").arg(name()).status() {
+        Ok(_s) if _s.success() => true,
+        Ok(_s) => {
+            log_error!(
+                "{}",
+                tr!("Failed to restart the Yggdrasil service ({}).", _s)
+            );
+            false
+        }
+        Err(e) => {
+            log_error!(
+                "{}",
+                tr!("Failed to restart the Yggdrasil service ({}).", e)
+            );
+            false
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod dbus {
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use zbus::blocking::{Connection, MessageIterator};
+    use zbus::zvariant::OwnedObjectPath;
+    use zbus::MatchRule;
+
+    pub enum RestartError {
+        Unavailable,
+        Failed(String),
+    }
+
+    // Asks systemd to restart the unit and waits for the result of the job
+    pub fn restart_unit(unit: &str) -> Result<(), RestartError> {
+        let conn = Connection::system().map_err(|_| RestartError::Unavailable)?;
+
+        let rule = MatchRule::builder()
+            .msg_type(zbus::message::Type::Signal)
+            .sender("org.freedesktop.systemd1")
+            .and_then(|b| b.interface("org.freedesktop.systemd1.Manager"))
+            .and_then(|b| b.member("JobRemoved"))
+            .map_err(|_| RestartError::Unavailable)?
+            .build();
+        // Subscribing before starting the job so the result can't be missed
+        let signals = MessageIterator::for_match_rule(rule, &conn, None)
+            .map_err(|_| RestartError::Unavailable)?;
+        // systemd sends JobRemoved only to the subscribed clients
+        manager_call(&conn, "Subscribe").map_err(|_| RestartError::Unavailable)?;
+
+        let result = restart_and_wait(&conn, signals, unit);
+        let _ret = manager_call(&conn, "Unsubscribe");
+        result
+    }
+
+    fn manager_call(conn: &Connection, method: &str) -> zbus::Result<zbus::Message> {
+        conn.call_method(
+            Some("org.freedesktop.systemd1"),
+            "/org/freedesktop/systemd1",
+            Some("org.freedesktop.systemd1.Manager"),
+            method,
+            &(),
+        )
+    }
+
+    fn restart_and_wait(
+        conn: &Connection,
+        signals: MessageIterator,
+        unit: &str,
+    ) -> Result<(), RestartError> {
+        let reply = conn
+            .call_method(
+                Some("org.freedesktop.systemd1"),
+                "/org/freedesktop/systemd1",
+                Some("org.freedesktop.systemd1.Manager"),
+                "RestartUnit",
+                &(unit, "replace"),
+            )
+            .map_err(|e| match e {
+                zbus::Error::MethodError(..) => RestartError::Failed(e.to_string()),
+                _ => RestartError::Unavailable,
+            })?;
+        let job: OwnedObjectPath = reply
+            .body()
+            .deserialize()
+            .map_err(|e| RestartError::Failed(e.to_string()))?;
+
+        let (tx, rx) = mpsc::channel::<String>();
+        std::thread::spawn(move || {
+            for msg in signals.flatten() {
+                let body = msg.body();
+                if let Ok((_id, _job, _unit, _result)) =
+                    body.deserialize::<(u32, OwnedObjectPath, String, String)>()
+                {
+                    if _job == job {
+                        let _ = tx.send(_result);
+                        return;
+                    }
+                }
+            }
+        });
+
+        match rx.recv_timeout(Duration::from_secs(90)) {
+            Ok(_r) if _r == "done" => Ok(()),
+            Ok(_r) => Err(RestartError::Failed(format!("job result: {}", _r))),
+            Err(_) => Err(RestartError::Failed(
+                "no job result from systemd".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod winsvc {
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use windows_service::service::{Service, ServiceAccess, ServiceState};
+    use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+
+    const STATE_TIMEOUT: Duration = Duration::from_secs(60);
+
+    // Stops the service (if it is running), starts it again and waits until
+    // it is actually running
+    pub fn restart_service(name: &str) -> Result<(), String> {
+        let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+            .map_err(|e| e.to_string())?;
+        let service = manager
+            .open_service(
+                name,
+                ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::START,
+            )
+            .map_err(|e| e.to_string())?;
+
+        let status = service.query_status().map_err(|e| e.to_string())?;
+        if status.current_state != ServiceState::Stopped {
+            if status.current_state != ServiceState::StopPending {
+                service.stop().map_err(|e| e.to_string())?;
+            }
+            wait_for_state(&service, ServiceState::Stopped)?;
+        }
+
+        service.start::<&str>(&[]).map_err(|e| e.to_string())?;
+        wait_for_state(&service, ServiceState::Running)
+    }
+
+    fn wait_for_state(service: &Service, state: ServiceState) -> Result<(), String> {
+        let started = Instant::now();
+        loop {
+            let status = service.query_status().map_err(|e| e.to_string())?;
+            if status.current_state == state {
+                return Ok(());
+            }
+            // The service failed to start
+            if state == ServiceState::Running && status.current_state == ServiceState::Stopped {
+                return Err(format!("the service stopped ({:?})", status.exit_code));
+            }
+            if started.elapsed() > STATE_TIMEOUT {
+                return Err(format!(
+                    "timed out waiting for {:?}, the service is {:?}",
+                    state, status.current_state
+                ));
+            }
+            thread::sleep(Duration::from_millis(500));
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use crate::i18n::tr;
+use nu_json::{Map, Value};
+use std::path::Path;
+use std::process::Command;
+
+// The settings file (HJSON) holds the command line options by their long
+// names ("number": 3, "prefer-reachable": true, ...) and the named profiles
+// overriding them:
+// { country: "germany", profiles: { mobile: { match: { ssid: "Phone" }, number: 2 } } }
+// and the notifiers ('notify', see notify.rs)
+pub struct Settings {
+    options: Map<String, Value>,
+    profiles: Map<String, Value>,
+    notify: Option<Value>,
+}
+
+pub fn load(path: &Path) -> Result<Settings, String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|e| tr!("Can't read the settings file ({}).", e))?;
+    let mut options: Map<String, Value> =
+        nu_json::from_str(&text).map_err(|e| tr!("Can't parse the settings file ({}).", e))?;
+
+    let profiles = match options.remove("profiles") {
+        Some(Value::Object(_p)) => _p,
+        Some(_) => return Err(tr!("'profiles' in the settings file must be an object.")),
+        _ => Map::new(),
+    };
+    let notify = options.remove("notify");
+
+    Ok(Settings {
+        options,
+        profiles,
+        notify,
+    })
+}
+
+impl Settings {
+    // The profile given by name or, without a name, the first one whose 'match'
+    // fits the current network ('default' if none does)
+    pub fn profile(&self, name: Option<&str>) -> Result<Option<String>, String> {
+        if let Some(_n) = name {
+            return match self.profiles.contains_key(_n) {
+                true => Ok(Some(_n.to_string())),
+                _ => Err(tr!("There is no profile '{}' in the settings file.", _n)),
+            };
+        }
+        if self.profiles.is_empty() {
+            return Ok(None);
+        }
+
+        let ssid = current_ssid();
+        let gateway = default_gateway();
+        let matched = self.profiles.iter().find(|(_, p)| {
+            let conditions = match p.as_object().and_then(|o| o.get("match")) {
+                Some(Value::Object(_m)) => _m,
+                _ => return false,
+            };
+            let fits = |key: &str, current: &Option<String>| match (conditions.get(key), current) {
+                (Some(_v), Some(_c)) => strings(_v).iter().any(|s| s == _c),
+                _ => false,
+            };
+            fits("ssid", &ssid) || fits("gateway", &gateway)
+        });
+
+        Ok(match matched {
+            Some((_k, _)) => Some(_k.clone()),
+            _ if self.profiles.contains_key("default") => Some("default".to_string()),
+            _ => None,
+        })
+    }
+
+    pub fn notifiers(&self) -> Result<Vec<crate::notify::Notifier>, String> {
+        match &self.notify {
+            Some(_n) => crate::notify::parse(_n),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    // The command line arguments for the options of the file and of the profile
+    pub fn args(&self, profile: Option<&str>) -> Result<Vec<String>, String> {
+        let mut args = to_args(&self.options)?;
+        if let Some(_name) = profile {
+            match self.profiles.get(_name) {
+                Some(Value::Object(_o)) => args.extend(to_args(_o)?),
+                Some(_) => return Err(tr!("The profile '{}' must be an object.", _name)),
+                _ => {}
+            }
+        }
+        Ok(args)
+    }
+}
+
+fn to_args(options: &Map<String, Value>) -> Result<Vec<String>, String> {
+    let mut args: Vec<String> = Vec::new();
+    for (key, value) in options {
+        if key == "match" {
+            continue;
+        }
+        let flag = format!("--{}", key);
+        match value {
+            Value::Null | Value::Bool(false) => {}
+            Value::Bool(true) => args.push(flag),
+            Value::Array(_a) => {
+                for v in _a {
+                    args.push(flag.clone());
+                    args.push(scalar(key, v)?);
+                }
+            }
+            _ => {
+                args.push(flag);
+                args.push(scalar(key, value)?);
+            }
+        }
+    }
+    Ok(args)
+}
+
+fn scalar(key: &str, value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(_s) => Ok(_s.clone()),
+        Value::I64(_n) => Ok(_n.to_string()),
+        Value::U64(_n) => Ok(_n.to_string()),
+        Value::F64(_n) => Ok(_n.to_string()),
+        _ => Err(tr!("Unsupported value of '{}' in the settings file.", key)),
+    }
+}
+
+// A 'match' condition is a string or an array of strings
+fn strings(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(_s) => vec![_s.clone()],
+        Value::Array(_a) => _a
+            .iter()
+            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    match output.status.success() {
+        true => Some(String::from_utf8_lossy(&output.stdout).to_string()),
+        _ => None,
+    }
+}
+
+// The SSID of the connected Wi-Fi network
+fn current_ssid() -> Option<String> {
+    #[cfg(target_os = "windows")]
+    let ssid = command_output("netsh", &["wlan", "show", "interfaces"]).and_then(|out| {
+        out.lines()
+            .filter_map(|l| l.split_once(':'))
+            .find(|(k, _)| k.trim() == "SSID")
+            .map(|(_, v)| v.trim().to_string())
+    });
+
+    #[cfg(target_os = "macos")]
+    let ssid = command_output(
+        "/System/Library/PrivateFrameworks/Apple80211.framework/Resources/airport",
+        &["-I"],
+    )
+    .and_then(|out| {
+        out.lines()
+            .filter_map(|l| l.split_once(':'))
+            .find(|(k, _)| k.trim() == "SSID")
+            .map(|(_, v)| v.trim().to_string())
+    });
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let ssid = command_output("iwgetid", &["-r"])
+        .map(|out| out.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .or_else(|| {
+            command_output("nmcli", &["-t", "-f", "active,ssid", "dev", "wifi"]).and_then(|out| {
+                out.lines()
+                    .find_map(|l| l.strip_prefix("yes:").map(|s| s.to_string()))
+            })
+        });
+
+    ssid.filter(|s| !s.is_empty())
+}
+
+// The address of the default gateway
+fn default_gateway() -> Option<String> {
+    #[cfg(target_os = "linux")]
+    return std::fs::read_to_string("/proc/net/route")
+        .ok()?
+        .lines()
+        .skip(1)
+        .map(|l| l.split_whitespace().collect::<Vec<&str>>())
+        .find(|f| f.len() > 2 && f[1] == "00000000" && f[2] != "00000000")
+        .and_then(|f| u32::from_str_radix(f[2], 16).ok())
+        .map(|gw| std::net::Ipv4Addr::from(gw.to_le_bytes()).to_string());
+
+    #[cfg(target_os = "windows")]
+    return command_output(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-Command",
+            "(Get-NetRoute -DestinationPrefix 0.0.0.0/0 | Sort-Object RouteMetric | Select-Object -First 1).NextHop",
+        ],
+    )
+    .map(|out| out.trim().to_string())
+    .filter(|s| !s.is_empty());
+
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    return command_output("route", &["-n", "get", "default"]).and_then(|out| {
+        out.lines()
+            .filter_map(|l| l.split_once(':'))
+            .find(|(k, _)| k.trim() == "gateway")
+            .map(|(_, v)| v.trim().to_string())
+    });
+}
@@ -0,0 +1,60 @@
+use crate::peer::Peer;
+
+// The SNI names to put into the TLS peer URIs: a plain name applies to all
+// the peers, 'host=name' to the peers of that host only
+pub struct SniRules {
+    default: Option<String>,
+    per_host: Vec<(String, String)>,
+}
+
+impl SniRules {
+    pub fn parse(spec: &str) -> Self {
+        let mut rules = SniRules {
+            default: None,
+            per_host: Vec::new(),
+        };
+        for rule in spec.split(' ').filter(|r| !r.is_empty()) {
+            match rule.split_once('=') {
+                Some((_h, _n)) => rules.per_host.push((_h.to_lowercase(), _n.to_string())),
+                _ => rules.default = Some(rule.to_string()),
+            }
+        }
+        rules
+    }
+
+    fn name_for(&self, host: &str) -> Option<&String> {
+        let host = host.to_lowercase();
+        match self.per_host.iter().find(|(h, _)| *h == host) {
+            Some((_, _n)) => Some(_n),
+            _ => self.default.as_ref(),
+        }
+    }
+
+    pub fn apply(&self, peer: &Peer) -> Peer {
+        let mut peer = peer.clone();
+        if peer.proto == "tls" {
+            if let Some(_n) = self.name_for(&peer.addr) {
+                peer.uri = with_query_param(&peer.uri, "sni", _n);
+            }
+        }
+        peer
+    }
+}
+
+// Sets the query parameter, replacing its previous value and keeping the others
+pub fn with_query_param(uri: &str, key: &str, value: &str) -> String {
+    let (base, query) = match uri.split_once('?') {
+        Some((_b, _q)) => (_b, _q),
+        _ => (uri, ""),
+    };
+
+    let params: Vec<String> = query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .filter(|p| p.split('=').next() != Some(key))
+        .map(|p| p.to_string())
+        .chain(std::iter::once(format!("{}={}", key, value)))
+        .collect();
+
+    format!("{}?{}", base, params.join("&"))
+}
@@ -0,0 +1,34 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// What is remembered between the runs
+#[derive(Default)]
+pub struct State {
+    // The unix time of the last successful update
+    pub last_update: Option<i64>,
+}
+
+// A missing state file is the same as an empty one
+pub fn load(path: &Path) -> io::Result<State> {
+    let text = match fs::read_to_string(path) {
+        Ok(_t) => _t,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(State::default()),
+        Err(e) => return Err(e),
+    };
+    let value: serde_json::Value = serde_json::from_str(&text).map_err(io::Error::other)?;
+
+    Ok(State {
+        last_update: value.get("last_update").and_then(|v| v.as_i64()),
+    })
+}
+
+pub fn save(path: &Path, state: &State) -> io::Result<()> {
+    if let Some(_dir) = path.parent() {
+        fs::create_dir_all(_dir)?;
+    }
+    let value = serde_json::json!({
+        "last_update": state.last_update,
+    });
+    fs::write(path, value.to_string())
+}
@@ -0,0 +1,187 @@
+use crate::i18n::tr;
+use crate::logging::log_error;
+use crate::peer::Peer;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Mutex;
+use std::time::Duration;
+
+// The outcome of the last update cycle, exposed with '--status-listen'
+struct Status {
+    last_run: Option<i64>,
+    exit_code: Option<i32>,
+    selected: Vec<(String, u128)>,
+    peers_json: serde_json::Value,
+    // The alive peers of the last cycle, the best first ('--control-socket')
+    candidates: serde_json::Value,
+    errors: Vec<String>,
+    current_errors: Vec<String>,
+}
+
+static STATUS: Mutex<Status> = Mutex::new(Status {
+    last_run: None,
+    exit_code: None,
+    selected: Vec::new(),
+    peers_json: serde_json::Value::Null,
+    candidates: serde_json::Value::Null,
+    errors: Vec::new(),
+    current_errors: Vec::new(),
+});
+
+// Prints an error of the running cycle and keeps it for the status report
+pub fn error(msg: String) {
+    let msg = crate::redact::redact(&msg);
+    log_error!("{}", msg);
+    if let Ok(mut status) = STATUS.lock() {
+        status.current_errors.push(msg);
+    }
+}
+
+pub fn record_selection(selected: &[&Peer], extra_peers: &[String]) {
+    if let Ok(mut status) = STATUS.lock() {
+        status.selected = selected
+            .iter()
+            .map(|peer| (crate::redact::redact(&peer.uri), peer.latency))
+            .collect();
+        status.peers_json = crate::output::peers_json(selected, extra_peers, false);
+    }
+}
+
+pub fn record_candidates(peers: &[Peer], ignored_peers: Option<&String>) {
+    let alive: Vec<&Peer> = peers
+        .iter()
+        .filter(|peer| peer.is_alive)
+        .filter(|peer| match ignored_peers {
+            Some(_i) => !crate::uri_normalize::list_contains(_i, &peer.uri),
+            _ => true,
+        })
+        .collect();
+    if let Ok(mut status) = STATUS.lock() {
+        status.candidates = crate::output::peers_json(&alive, &[], true);
+    }
+}
+
+pub fn candidates_json() -> serde_json::Value {
+    match STATUS.lock() {
+        Ok(status) => status.candidates.clone(),
+        _ => serde_json::Value::Null,
+    }
+}
+
+// The errors of the running cycle so far
+pub fn cycle_errors() -> Vec<String> {
+    match STATUS.lock() {
+        Ok(status) => status.current_errors.clone(),
+        _ => Vec::new(),
+    }
+}
+
+// A failed run is retried ('--wait-for-network'): only the errors of the last
+// attempt are kept
+pub fn discard_cycle_errors() {
+    if let Ok(mut status) = STATUS.lock() {
+        status.current_errors.clear();
+    }
+}
+
+pub fn finish_cycle(exit_code: i32) {
+    if let Ok(mut status) = STATUS.lock() {
+        status.last_run = Some(crate::history::unix_time());
+        status.exit_code = Some(exit_code);
+        status.errors = std::mem::take(&mut status.current_errors);
+    }
+}
+
+pub fn to_json() -> serde_json::Value {
+    match STATUS.lock() {
+        Ok(status) => serde_json::json!({
+            "last_run": status.last_run,
+            "exit_code": status.exit_code,
+            "selected": status
+                .selected
+                .iter()
+                .map(|(uri, latency)| serde_json::json!({ "uri": uri, "latency_ms": latency }))
+                .collect::<Vec<_>>(),
+            "errors": status.errors,
+        }),
+        _ => serde_json::json!({}),
+    }
+}
+
+// What a listener answers with: the content type and the body, if the path
+// is known
+type Handler = fn(&str) -> Option<(&'static str, String)>;
+
+pub fn status_handler(_path: &str) -> Option<(&'static str, String)> {
+    Some(("application/json", to_json().to_string()))
+}
+
+// The best peers for other machines: '/peers.txt' (a URI per line) and '/peers.json'
+pub fn peers_handler(path: &str) -> Option<(&'static str, String)> {
+    // Nothing to serve until the first selection
+    let peers_json = match STATUS.lock() {
+        Ok(status) if !status.peers_json.is_null() => status.peers_json.clone(),
+        _ => return None,
+    };
+
+    match path {
+        "/peers.json" => Some(("application/json", peers_json.to_string())),
+        "/peers.txt" => Some((
+            "text/plain; charset=utf-8",
+            peers_json["peers"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|p| p["uri"].as_str())
+                .map(|uri| format!("{}\n", uri))
+                .collect(),
+        )),
+        _ => None,
+    }
+}
+
+// Answers the HTTP requests on the address
+pub fn serve(addr: &str, handler: Handler) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(e) = respond(stream, handler) {
+                log_error!("{}", tr!("Failed to answer an HTTP request ({}).", e));
+            }
+        }
+    });
+    Ok(())
+}
+
+fn respond(mut stream: TcpStream, handler: Handler) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+    // Only the path of the request line matters, the headers are skipped
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        if line == "\r\n" || line == "\n" {
+            break;
+        }
+        line.clear();
+    }
+
+    let path = request_line.split(' ').nth(1).unwrap_or("/");
+    let path = path.split('?').next().unwrap_or(path);
+    let (status, content_type, body) = match handler(path) {
+        Some((_ct, _b)) => ("200 OK", _ct, _b),
+        _ => ("404 Not Found", "text/plain", "Not Found\n".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
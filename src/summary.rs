@@ -0,0 +1,77 @@
+use crate::i18n::tr;
+use crate::logging::output;
+use crate::redact::redact;
+
+// What the run did to a configuration file
+pub struct ConfigChange {
+    pub path: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub pinned: usize,
+    pub written: bool,
+}
+
+impl ConfigChange {
+    pub fn new(path: String, old_cfg: &str, new_cfg: &str, written: bool) -> Self {
+        let old_peers = crate::parse_config::get_peers(old_cfg).unwrap_or_default();
+        let new_peers = crate::parse_config::get_peers(new_cfg).unwrap_or_default();
+        let missing_from = |list: &[String], uri: &String| {
+            let uri = crate::uri_normalize::normalize(uri);
+            !list
+                .iter()
+                .any(|u| crate::uri_normalize::normalize(u) == uri)
+        };
+
+        ConfigChange {
+            path,
+            added: new_peers
+                .iter()
+                .filter(|uri| missing_from(&old_peers, uri))
+                .cloned()
+                .collect(),
+            removed: old_peers
+                .iter()
+                .filter(|uri| missing_from(&new_peers, uri))
+                .cloned()
+                .collect(),
+            pinned: crate::cfg_file_modify::pinned_peers(old_cfg).uris.len(),
+            written,
+        }
+    }
+}
+
+// The end-of-run summary, e.g. "/etc/yggdrasil.conf: added 4 peers, removed 3,
+// kept 2 pinned, config written" with the changed URIs and the restart result
+pub fn print(changes: &[ConfigChange], restarted: Option<bool>) {
+    let mut lines: Vec<String> = Vec::new();
+    for change in changes {
+        lines.push(tr!(
+            "{}: added {} peers, removed {}, kept {} pinned, {}",
+            change.path,
+            change.added.len(),
+            change.removed.len(),
+            change.pinned,
+            match change.written {
+                true => tr!("config written"),
+                _ => tr!("config not written"),
+            }
+        ));
+        for uri in &change.added {
+            lines.push(format!("  + {}", redact(uri)));
+        }
+        for uri in &change.removed {
+            lines.push(format!("  - {}", redact(uri)));
+        }
+    }
+
+    match restarted {
+        Some(true) => lines.push(tr!("Service restarted")),
+        Some(false) => lines.push(tr!("Service restart failed")),
+        _ => {}
+    }
+
+    for line in &lines {
+        output(line);
+    }
+    crate::report::record_summary(lines);
+}
@@ -0,0 +1,185 @@
+use crate::i18n::tr;
+use crate::logging::{log_info, log_warn};
+use crate::peer::Peer;
+use crate::using_api::{ApiBackend, ApiClient};
+use nu_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+// '--supervise': between the daemon cycles the sessions of the node are
+// checked, and a selected peer that stays down or slow for longer than the
+// grace time is swapped for the next best peer of the last ranking
+struct Settings {
+    backend: ApiBackend,
+    max_latency: u128,
+    grace: Duration,
+}
+
+// A node updated through the admin API by the last cycle
+pub struct Node {
+    conf_obj: Map<String, Value>,
+    // The selected peers that are watched
+    peers: Vec<String>,
+    // The normalized URIs the node has or had since the last cycle, never
+    // taken as a replacement
+    used: Vec<String>,
+    // Since when a watched peer is down or slow
+    bad_since: HashMap<String, Instant>,
+}
+
+impl Node {
+    pub fn new(conf_obj: Map<String, Value>, selected: &[&Peer], extra_peers: &[String]) -> Self {
+        let peers: Vec<String> = selected.iter().map(|peer| peer.uri.clone()).collect();
+        let used = peers
+            .iter()
+            .chain(extra_peers.iter())
+            .map(|uri| crate::uri_normalize::normalize(uri))
+            .collect();
+        Node {
+            conf_obj,
+            peers,
+            used,
+            bad_since: HashMap::new(),
+        }
+    }
+}
+
+struct Supervisor {
+    nodes: Vec<Node>,
+    // The alive peers of the last cycle, the best first
+    ranking: Vec<String>,
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+static SUPERVISOR: Mutex<Supervisor> = Mutex::new(Supervisor {
+    nodes: Vec::new(),
+    ranking: Vec::new(),
+});
+
+// The sessions are checked this often at most
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+pub fn set(backend: ApiBackend, max_latency: u128, grace: Duration) {
+    let _ret = SETTINGS.set(Settings {
+        backend,
+        max_latency,
+        grace,
+    });
+}
+
+pub fn enabled() -> bool {
+    SETTINGS.get().is_some()
+}
+
+// How often the daemon checks the sessions, None without '--supervise'
+pub fn check_interval() -> Option<Duration> {
+    SETTINGS
+        .get()
+        .map(|s| s.grace.clamp(Duration::from_secs(1), CHECK_INTERVAL))
+}
+
+// Called by every cycle that applied its selection through the API: the
+// watching starts over with the new peers and ranking. The replacements get
+// their SNI names ('--sni') like the selected peers.
+pub fn record(
+    nodes: Vec<Node>,
+    peers: &[Peer],
+    ignored_peers: Option<&String>,
+    sni: Option<&String>,
+) {
+    let rules = sni.map(|s| crate::sni::SniRules::parse(s));
+    let ranking = peers
+        .iter()
+        .filter(|peer| peer.is_alive)
+        .filter(|peer| match ignored_peers {
+            Some(_i) => !crate::uri_normalize::list_contains(_i, &peer.uri),
+            _ => true,
+        })
+        .map(|peer| match &rules {
+            Some(_r) => _r.apply(peer).uri,
+            _ => peer.uri.clone(),
+        })
+        .collect();
+    if let Ok(mut supervisor) = SUPERVISOR.lock() {
+        *supervisor = Supervisor { nodes, ranking };
+    }
+}
+
+// Checks the sessions of the nodes and replaces the peers that have been
+// down or slower than '--supervise-latency' for the grace time
+pub fn check() {
+    let settings = match SETTINGS.get() {
+        Some(_s) => _s,
+        _ => return,
+    };
+    let mut supervisor = match SUPERVISOR.lock() {
+        Ok(_s) => _s,
+        _ => return,
+    };
+    let Supervisor { nodes, ranking } = &mut *supervisor;
+
+    for node in nodes.iter_mut() {
+        let mut client = ApiClient::new(&mut node.conf_obj, &settings.backend);
+        // The node can't be asked: nothing is known about the peers
+        let sessions = match crate::using_api::sessions(&mut client) {
+            Some(_s) => _s,
+            _ => continue,
+        };
+        let now = Instant::now();
+
+        for i in 0..node.peers.len() {
+            let uri = node.peers[i].clone();
+            let normalized = crate::uri_normalize::normalize(&uri);
+            // Older versions don't list the sessions that are down
+            let healthy = sessions
+                .iter()
+                .find(|(s, _)| *s == normalized)
+                .and_then(|(_, latency)| *latency)
+                .is_some_and(|l| l <= settings.max_latency);
+            if healthy {
+                node.bad_since.remove(&uri);
+                continue;
+            }
+
+            let since = *node.bad_since.entry(uri.clone()).or_insert(now);
+            if now.duration_since(since) < settings.grace {
+                continue;
+            }
+
+            let replacement = ranking
+                .iter()
+                .find(|r| !node.used.contains(&crate::uri_normalize::normalize(r)));
+            let _r = match replacement {
+                Some(_r) => _r.clone(),
+                _ => {
+                    log_warn!(
+                        "{}",
+                        tr!(
+                            "'--supervise': {} has been down or slow for {} s, but no other alive peer is left to replace it with.",
+                            crate::redact::redact(&uri),
+                            now.duration_since(since).as_secs()
+                        )
+                    );
+                    // Not warning again on every check
+                    node.bad_since.insert(uri, now);
+                    continue;
+                }
+            };
+
+            log_info!(
+                "{}",
+                tr!(
+                    "'--supervise': {} has been down or slow for {} s, replaced with {}.",
+                    crate::redact::redact(&uri),
+                    now.duration_since(since).as_secs(),
+                    crate::redact::redact(&_r)
+                )
+            );
+            crate::using_api::replace_peer(&uri, &_r, &mut client);
+            node.bad_since.remove(&uri);
+            node.used.push(crate::uri_normalize::normalize(&_r));
+            node.peers[i] = _r;
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use crate::i18n::tr;
+use std::io;
+use std::process::Command;
+use std::time::Duration;
+
+pub const TASK_NAME: &str = "Yggdrasil peers updater";
+
+// The source of the messages in the Application event log ('--log-target eventlog')
+pub const EVENT_SOURCE: &str = "PeersUpdater";
+
+// Registers a Scheduled Task running the updater with the given arguments
+// under SYSTEM every `interval`
+pub fn install(interval: Duration, args: &[String]) -> io::Result<()> {
+    let schedule = schedule(interval).ok_or_else(|| {
+        io::Error::other(tr!(
+            "The interval can't be represented as a schedule ({}s).",
+            interval.as_secs()
+        ))
+    })?;
+
+    let exe = std::env::current_exe()?;
+    let task_run: String = std::iter::once(quote(&exe.to_string_lossy()))
+        .chain(args.iter().map(|a| quote(a)))
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    let status = Command::new("schtasks")
+        .args(["/Create", "/F", "/RU", "SYSTEM", "/RL", "HIGHEST"])
+        .args(["/TN", TASK_NAME])
+        .args(["/TR", &task_run])
+        .args(["/SC", schedule.0])
+        .args(["/MO", &schedule.1.to_string()])
+        .status()?;
+
+    match status.success() {
+        true => Ok(()),
+        _ => Err(io::Error::other(format!("schtasks exited with {}", status))),
+    }
+}
+
+// Registers the event log source, so that Event Viewer shows the messages
+// without the "description cannot be found" notice
+pub fn register_event_source() -> io::Result<()> {
+    let script = format!(
+        "if (-not [System.Diagnostics.EventLog]::SourceExists('{0}')) {{ New-EventLog -LogName Application -Source '{0}' }}",
+        EVENT_SOURCE
+    );
+    let status = Command::new("powershell")
+        .args(["-NoProfile", "-Command", &script])
+        .status()?;
+
+    match status.success() {
+        true => Ok(()),
+        _ => Err(io::Error::other(format!(
+            "powershell exited with {}",
+            status
+        ))),
+    }
+}
+
+// The schtasks schedule type and modifier for the interval
+fn schedule(interval: Duration) -> Option<(&'static str, u64)> {
+    let secs = interval.as_secs();
+    match secs {
+        0 => None,
+        _ if secs.is_multiple_of(86400) && secs / 86400 <= 365 => Some(("DAILY", secs / 86400)),
+        _ if secs.is_multiple_of(3600) && secs / 3600 < 24 => Some(("HOURLY", secs / 3600)),
+        _ if secs.is_multiple_of(60) && secs / 60 < 1440 => Some(("MINUTE", secs / 60)),
+        _ => None,
+    }
+}
+
+// Quoting for the task command line (CommandLineToArgvW rules)
+fn quote(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        return arg.to_string();
+    }
+    format!("\"{}\"", arg.replace('"', "\\\""))
+}
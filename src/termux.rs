@@ -0,0 +1,68 @@
+use crate::i18n::tr;
+use crate::logging::log_error;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+const DEF_PREFIX: &str = "/data/data/com.termux/files/usr";
+
+static PREFIX: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+// The Termux prefix ($PREFIX) if running in Termux: there is no systemd and the
+// usual paths (/etc, /var, /tmp) live under the prefix
+pub fn prefix() -> Option<&'static Path> {
+    PREFIX.get_or_init(detect_prefix).as_deref()
+}
+
+fn detect_prefix() -> Option<PathBuf> {
+    if !cfg!(any(target_os = "android", target_os = "linux")) {
+        return None;
+    }
+    let env_prefix = std::env::var_os("PREFIX").filter(|p| !p.is_empty());
+    match env_prefix {
+        Some(_p)
+            if std::env::var_os("TERMUX_VERSION").is_some()
+                || _p.to_string_lossy().contains("com.termux") =>
+        {
+            Some(PathBuf::from(_p))
+        }
+        _ if Path::new(DEF_PREFIX).is_dir() => Some(PathBuf::from(DEF_PREFIX)),
+        _ => None,
+    }
+}
+
+// Restarting Yggdrasil with termux-services (runit), if the service is set up
+#[cfg(not(target_os = "windows"))]
+pub fn restart(prefix: &Path) -> bool {
+    let sv_dir = prefix.join("var/service");
+    let name = crate::service::name();
+    if !sv_dir.join(name).is_dir() {
+        log_error!(
+            "{}",
+            tr!("Termux has no service manager by default, restart Yggdrasil manually or install termux-services and run 'sv-enable {}'.", name)
+        );
+        return false;
+    }
+
+    match Command::new("sv")
+        .env("SVDIR", &sv_dir)
+        .args(["restart", name])
+        .status()
+    {
+        Ok(_s) if _s.success() => true,
+        Ok(_s) => {
+            log_error!(
+                "{}",
+                tr!("Failed to restart the Yggdrasil service ({}).", _s)
+            );
+            false
+        }
+        Err(e) => {
+            log_error!(
+                "{}",
+                tr!("Failed to restart the Yggdrasil service ({}).", e)
+            );
+            false
+        }
+    }
+}
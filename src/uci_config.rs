@@ -0,0 +1,133 @@
+use crate::i18n::tr;
+use crate::logging::log_error;
+use crate::peer::Peer;
+use nu_json::{Map, Value};
+use std::path::Path;
+use std::process::Command;
+
+// OpenWrt keeps the yggdrasil settings in /etc/config/yggdrasil (UCI), each peer
+// being a separate 'peer' section with the 'uri' option
+pub fn update_peers(selected: &[&Peer], extra_peers: &[String], conf_path: &Path) {
+    let (conf_dir, package) = match split_path(conf_path) {
+        Some(_p) => _p,
+        _ => {
+            log_error!(
+                "{}",
+                tr!("Incorrect UCI configuration path. The file was not written to.")
+            );
+            return;
+        }
+    };
+
+    // Removing all the existing peer sections
+    while uci(
+        &conf_dir,
+        &["-q", "delete", &format!("{}.@peer[0]", package)],
+    )
+    .is_some()
+    {}
+
+    let uris = selected
+        .iter()
+        .map(|peer| &peer.uri)
+        .chain(extra_peers.iter());
+    for uri in uris {
+        if uci(&conf_dir, &["add", &package, "peer"]).is_none()
+            || uci(
+                &conf_dir,
+                &["set", &format!("{}.@peer[-1].uri={}", package, uri)],
+            )
+            .is_none()
+        {
+            log_error!(
+                "{}",
+                tr!("Failed to add the peer {} to the UCI configuration.", uri)
+            );
+            let _ = uci(&conf_dir, &["revert", &package]);
+            return;
+        }
+    }
+
+    if uci(&conf_dir, &["commit", &package]).is_none() {
+        log_error!(
+            "{}",
+            tr!("The changes could not be committed to the UCI configuration.")
+        );
+    }
+}
+
+// The options needed for the admin API (AdminListen) in the same form as the HJSON config
+pub fn get_conf_obj(conf_path: &Path) -> Map<String, Value> {
+    let mut conf_obj: Map<String, Value> = Map::new();
+
+    if let Some((conf_dir, package)) = split_path(conf_path) {
+        if let Some(_al) = uci(
+            &conf_dir,
+            &[
+                "-q",
+                "get",
+                &format!("{}.@yggdrasil[0].AdminListen", package),
+            ],
+        ) {
+            conf_obj.insert("AdminListen".to_string(), Value::String(_al));
+        }
+    }
+
+    conf_obj
+}
+
+pub fn get_peers(conf_path: &Path) -> Vec<String> {
+    let mut peers: Vec<String> = Vec::new();
+
+    if let Some((conf_dir, package)) = split_path(conf_path) {
+        while let Some(_uri) = uci(
+            &conf_dir,
+            &[
+                "-q",
+                "get",
+                &format!("{}.@peer[{}].uri", package, peers.len()),
+            ],
+        ) {
+            peers.push(_uri);
+        }
+    }
+
+    peers
+}
+
+pub fn reload_service() -> bool {
+    let error = match Command::new(format!("/etc/init.d/{}", crate::service::name()))
+        .arg("reload")
+        .status()
+    {
+        Ok(_s) if _s.success() => return true,
+        Ok(_s) => _s.to_string(),
+        Err(e) => e.to_string(),
+    };
+    log_error!(
+        "{}",
+        tr!("Failed to reload the Yggdrasil service ({}).", error)
+    );
+    false
+}
+
+fn split_path(conf_path: &Path) -> Option<(String, String)> {
+    let package = conf_path.file_name()?.to_str()?.to_string();
+    let conf_dir = conf_path.parent()?.to_str()?.to_string();
+    Some((conf_dir, package))
+}
+
+// Runs uci with the given arguments and returns its output if it succeeded
+fn uci(conf_dir: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new("uci")
+        .arg("-c")
+        .arg(conf_dir)
+        .args(args)
+        .output()
+        .ok()?;
+
+    match output.status.success() {
+        true => Some(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+        _ => None,
+    }
+}
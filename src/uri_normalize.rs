@@ -0,0 +1,159 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+// Brings a peer URI to a canonical form so that equal URIs written
+// differently (case, default ports, escapes, IPv6 forms) compare equal
+pub fn normalize(uri: &str) -> String {
+    let uri = uri.trim();
+
+    let (scheme, rest) = match uri.split_once("://") {
+        Some((_s, _r)) => (_s.to_lowercase(), _r),
+        _ => return uri.to_string(),
+    };
+
+    let (authority, path_and_query) = match rest.find(['/', '?']) {
+        Some(_p) => rest.split_at(_p),
+        _ => (rest, ""),
+    };
+    let (path, query) = match path_and_query.split_once('?') {
+        Some((_p, _q)) => (_p, Some(_q)),
+        _ => (path_and_query, None),
+    };
+
+    let (host, port) = split_host_port(authority);
+    let host = normalize_host(host);
+    let port = match port {
+        Some(_p) => match _p.parse::<u16>() {
+            Ok(_n) if Some(_n) == default_port(&scheme) => None,
+            Ok(_n) => Some(_n.to_string()),
+            _ => Some(_p.to_string()),
+        },
+        _ => None,
+    };
+
+    let mut normalized = format!("{}://{}", scheme, host);
+    if let Some(_p) = port {
+        normalized.push(':');
+        normalized.push_str(&_p);
+    }
+    if path != "/" {
+        normalized.push_str(&percent_decode_unreserved(path));
+    }
+    if let Some(_q) = query {
+        let mut params: Vec<String> = _q
+            .split('&')
+            .filter(|p| !p.is_empty())
+            .map(percent_decode_unreserved)
+            .collect();
+        params.sort();
+        if !params.is_empty() {
+            normalized.push('?');
+            normalized.push_str(&params.join("&"));
+        }
+    }
+
+    normalized
+}
+
+// Checks whether a space-separated list of URIs contains the URI
+pub fn list_contains(list: &str, uri: &str) -> bool {
+    let uri = normalize(uri);
+    list.split(' ')
+        .filter(|u| !u.is_empty())
+        .any(|u| normalize(u) == uri)
+}
+
+// Splits an IPv6 literal with a zone ID into the address and the zone: in URIs
+// the '%' is written as '%25' (RFC 6874), '[fe80::1%eth0]' is accepted too
+pub fn split_zone(host: &str) -> (&str, Option<&str>) {
+    let addr = host.trim_start_matches('[').trim_end_matches(']');
+    match addr.split_once('%') {
+        Some((_ip, _zone)) => match _zone.strip_prefix("25") {
+            Some(_z) if !_z.is_empty() => (_ip, Some(_z)),
+            _ => (_ip, Some(_zone)),
+        },
+        _ => (addr, None),
+    }
+}
+
+// tcp://[fe80::1%eth0]:1234 -> tcp://[fe80::1%25eth0]:1234, the form Yggdrasil parses
+pub fn encode_zone(uri: &str) -> String {
+    match (uri.find('['), uri.find(']')) {
+        (Some(_s), Some(_e)) if _s < _e => match split_zone(&uri[_s.._e + 1]) {
+            (_ip, Some(_zone)) => format!("{}[{}%25{}]{}", &uri[.._s], _ip, _zone, &uri[_e + 1..]),
+            _ => uri.to_string(),
+        },
+        _ => uri.to_string(),
+    }
+}
+
+fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "ws" | "http" => Some(80),
+        "wss" | "https" => Some(443),
+        _ => None,
+    }
+}
+
+fn split_host_port(authority: &str) -> (&str, Option<&str>) {
+    if authority.starts_with('[') {
+        match authority.find(']') {
+            Some(_e) => {
+                let port = authority[_e + 1..].strip_prefix(':');
+                (&authority[.._e + 1], port)
+            }
+            _ => (authority, None),
+        }
+    } else {
+        match authority.rsplit_once(':') {
+            Some((_h, _p)) => (_h, Some(_p)),
+            _ => (authority, None),
+        }
+    }
+}
+
+fn normalize_host(host: &str) -> String {
+    if host.starts_with('[') && host.ends_with(']') {
+        let (ip, zone) = split_zone(host);
+        return match (ip.parse::<Ipv6Addr>(), zone) {
+            (Ok(_ip), Some(_zone)) => format!("[{}%25{}]", _ip, _zone),
+            (Ok(_ip), _) => format!("[{}]", _ip),
+            _ => host.to_lowercase(),
+        };
+    }
+
+    match host.parse::<Ipv4Addr>() {
+        Ok(_ip) => _ip.to_string(),
+        _ => host.to_lowercase().trim_end_matches('.').to_string(),
+    }
+}
+
+// Decodes only the escapes of unreserved characters (they never change the meaning)
+// and upper-cases the hex digits of the remaining ones
+fn percent_decode_unreserved(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = [bytes[i + 1], bytes[i + 2]];
+            if let Some(_b) = std::str::from_utf8(&hex)
+                .ok()
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+            {
+                if _b.is_ascii_alphanumeric() || b"-._~".contains(&_b) {
+                    out.push(_b);
+                } else {
+                    out.push(b'%');
+                    out.extend(hex.to_ascii_uppercase());
+                }
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
@@ -1,14 +1,48 @@
+use crate::i18n::tr;
+use crate::logging::{log_error, log_info, log_warn};
 use crate::peer::Peer;
 use nu_json::Map;
+use std::io::{self, BufReader, Write};
 use std::net::{SocketAddr, TcpStream};
 #[cfg(not(target_os = "windows"))]
 use std::os::unix::net::UnixStream;
+use std::process::Command;
 use std::time;
 
+// How the admin API is reached: directly through the socket or by running
+// yggdrasilctl (where the socket itself isn't accessible)
+#[derive(Clone)]
+pub enum ApiBackend {
+    Socket,
+    Yggdrasilctl(String),
+}
+
+impl ApiBackend {
+    // 'socket', 'yggdrasilctl' or 'yggdrasilctl:/path/to/yggdrasilctl'
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        match spec.split_once(':') {
+            Some(("yggdrasilctl", _p)) if !_p.is_empty() => {
+                Ok(ApiBackend::Yggdrasilctl(_p.to_string()))
+            }
+            _ if spec == "yggdrasilctl" => Ok(ApiBackend::Yggdrasilctl(spec.to_string())),
+            _ if spec == "socket" => Ok(ApiBackend::Socket),
+            _ => Err(format!("unknown API backend '{}'", spec)),
+        }
+    }
+}
+
+// Connecting to and waiting for an answer of the admin socket
+const TIMEOUT: time::Duration = time::Duration::from_secs(10);
+
+// The selected sessions that must be up before the old peers are removed
+const SESSIONS_BEFORE_BREAK: usize = 3;
+
 enum SockAddr {
     Tcp(SocketAddr),
     #[cfg(not(target_os = "windows"))]
     Unix(String),
+    // The program and the endpoint (AdminListen) for it
+    Ctl(String, Option<String>),
     None,
 }
 
@@ -16,139 +50,428 @@ enum Connection {
     Tcp(TcpStream),
     #[cfg(not(target_os = "windows"))]
     Unix(UnixStream),
-    None,
 }
 
-pub fn update_peers(
-    peers: &Vec<Peer>,
-    conf_obj: &mut Map<String, nu_json::Value>,
-    n_peers: u8,
-    always_in_p: Option<&String>,
-    ignored_peers: Option<&String>,
-) {
-    let socket_addr = get_socket_addr(conf_obj);
+impl io::Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(_s) => _s.read(buf),
+            #[cfg(not(target_os = "windows"))]
+            Connection::Unix(_s) => _s.read(buf),
+        }
+    }
+}
 
-    let mut response = String::new();
+impl io::Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(_s) => _s.write(buf),
+            #[cfg(not(target_os = "windows"))]
+            Connection::Unix(_s) => _s.write(buf),
+        }
+    }
 
-    // Get peers
-    request("{\"request\": \"getpeers\"}", &socket_addr, &mut response);
-    if response.is_empty() {
-        eprintln!("Can't get connected peers.");
-        return;
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Tcp(_s) => _s.flush(),
+            #[cfg(not(target_os = "windows"))]
+            Connection::Unix(_s) => _s.flush(),
+        }
     }
+}
 
-    // Removing old peers
-    remove_peers(&mut response, &socket_addr);
+// One connection to the admin socket for all the calls: the requests ask the
+// node to keep it open ('keepalive'), a node that closes it anyway is
+// connected to again. Every call returns its own error.
+pub struct ApiClient {
+    addr: SockAddr,
+    conn: Option<BufReader<Connection>>,
+}
 
-    // Adding new peers
-    let mut n_added: u8 = 0;
-    for peer in peers {
-        if let Some(ignored_peers_p) = ignored_peers {
-            if ignored_peers_p.contains(&peer.uri) {
-                continue;
+impl ApiClient {
+    pub fn new(conf_obj: &mut Map<String, nu_json::Value>, backend: &ApiBackend) -> Self {
+        ApiClient {
+            addr: api_addr(conf_obj, backend),
+            conn: None,
+        }
+    }
+
+    fn call(&mut self, request: &str, uri: Option<&str>) -> Result<String, String> {
+        let mut req = serde_json::json!({"request": request, "keepalive": true});
+        if let Some(_u) = uri {
+            req["arguments"] = serde_json::json!({ "uri": _u });
+        }
+
+        let response = match &self.addr {
+            SockAddr::Ctl(program, endpoint) => ctl_request(&req, program, endpoint.as_deref())
+                .map_err(|e| tr!("Failed to run {} ({}).", program, e))?,
+            SockAddr::None => return Err(tr!("Unable to connect to the administrator socket.")),
+            _ => self.exchange(&req)?,
+        };
+
+        match response["status"].as_str() {
+            Some("error") => Err(response["error"].as_str().unwrap_or_default().to_string()),
+            _ => Ok(response.to_string()),
+        }
+    }
+
+    fn exchange(&mut self, req: &serde_json::Value) -> Result<serde_json::Value, String> {
+        // The node may have closed the kept connection after the previous answer
+        if let Some(_c) = self.conn.as_mut() {
+            if let Ok(_r) = socket_io(_c, req) {
+                return Ok(_r);
             }
+            self.conn = None;
         }
-        response.clear();
-        request(
-            format!(
-                "{{\"request\": \"addpeer\", \"arguments\": {{\"uri\": \"{}\"}}}}",
-                peer.uri
-            )
-            .as_str(),
-            &socket_addr,
-            &mut response,
+
+        let mut conn = BufReader::new(get_connection(&self.addr)?);
+        let response = socket_io(&mut conn, req).map_err(|e| tr!("Socket I/O error ({}).", e))?;
+        self.conn = Some(conn);
+        Ok(response)
+    }
+}
+
+// Make-before-break: the new peers are added first and the old ones are only
+// removed once a few of the selected sessions are up, so the node is never
+// left without connectivity. If that doesn't happen within `settle`, the old
+// peers stay.
+pub fn update_peers(
+    selected: &[&Peer],
+    extra_peers: &[String],
+    client: &mut ApiClient,
+    settle: time::Duration,
+) {
+    // Get peers
+    let connected = match get_peers(client) {
+        Some(_c) => _c,
+        _ => return,
+    };
+
+    let uris: Vec<&String> = selected
+        .iter()
+        .map(|peer| &peer.uri)
+        .chain(extra_peers.iter())
+        .collect();
+    // '-n 0' would leave the node without peers
+    if uris.is_empty() {
+        log_warn!(
+            "{}",
+            tr!("No peers were selected, the current peers of the node were kept.")
         );
-        n_added += 1;
-        if n_added == n_peers {
-            break;
+        return;
+    }
+    let wanted: Vec<String> = uris
+        .iter()
+        .map(|uri| crate::uri_normalize::normalize(uri))
+        .collect();
+    let connected: Vec<String> = connected
+        .iter()
+        .map(|p| crate::uri_normalize::normalize(&p.uri))
+        .collect();
+
+    // Adding new peers (the ones that stay are not reconnected)
+    for (uri, normalized) in uris.iter().zip(&wanted) {
+        if !connected.contains(normalized) {
+            add_peer(uri, client);
         }
     }
 
-    //Always in
-    if let Some(always_in) = always_in_p {
-        let ai = always_in.split(" ");
-        for ai_s in ai {
-            response.clear();
-            request(
-                format!(
-                    "{{\"request\": \"addpeer\", \"arguments\": {{\"uri\": \"{}\"}}}}",
-                    ai_s
-                )
-                .as_str(),
-                &socket_addr,
-                &mut response,
-            );
+    if connected.iter().all(|uri| wanted.contains(uri)) {
+        return;
+    }
+
+    // Waiting for the new sessions before removing the old peers
+    let needed = SESSIONS_BEFORE_BREAK.min(wanted.len());
+    let deadline = time::Instant::now() + settle;
+    let (established, connected) = loop {
+        let connected = get_peers(client).unwrap_or_default();
+        let established = connected
+            .iter()
+            .filter(|p| p.up && wanted.contains(&crate::uri_normalize::normalize(&p.uri)))
+            .count();
+        if established >= needed || time::Instant::now() >= deadline {
+            break (established, connected);
         }
+        std::thread::sleep(time::Duration::from_secs(1));
+    };
+
+    if established < needed {
+        log_warn!(
+            "{}",
+            tr!(
+                "Only {} of the selected peers are connected after {}s, the old peers were kept.",
+                established,
+                settle.as_secs()
+            )
+        );
+        return;
     }
+
+    // Removing old peers
+    remove_peers(connected, client, &uris);
 }
 
-fn socket_io<T: std::io::Write + std::io::Read>(
-    conn: &mut T,
-    req: &str,
-    resp: &mut String,
-) -> std::io::Result<()> {
-    conn.write_all(req.as_bytes())?;
-    conn.read_to_string(resp)?;
+// Only adds as many peers as needed to have `target` established sessions,
+// nothing is removed
+pub fn top_up_peers(
+    selected: &[&Peer],
+    extra_peers: &[String],
+    client: &mut ApiClient,
+    target: usize,
+) {
+    // Get peers
+    let connected = match get_peers(client) {
+        Some(_c) => _c,
+        _ => return,
+    };
+
+    let established = connected.iter().filter(|p| p.up).count();
+    let known: Vec<String> = connected
+        .iter()
+        .map(|p| crate::uri_normalize::normalize(&p.uri))
+        .collect();
 
-    Ok(())
+    let missing = target.saturating_sub(established);
+    log_info!(
+        "{}",
+        tr!(
+            "{} peers are connected, {} will be added.",
+            established,
+            missing
+        )
+    );
+
+    let uris = extra_peers
+        .iter()
+        .chain(selected.iter().map(|peer| &peer.uri))
+        .filter(|uri| !known.contains(&crate::uri_normalize::normalize(uri)))
+        .take(missing);
+    for uri in uris {
+        add_peer(uri, client);
+    }
 }
 
-fn request(req: &str, socket_addr: &SockAddr, resp: &mut String) {
-    let connection = get_connection(socket_addr);
+// Removes the worst peers until the node has no more than `max` of them:
+// first the ones that are not in the selection, then the slowest selected
+// ones. The extra peers are removed last.
+pub fn enforce_max_peers(
+    selected: &[&Peer],
+    extra_peers: &[String],
+    client: &mut ApiClient,
+    max: usize,
+) {
+    // Get peers
+    let mut connected = match get_peers(client) {
+        Some(_c) => _c,
+        _ => return,
+    };
+    if connected.len() <= max {
+        return;
+    }
 
-    match connection {
-        Connection::Tcp(conn) => {
-            let mut mut_conn = conn;
-            let _ = match socket_io(&mut mut_conn, req, resp) {
-                Ok(_) => {}
-                Err(e) => {
-                    eprintln!("Socket I/O error ({}).", e);
-                }
-            };
-        }
-        #[cfg(not(target_os = "windows"))]
-        Connection::Unix(conn) => {
-            let mut mut_conn = conn;
-            let _ = match socket_io(&mut mut_conn, req, resp) {
-                Ok(_) => {}
-                Err(e) => {
-                    eprintln!("Socket I/O error ({}).", e);
-                }
-            };
-        }
-        Connection::None => {
-            eprintln!("Unable to connect to the administrator socket.");
+    let rank = |uri: &str| {
+        let uri = crate::uri_normalize::normalize(uri);
+        if extra_peers
+            .iter()
+            .any(|ep| crate::uri_normalize::normalize(ep) == uri)
+        {
+            2
+        } else if selected
+            .iter()
+            .any(|peer| crate::uri_normalize::normalize(&peer.uri) == uri)
+        {
+            1
+        } else {
+            0
         }
     };
-}
+    // The sessions that are down or have no latency go first
+    connected.sort_by(|a, b| {
+        rank(&a.uri).cmp(&rank(&b.uri)).then(
+            b.latency
+                .filter(|_| b.up)
+                .unwrap_or(f64::MAX)
+                .total_cmp(&a.latency.filter(|_| a.up).unwrap_or(f64::MAX)),
+        )
+    });
 
-fn remove_peer(peer_uri: &String, socket_addr: &SockAddr, resp: &mut String) {
-    request(
-        format!(
-            "{{\"request\": \"removepeer\", \"arguments\": {{\"uri\": \"{}\"}}}}",
-            peer_uri
+    let excess = connected.len() - max;
+    for peer in connected.iter().take(excess) {
+        remove_peer(&peer.uri, client);
+    }
+    log_info!(
+        "{}",
+        tr!(
+            "{} peers were removed to stay within '--max-peers'.",
+            excess
         )
-        .as_str(),
-        &socket_addr,
-        resp,
     );
 }
 
-fn remove_peers(getpeers_resp: &mut String, socket_addr: &SockAddr) {
+// The number of the established sessions not slower than `max_latency` ms
+// ('--only-if-stale') and the number of all the peers. The latency is reported
+// in nanoseconds; the sessions of the versions that don't report it count as
+// fast.
+pub fn healthy_sessions(client: &mut ApiClient, max_latency: u128) -> Option<(usize, usize)> {
+    get_peers(client).map(|connected| {
+        let healthy = connected
+            .iter()
+            .filter(|p| p.up)
+            .filter(|p| p.latency.is_none_or(|l| l / 1e6 <= max_latency as f64))
+            .count();
+        (healthy, connected.len())
+    })
+}
+
+// '--count-multicast': the healthy peers the node found on the LAN through
+// multicast, i.e. the link-local ones ('[fe80::...%eth0]') on an interface
+// with multicast enabled. None if the node can't be asked.
+pub fn multicast_links(client: &mut ApiClient) -> Option<usize> {
+    // Older versions don't have the request, then any link-local peer counts
+    let interfaces = client
+        .call("getmulticastinterfaces", None)
+        .ok()
+        .map(|r| multicast_interfaces(&r));
+    let connected = get_peers(client)?;
+    let links = connected
+        .iter()
+        .filter(|p| p.up)
+        .filter_map(|p| link_local_zone(&p.uri))
+        .filter(|zone| interfaces.as_ref().is_none_or(|i| i.contains(zone)))
+        .count();
+    Some(links)
+}
+
+// The interface names: strings in older versions, objects with 'name' in newer ones
+fn multicast_interfaces(resp: &str) -> Vec<String> {
+    let resp: serde_json::Value = serde_json::from_str(resp).unwrap_or_default();
+    match resp["response"]["multicast_interfaces"].as_array() {
+        Some(_i) => _i
+            .iter()
+            .filter_map(|i| i.as_str().or_else(|| i["name"].as_str()))
+            .map(|i| i.to_string())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// The zone (interface) of a link-local peer address, empty without one
+fn link_local_zone(uri: &str) -> Option<String> {
+    let host = uri.split_once('[')?.1.split_once(']')?.0;
+    if !host.to_lowercase().starts_with("fe80:") {
+        return None;
+    }
+    // The '%' may be URL-encoded
+    let zone = match host.split_once("%25").or_else(|| host.split_once('%')) {
+        Some((_, _z)) => _z,
+        _ => "",
+    };
+    Some(zone.to_string())
+}
+
+// '--supervise': the normalized URIs of the peers with the latency of their
+// sessions in ms, None if down (0 if the version doesn't report it)
+pub fn sessions(client: &mut ApiClient) -> Option<Vec<(String, Option<u128>)>> {
+    get_peers(client).map(|connected| {
+        connected
+            .iter()
+            .map(|p| {
+                let latency = p.latency.map(|l| (l / 1e6) as u128).unwrap_or(0);
+                (
+                    crate::uri_normalize::normalize(&p.uri),
+                    p.up.then_some(latency),
+                )
+            })
+            .collect()
+    })
+}
+
+// '--supervise': the new peer is added before the old one is removed
+pub fn replace_peer(old_uri: &str, new_uri: &str, client: &mut ApiClient) {
+    add_peer(new_uri, client);
+    remove_peer(old_uri, client);
+}
+
+// Sends the request and reads a single JSON answer, the connection stays open
+fn socket_io(
+    conn: &mut BufReader<Connection>,
+    req: &serde_json::Value,
+) -> io::Result<serde_json::Value> {
+    conn.get_mut().write_all(req.to_string().as_bytes())?;
+    match serde_json::Deserializer::from_reader(conn)
+        .into_iter::<serde_json::Value>()
+        .next()
+    {
+        Some(_r) => Ok(_r?),
+        _ => Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+    }
+}
+
+// The peers of the node, None if the request failed
+fn get_peers(client: &mut ApiClient) -> Option<Vec<ConnectedPeer>> {
+    match client.call("getpeers", None) {
+        Ok(_r) => Some(connected_peers(&_r)),
+        Err(e) => {
+            log_error!("{}", tr!("Can't get connected peers ({}).", e));
+            None
+        }
+    }
+}
+
+fn add_peer(peer_uri: &str, client: &mut ApiClient) {
+    if let Err(e) = client.call("addpeer", Some(peer_uri)) {
+        log_error!(
+            "{}",
+            tr!(
+                "Failed to add the peer {} ({}).",
+                crate::redact::redact(peer_uri),
+                e
+            )
+        );
+    }
+}
+
+fn remove_peer(peer_uri: &str, client: &mut ApiClient) {
+    if let Err(e) = client.call("removepeer", Some(peer_uri)) {
+        log_error!(
+            "{}",
+            tr!(
+                "Failed to remove the peer {} ({}).",
+                crate::redact::redact(peer_uri),
+                e
+            )
+        );
+    }
+}
+
+// A peer from the getpeers response
+struct ConnectedPeer {
+    uri: String,
+    // Older versions list the established sessions only
+    up: bool,
+    latency: Option<f64>,
+}
+
+fn connected_peers(getpeers_resp: &str) -> Vec<ConnectedPeer> {
+    let mut peers: Vec<ConnectedPeer> = Vec::new();
+
     //parse to obj
     let connected_peers: Map<String, nu_json::Value> = match nu_json::from_str(getpeers_resp) {
         Ok(cp) => cp,
         Err(e) => {
-            eprintln!("Error converting a json string to an object ({}).", e);
-            return;
+            log_error!(
+                "{}",
+                tr!("Error converting a json string to an object ({}).", e)
+            );
+            return peers;
         }
     };
 
     let resp = match connected_peers.get("response") {
         Some(_a) => _a,
         _ => {
-            eprintln!("Couldn't get response from the getpeers result.");
-            return;
+            log_error!("{}", tr!("Couldn't get response from the getpeers result."));
+            return peers;
         }
     };
 
@@ -156,21 +479,27 @@ fn remove_peers(getpeers_resp: &mut String, socket_addr: &SockAddr) {
         Some(pv) => match pv.get("peers") {
             Some(_a) => _a,
             _ => {
-                eprintln!("Couldn't get peers from the response obj.");
-                return;
+                log_error!("{}", tr!("Couldn't get peers from the response obj."));
+                return peers;
             }
         },
         _ => {
-            eprintln!("Couldn't get peers from the response obj (0002).");
-            return;
+            log_error!(
+                "{}",
+                tr!("Couldn't get peers from the response obj (0002).")
+            );
+            return peers;
         }
     };
 
     let mp_array = match peers_val.as_array() {
         Some(_mv) => _mv,
         _ => {
-            eprintln!("Couldn't get peers array from the the response obj.");
-            return;
+            log_error!(
+                "{}",
+                tr!("Couldn't get peers array from the the response obj.")
+            );
+            return peers;
         }
     };
 
@@ -178,7 +507,7 @@ fn remove_peers(getpeers_resp: &mut String, socket_addr: &SockAddr) {
         let peer_obj = match peer.as_object() {
             Some(_po) => _po,
             _ => {
-                //eprintln!("Couldn't get peer obj.");
+                //log_error!("Couldn't get peer obj.");
                 continue;
             }
         };
@@ -186,45 +515,109 @@ fn remove_peers(getpeers_resp: &mut String, socket_addr: &SockAddr) {
         let peer_uri = match peer_obj.get("remote") {
             Some(_pu) => _pu.to_string().replace("\"", ""),
             _ => {
-                //eprintln!("Couldn't get peer uri.");
+                //log_error!("Couldn't get peer uri.");
                 continue;
             }
         };
 
-        getpeers_resp.clear();
-        remove_peer(&peer_uri, socket_addr, getpeers_resp);
+        peers.push(ConnectedPeer {
+            uri: peer_uri,
+            up: peer_obj.get("up").and_then(|u| u.as_bool()).unwrap_or(true),
+            latency: peer_obj.get("latency").and_then(|l| l.as_f64()),
+        });
     }
+
+    peers
 }
 
-fn get_connection(sock_addr: &SockAddr) -> Connection {
-    match sock_addr {
-        SockAddr::Tcp(_sa) => {
-            let _ = match TcpStream::connect_timeout(&_sa, time::Duration::from_secs(10)) {
-                Ok(_s) => {
-                    return Connection::Tcp(_s);
-                }
-                Err(e) => {
-                    eprintln!("Failed to connect via TCP stream ({}).", e);
-                    return Connection::None;
-                }
-            };
+// Removes the connected peers that are not in `keep`
+fn remove_peers(connected: Vec<ConnectedPeer>, client: &mut ApiClient, keep: &[&String]) {
+    let keep: Vec<String> = keep
+        .iter()
+        .map(|uri| crate::uri_normalize::normalize(uri))
+        .collect();
+
+    for peer in connected {
+        if !keep.contains(&crate::uri_normalize::normalize(&peer.uri)) {
+            remove_peer(&peer.uri, client);
         }
+    }
+}
+
+fn get_connection(sock_addr: &SockAddr) -> Result<Connection, String> {
+    match sock_addr {
+        SockAddr::Tcp(_sa) => match TcpStream::connect_timeout(_sa, TIMEOUT) {
+            Ok(_s) => {
+                let _ret = _s.set_read_timeout(Some(TIMEOUT));
+                Ok(Connection::Tcp(_s))
+            }
+            Err(e) => Err(tr!("Failed to connect via TCP stream ({}).", e)),
+        },
         #[cfg(not(target_os = "windows"))]
-        SockAddr::Unix(_sa) => {
-            let _ = match UnixStream::connect(_sa) {
-                Ok(_s) => {
-                    return Connection::Unix(_s);
-                }
-                Err(e) => {
-                    eprintln!("Failed to connect via unix domain socket ({}).", e);
-                    return Connection::None;
-                }
-            };
+        SockAddr::Unix(_sa) => match UnixStream::connect(_sa) {
+            Ok(_s) => {
+                let _ret = _s.set_read_timeout(Some(TIMEOUT));
+                Ok(Connection::Unix(_s))
+            }
+            Err(e) => Err(tr!("Failed to connect via unix domain socket ({}).", e)),
+        },
+        SockAddr::Ctl(..) | SockAddr::None => {
+            Err(tr!("Unable to connect to the administrator socket."))
         }
-        SockAddr::None => {
-            return Connection::None;
+    }
+}
+
+fn api_addr(conf_obj: &mut Map<String, nu_json::Value>, backend: &ApiBackend) -> SockAddr {
+    match backend {
+        ApiBackend::Socket => get_socket_addr(conf_obj),
+        ApiBackend::Yggdrasilctl(_p) => SockAddr::Ctl(
+            _p.to_string(),
+            conf_obj.get("AdminListen").map(|al| {
+                let al = format!("{}", al).replace("\"", "");
+                match al.contains("://") {
+                    true => al,
+                    _ if cfg!(target_os = "windows") => format!("tcp://{}", al),
+                    _ => format!("unix://{}", al),
+                }
+            }),
+        ),
+    }
+}
+
+// Runs the request with yggdrasilctl and wraps its JSON output the same way
+// the admin socket answers
+fn ctl_request(
+    req: &serde_json::Value,
+    program: &str,
+    endpoint: Option<&str>,
+) -> io::Result<serde_json::Value> {
+    let mut command = Command::new(program);
+    command.arg("-json");
+    if let Some(_e) = endpoint {
+        command.arg(format!("-endpoint={}", _e));
+    }
+    command.arg(req["request"].as_str().unwrap_or_default());
+    if let Some(_args) = req["arguments"].as_object() {
+        for (key, value) in _args {
+            command.arg(format!("{}={}", key, value.as_str().unwrap_or_default()));
         }
+    }
+
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "{} ({})",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let response = String::from_utf8_lossy(&output.stdout);
+    let response: serde_json::Value = match response.trim() {
+        "" => serde_json::json!({}),
+        _r => serde_json::from_str(_r).map_err(io::Error::other)?,
     };
+    Ok(serde_json::json!({"status": "success", "response": response}))
 }
 
 fn get_socket_addr(conf_obj: &mut Map<String, nu_json::Value>) -> SockAddr {
@@ -235,7 +628,7 @@ fn get_socket_addr(conf_obj: &mut Map<String, nu_json::Value>) -> SockAddr {
 
         _t_sa
     } else {
-        String::from(crate::defaults::DEF_SOCKET_ADDR)
+        String::from(crate::defaults::socket_addr())
     };
 
     if string_addr.contains("unix://") {
@@ -249,8 +642,11 @@ fn get_socket_addr(conf_obj: &mut Map<String, nu_json::Value>) -> SockAddr {
         );
         #[allow(unreachable_code)]
         {
-            eprintln!("It is not possible to use a unix socket in Windows.");
-            return SockAddr::None;
+            log_error!(
+                "{}",
+                tr!("It is not possible to use a unix socket in Windows.")
+            );
+            SockAddr::None
         }
     } else {
         //tcp
@@ -258,7 +654,7 @@ fn get_socket_addr(conf_obj: &mut Map<String, nu_json::Value>) -> SockAddr {
         let uri = match uriparse::URI::try_from(string_addr.as_str()) {
             Ok(_u) => _u,
             Err(e) => {
-                eprintln!("Unable to parse socket URI ({}).", e);
+                log_error!("{}", tr!("Unable to parse socket URI ({}).", e));
                 return SockAddr::None;
             }
         };
@@ -266,7 +662,10 @@ fn get_socket_addr(conf_obj: &mut Map<String, nu_json::Value>) -> SockAddr {
         let host = match uri.host() {
             Some(_h) => _h,
             _ => {
-                eprintln!("Unable to parse socket URI (failed to get host from URI).");
+                log_error!(
+                    "{}",
+                    tr!("Unable to parse socket URI (failed to get host from URI).")
+                );
                 return SockAddr::None;
             }
         };
@@ -274,7 +673,7 @@ fn get_socket_addr(conf_obj: &mut Map<String, nu_json::Value>) -> SockAddr {
         let ip_addr = match crate::resolve::resolve(&host.to_string()) {
             Some(_a) => _a,
             _ => {
-                eprintln!("Failed to resolve host from socket URI.");
+                log_error!("{}", tr!("Failed to resolve host from socket URI."));
                 return SockAddr::None;
             }
         };
@@ -282,21 +681,22 @@ fn get_socket_addr(conf_obj: &mut Map<String, nu_json::Value>) -> SockAddr {
         let port = match uri.port() {
             Some(_p) => _p,
             _ => {
-                eprintln!("Unable to parse socket URI (failed to get port from URI).");
+                log_error!(
+                    "{}",
+                    tr!("Unable to parse socket URI (failed to get port from URI).")
+                );
                 return SockAddr::None;
             }
         };
 
         string_addr = format!("{}:{}", ip_addr, port);
 
-        let _socket_addr = match string_addr.as_str().parse::<SocketAddr>() {
-            Ok(_a) => {
-                return SockAddr::Tcp(_a);
-            }
+        match string_addr.as_str().parse::<SocketAddr>() {
+            Ok(_a) => SockAddr::Tcp(_a),
             _ => {
-                eprintln!("Unknown type of administrative socket address.");
-                return SockAddr::None;
+                log_error!("{}", tr!("Unknown type of administrative socket address."));
+                SockAddr::None
             }
-        };
+        }
     }
 }
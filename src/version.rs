@@ -1 +1 @@
-pub const APP_VERSION: &'static str = "0.0.4";
+pub const APP_VERSION: &str = "0.0.4";